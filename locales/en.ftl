@@ -0,0 +1,359 @@
+# CodePilot's interface text. To translate it, copy this file to
+# <LOCALE_DIR>/<locale>.ftl, translate the values, and set LOCALE=<locale>.
+# Anything left out falls back to English. { $name } is filled in by the app.
+
+## Responses
+
+response-success = Success:
+response-error = Error:
+
+## Help screen
+
+help-input-title = Task input
+# Enter
+help-input-1 = submit the task
+# Shift+Enter
+help-input-2 = insert a newline instead of submitting
+# Up/Down
+help-input-3 = recall previous tasks, or pick a completion
+# ←/→ Home/End
+help-input-4 = move the cursor; Ctrl+←/→ jumps by word
+# Ctrl+W/K/U
+help-input-5 = delete word before / to end / to start
+# @path
+help-input-6 = attach a repo file to the task; Tab completes
+# Esc
+help-input-7 = back to normal mode
+
+help-details-title = Edit detail view
+# j/k PgUp/PgDn
+help-details-1 = browse older and newer edits
+# f
+help-details-2 = filter edits by task or path
+# ←/→
+help-details-3 = scroll the tsc error messages
+# y
+help-details-4 = copy the edit
+# Ctrl+O / Esc
+help-details-5 = close
+
+help-message-title = Message view
+# y
+help-message-1 = copy the message
+# Enter / Esc
+help-message-2 = close
+
+help-confirmation-title = Confirming an edit
+# y / Enter
+help-confirmation-1 = approve the edit
+# e
+help-confirmation-2 = edit the proposed file in $EDITOR first
+# n / Esc
+help-confirmation-3 = decline
+
+help-tasks-title = Tasks panel
+# j/k
+help-tasks-1 = choose a task
+# Enter
+help-tasks-2 = put it in the input box
+# s
+help-tasks-3 = star or unstar it
+# Esc
+help-tasks-4 = close
+
+help-anywhere-title = Anywhere
+# F1
+help-anywhere-1 = this help, for wherever you are
+# Ctrl+O
+help-anywhere-2 = view edit detail
+# Ctrl+T
+help-anywhere-3 = new conversation tab
+# Ctrl+Tab
+help-anywhere-4 = next tab, where the terminal reports it
+# Ctrl+C
+help-anywhere-5 = press twice quickly to exit
+# Mouse
+help-anywhere-6 = wheel scrolls, click selects (Shift+drag to select text)
+
+help-commands-title = Slash commands (type in the input box)
+help-normal-title = Normal mode ({ $keymap } keymap)
+
+## Normal-mode keys, by the action's KEYMAP_<ACTION> name
+
+action-quit = quit
+action-insert = describe a code task
+action-help = toggle this help screen
+action-copy = copy the selected message, or the last result
+action-open = open the selected message in full
+action-select_next = select the next message
+action-select_previous = select the previous message
+action-scroll_up = scroll messages up
+action-scroll_down = scroll messages down
+action-top = jump to the top
+action-bottom = jump to the bottom
+action-search = search messages
+action-next_match = jump to the next match
+action-previous_match = jump to the previous match
+action-next_tab = switch to the next tab
+action-previous_tab = switch to the previous tab
+action-close_tab = close the current tab
+action-toggle_log = toggle the split log pane
+action-next_theme = switch to the next color theme
+action-check_connection = re-check the connection to the LLM provider
+action-filter = show all messages / only the conversation / only tool calls
+action-open_link = open the selected message's link or file (else the latest one)
+action-queries = recent and starred tasks, to run again
+action-command = open the command line
+action-rerun = run the selected task again
+action-edit_rerun = edit the selected task, then run it again
+action-bookmark = bookmark the selected message (again to remove); see /bookmarks
+action-expand = show the selected message's shortened values in full (again to shorten)
+action-cancel = stop the running task
+action-back = close help / clear search or selection / quit
+
+## Slash commands, by name
+
+command-help = show the help screen
+command-clear = clear this tab's messages and session context
+command-model = show or set the model used for the next tasks
+command-mode = show or set the agent mode
+command-dryrun = toggle plan mode: propose edits without writing them
+command-theme = switch color theme
+command-filter = show only matching messages; no arguments shows everything
+command-export = write this tab's tasks, edits, and activity to a file, or a table (csv, tsv)
+command-history = search saved sessions, then reopen a match or run its task again
+command-rename = show or set the name this session is saved under
+command-bookmarks = list bookmarked messages to copy or remove
+command-stats = task counts, failure rate, tools run, and latency, now and across saved sessions
+command-reset-state = delete saved sessions, history, starred tasks, and bookmarks from SAVE_STATE_DIR
+
+## Activity pane
+
+activity-empty = No activity yet — press 'i' and describe a code task.
+activity-empty-filtered = No { $filter } messages yet — /filter all shows everything.
+
+## What to do about a failure
+
+hint-api-key = Run `codepilot auth set` to save a key, or add OPENAI_API_KEY to .env.
+hint-config = Run `codepilot config doctor` to see what's wrong and how to fix it.
+hint-auth = The provider refused the key. Save a new one with `codepilot auth set`, then check it with `codepilot status`.
+hint-model = This provider doesn't serve OPENAI_MODEL. Set one it lists, or try another for now with /model <name>.
+hint-rate-limit = The provider is rate limiting or out of quota. Wait a moment, then run the task again with R.
+hint-unreachable = The provider didn't answer. Check OPENAI_BASE_URL and your network, then `codepilot status`.
+hint-read-only = Edits are refused while READ_ONLY is set or --read-only is passed. Use plan mode to see proposed edits without writing them.
+hint-schema = The reply wasn't in the OpenAI API's format. Check that OPENAI_BASE_URL is an OpenAI-compatible endpoint, usually ending in /v1.
+
+## Title bar, input box, and status bar
+
+title-tagline = JS/TS coding agent
+tab-new = new
+working = Working… { $seconds }s
+working-queued = { $count } queued
+input-hint = Describe a task · Enter to run · Shift+Enter for newline · Esc to cancel
+input-hint-queue = Describe a task · Enter to queue it after the running one · Esc to cancel
+input-hint-normal = Press 'i' to describe a code task
+activity-title = Activity
+log-title = Log
+log-empty = No events yet.
+status-search = SEARCH
+status-search-keys = Enter keep  Esc clear
+status-insert = INSERT
+status-normal = NORMAL
+status-read-only = READ-ONLY
+status-working = working
+status-idle = idle
+status-tokens = tokens { $sent }↑ { $received }↓
+status-help = 'h' help
+connection-checking = checking…
+connection-up = connected
+connection-down = unreachable
+connection-down-retry = unreachable · { $keys } retry
+connection-unknown = not contacted yet
+
+## Help screen frame
+
+help-title = Help · CodePilot, a JS/TS coding agent
+help-here = here
+help-no-match = Nothing matches the filter.
+help-keys = / search · j/k scroll · Esc close
+help-search-keys = Enter done · Esc clear
+
+## Labels in the confirmation and edit detail views
+
+field-task = Task
+field-file = File
+field-kind = Kind
+field-size = Size
+field-time = Time
+field-status = Status
+field-verification = Verification
+field-attempts = Attempts
+field-content = Content
+
+## Confirming an edit
+
+confirm-title = Confirm edit
+confirm-title-destructive = Confirm destructive edit
+confirm-edited = { $kind } (edited by you)
+confirm-size = { $bytes } bytes, { $lines } lines
+confirm-injection = Asking because the model read instructions aimed at it: { $excerpt }
+confirm-keys = y/Enter approve · e edit in $EDITOR · n/Esc decline
+
+## Quitting mid-task
+
+quit-title = Quit?
+quit-running = A task is still running: { $step }.
+quit-revert = Aborting puts back an edit that tsc hasn't checked yet.
+quit-queued = { $count } queued task(s) won't run.
+quit-wait = wait for it to finish, then quit
+quit-abort = abort it and quit now
+quit-keep = keep working
+
+## Popups
+
+tasks-title = Tasks
+tasks-empty = No tasks run yet.
+tasks-keys = j/k choose · Enter edit and run · s star · Esc close
+sessions-title = Resume a session?
+sessions-counts = { $messages } messages · { $tabs } tabs
+sessions-keys = j/k or ↑/↓ choose · Enter resume · n/Esc start fresh
+history-title = { $count } matches for '{ $query }'
+history-keys = j/k or ↑/↓ choose · Enter reopen in a new tab · r run the task again · Esc close
+bookmarks-title = Bookmarks
+bookmarks-keys = j/k or ↑/↓ choose · y copy · d remove · Esc close
+stats-title = Usage
+stats-run = This run
+stats-saved = All saved sessions, with this run
+stats-unsaved = Set SAVE_STATE_DIR to keep sessions and see totals across them.
+stats-keys = any key to close
+stats-totals = { $sessions } session(s) · { $tasks } task(s), { $finished } finished, { $failed } failed
+stats-agents = Agents: { $counts }
+stats-tools = Tools: { $counts }
+stats-latency = Latency: p50 { $p50 }s · p90 { $p90 }s · p99 { $p99 }s
+stats-latency-none = Latency: no finished tasks yet
+stats-none = none
+setup-title = Setup
+setup-field-key = API key
+setup-field-url = Base URL
+setup-field-model = Model
+setup-intro = No OPENAI_API_KEY is set. Enter the provider details below; they're tested before being saved to .env.
+setup-checking = Testing the connection…
+setup-keys = Tab/↑/↓ move · Enter next, test and save on the last field · Esc skip
+message-title = Message
+message-keys = y copy · Enter or Esc to close
+
+## Edit detail view
+
+details-title = Last Edit
+details-match = { $count } match
+details-matches = { $count } matches
+details-filter = filter: { $filter } · Enter keep · Esc clear
+details-keys = y copy · Ctrl+O or Esc to close
+details-keys-browse = j/k or PageUp/Dn to browse · f filter · ←/→ scroll errors · y copy · Ctrl+O or Esc to close
+details-planned = Planned (not written)
+details-applied = Applied
+details-rejected = Rejected
+details-size = { $bytes } bytes
+details-tsc-error = tsc: failed - { $count } error
+details-tsc-errors = tsc: failed - { $count } errors
+details-no-match = No edits match the filter.
+details-empty = No edits yet — run a task and its full file path + content will show up here.
+diagnostic-line = Line
+diagnostic-column = Col
+diagnostic-code = Code
+diagnostic-message = Message
+
+## Activity messages
+
+startup-unknown-theme = Unknown THEME '{ $name }', using dark
+startup-theme-overrides = Ignored theme overrides: { $names }
+startup-unknown-keymap = Unknown KEYMAP '{ $name }', using default
+startup-keymap-overrides = Ignored keymap overrides: { $names }
+quit-exiting = Exiting...
+quit-ctrl-c = Press Ctrl+C again within 2 seconds to exit
+quit-when-idle = Quitting once the running task finishes.
+quit-when-idle-dropped = Quitting once the running task finishes; dropped { $count } queued task(s).
+rerun-select = Select a task you ran (j/k) to run it again
+rerun-edit-select = Select a task you ran (j/k) to edit and run it again
+cancel-running = Cancelling "{ $task }"
+cancel-idle = No task is running
+bookmark-added = Bookmarked; /bookmarks lists them
+bookmark-removed = Removed the bookmark
+bookmark-select = Select a message (j/k) to bookmark it
+bookmarks-key = the bookmark key
+bookmarks-none = No bookmarks yet: select a message and press { $key }
+expand-nothing = The selected message has nothing cut short to expand
+tab-close-running = This tab has a task running; wait for it to finish before closing
+tab-close-last = Can't close the last tab
+open-done = Opened { $target }
+open-failed = Could not open { $target }: { $error }
+open-nothing = No link or file to open in that message
+theme-show = Theme: { $name }
+theme-unknown = Unknown theme '{ $name }' (try { $themes })
+model-show = Model: { $model } @ { $provider }
+model-set = Model set to { $model } for the next tasks
+mode-show = Mode: { $mode }
+mode-unknown = Unknown mode '{ $mode }' (try ask, agent, plan)
+filter-invalid = Can't filter on that: { $error } (try kind=tool, agent=tsc, conversation, tools, all)
+export-table = Exported the table to { $path }
+export-tab = Exported this tab to { $path }
+export-failed = Could not export: { $error }
+session-untitled = untitled
+session-resumed = Resumed session: { $title }
+rename-show = This session is '{ $title }'; /rename <name> changes it
+rename-done = Renamed this session to '{ $title }'
+rename-unsaved = Renamed this session to '{ $title }' (not saved: SAVE_STATE_DIR is not set)
+history-unsaved = Nothing to search: SAVE_STATE_DIR is not set, so no sessions are saved
+history-usage = Usage: /history <text>
+history-none = No saved messages mention '{ $query }'
+history-unreadable = Could not read { $path }
+reset-unsaved = Nothing to reset: SAVE_STATE_DIR is not set, so nothing is saved
+reset-done = Deleted { $count } saved file(s) from { $dir }. This run is saved again after its next task
+reset-failed = Could not reset { $dir }: { $error }
+reset-confirm = This deletes the saved sessions, edit history, input history, starred tasks, and bookmarks in { $dir }. Type /reset-state confirm to go ahead
+unknown-command = Unknown command /{ $command } - try /help
+copy-nothing = Nothing to copy yet ({ $what })
+copy-done = Copied { $what } to the clipboard
+copy-done-osc52 = Copied { $what } to the clipboard (via terminal OSC 52)
+copy-failed = Could not copy { $what }: { $error }
+save-input-failed = Could not save input history: { $error }
+save-favorites-failed = Could not save favorites: { $error }
+save-bookmarks-failed = Could not save bookmarks: { $error }
+save-history-failed = Could not save history: { $error }
+task-queued = Queued task: { $task } ({ $ahead } ahead)
+connection-ok = Connected: { $model } @ { $provider } is available.
+connection-failed = Connection check failed: { $error }
+setup-skipped = Setup skipped - tasks will fail until OPENAI_API_KEY is set in .env.
+setup-saved = Connected to { $model } @ { $provider }. Settings saved to .env.
+setup-save-failed = Connected, but could not save .env: { $error }
+confirm-declined = Declined { $kind } edit to { $path }
+confirm-approved = Approved { $kind } edit to { $path }
+confirm-approved-edited = Approved (edited) { $kind } edit to { $path }
+editor-edited = Edited the proposed content; approve to write it
+editor-unchanged = Editor closed without changes
+editor-unreadable = Could not read back the edited file: { $error }
+editor-failed = Editor exited with { $status }; keeping the proposed content
+editor-missing = Could not start editor '{ $program }': { $error }
+
+## Task results
+
+result-attempts = after { $count } attempts
+result-planned = Planned edit to { $path } ({ $bytes } bytes) — nothing written; Ctrl+O to review
+result-applied = Wrote { $path } ({ $bytes } bytes)
+result-rejected = Rejected edit to { $path }
+
+## Task progress: the spinner step, then the activity line
+
+step-planning = planning
+step-reading = reading { $paths }
+step-writing = writing { $path }
+step-confirming = waiting for confirmation
+step-gate = running { $gate }
+step-gate-retry = running { $gate } (attempt { $attempt })
+step-fixing = planning a fix
+event-reading = Reading { $paths }
+event-injection = Warning: { $source } has text addressed to the model ({ $excerpt }); edits in this task will ask first
+event-proposed = Proposed edit to { $path }
+event-proposed-retry = Proposed edit to { $path } (attempt { $attempt })
+event-gate = Working: running { $gate }
+event-retrying = Verification failed, edit reverted; retrying (attempt { $attempt } of { $max })
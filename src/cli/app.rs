@@ -1,71 +1,206 @@
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, Clear, ClearType},
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use tokio::sync::{broadcast, mpsc, oneshot};
 
-use crate::cli::{persistence, state::AppState, ui};
-use crate::config::Config;
-use crate::orchestrator::CodeTaskOrchestrator;
+use crate::cli::{
+    clipboard::{Clipboard, CopyMethod},
+    encryption, export, links,
+    message::{Message, MessageFilter, MessageKind},
+    persistence,
+    state::{AppState, PendingConfirmation, HistorySearch, QueuedTask, SessionPicker, StatsView},
+    stats::Stats,
+    logging,
+    tasks::{Task, TaskManager},
+    terminal::{self, TerminalGuard},
+    commands,
+    keymap::{Action, Keymap},
+    setup::{self, SetupWizard},
+    theme::Theme,
+    ui,
+};
+use crate::config::{AgentMode, Config};
+use crate::events::OrchestratorEvent;
+use crate::orchestrator::{
+    CodeTaskOrchestrator, ConfirmationRequest, ConfirmationResponse, TaskResult,
+};
+use crate::error::CodepilotError;
 use crate::formatter::ResponseFormatter;
+use crate::i18n;
+use crate::redact;
+
+/// Upper bound on repo files indexed for `@path` completion.
+const MAX_COMPLETION_FILES: usize = 5000;
+
+/// How long the event loop waits for a key before checking on the running task.
+const TICK: Duration = Duration::from_millis(100);
+
+/// From unlocking the saved state (or where that would be) to the first
+/// frame. Past it, the startup is logged as a warning with how long it took.
+const STARTUP_BUDGET: Duration = Duration::from_secs(1);
+
+/// A task running in the background, plus the channels it reports progress and
+/// asks for approval on.
+struct RunningTask {
+    task: String,
+    /// The conversation tab it was started from, where its output goes.
+    tab_id: u64,
+    rerun_of: Option<chrono::DateTime<chrono::Utc>>,
+    handle: Task<Result<TaskResult>>,
+    confirmations: mpsc::UnboundedReceiver<ConfirmationRequest>,
+    events: broadcast::Receiver<OrchestratorEvent>,
+}
 
 pub struct App {
     pub state: AppState,
     pub config: Config,
     pub should_quit: bool,
     pub last_ctrl_c: Option<Instant>,
+    /// Owns the running task, connection checks, and naming, so quitting
+    /// stops all of them.
+    tasks: TaskManager,
+    /// Log-pane lines from the `tracing` subscriber, drained each tick.
+    log_lines: mpsc::UnboundedReceiver<String>,
+    running: Option<RunningTask>,
+    /// Answers the confirmation currently shown in the modal, if any.
+    confirm_responder: Option<oneshot::Sender<ConfirmationResponse>>,
+    clipboard: Clipboard,
+    /// The mode `/dryrun` goes back to when toggled off.
+    previous_agent_mode: AgentMode,
+    /// A connection check started with `r`, polled each tick like a task.
+    connection_check: Option<Task<Result<()>>>,
+    /// This run's session file under `SAVE_STATE_DIR`, or the resumed one's.
+    session_path: Option<std::path::PathBuf>,
+    /// What the session is saved as: set with `/rename`, or by the LLM from the
+    /// first task. `None` falls back to the first tab's title.
+    session_name: Option<String>,
+    /// The LLM naming the session, polled each tick like a connection check.
+    naming: Option<Task<Result<String>>>,
+    /// The repo walk for `@path` completion, which the first frame doesn't wait on.
+    listing: Option<Task<Vec<String>>>,
+    /// When startup was past the passphrase prompt, until the first frame is drawn.
+    started: Option<Instant>,
+    /// Naming is tried once per session, whether or not it works.
+    asked_for_name: bool,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
+    /// `read_only` is `--read-only`, on top of whatever `READ_ONLY` says.
+    pub fn new(read_only: bool) -> Result<Self> {
         let mut state = AppState::new();
-        let config = Config::from_env()?;
+        let mut config = Config::from_env()?;
+        config.read_only |= read_only;
+        // Anything but the API key stops here, printed where it can be read;
+        // the key is what the setup form below asks for.
+        config.validate_settings()?;
+        // Before the alternate screen, so a passphrase prompt shows normally.
+        encryption::setup(&config)?;
+        // After the passphrase, which is the user's time, not ours.
+        let started = Instant::now();
+        i18n::init(&config)?;
+        redact::init(&config)?;
+        let (log_tx, log_lines) = mpsc::unbounded_channel();
+        logging::init(&config, Some(log_tx))?;
         state.target_repo_path = config.target_repo_path.clone();
+        state.agent_mode = config.agent_mode;
+        state.read_only = config.read_only;
+        state.model = config.model.clone();
+        state.provider = provider_host(config.openai_base_url.as_deref());
+        let base_theme = Theme::named(&config.theme);
+        let (theme, rejected) = base_theme
+            .unwrap_or_default()
+            .with_overrides(&config.theme_overrides);
+        state.theme = theme;
+        if base_theme.is_none() {
+            state.add_message(i18n::tr_args("startup-unknown-theme", &[("name", &config.theme)]));
+        }
+        if !rejected.is_empty() {
+            state.add_message(i18n::tr_args(
+                "startup-theme-overrides",
+                &[("names", &rejected.join(", "))],
+            ));
+        }
+        let base_keymap = Keymap::named(&config.keymap);
+        let (keymap, rejected) = base_keymap
+            .clone()
+            .unwrap_or_default()
+            .with_overrides(&config.keymap_overrides);
+        state.keymap = keymap;
+        if base_keymap.is_none() {
+            state.add_message(i18n::tr_args("startup-unknown-keymap", &[("name", &config.keymap)]));
+        }
+        if !rejected.is_empty() {
+            state.add_message(i18n::tr_args(
+                "startup-keymap-overrides",
+                &[("names", &rejected.join(", "))],
+            ));
+        }
+        if config.validate().is_err() {
+            state.setup = Some(SetupWizard::new(&config));
+        }
 
         if let Some(save_state_dir) = &config.save_state_dir {
             state.edit_history = persistence::load_entries(save_state_dir);
+            state.input_history = persistence::load_inputs(save_state_dir);
+            state.favorites = persistence::load_favorites(save_state_dir);
+            state.bookmarks = persistence::load_bookmarks(save_state_dir);
             state.detail_cursor = state.edit_history.len().saturating_sub(1);
+            let sessions = persistence::load_sessions(save_state_dir);
+            if !sessions.is_empty() {
+                state.session_picker = Some(SessionPicker { sessions, cursor: 0 });
+            }
         }
+        let session_path = config
+            .save_state_dir
+            .as_deref()
+            .map(|dir| persistence::session_path(dir, chrono::Utc::now()));
 
         Ok(Self {
             state,
+            previous_agent_mode: config.agent_mode,
             config,
             should_quit: false,
             last_ctrl_c: None,
+            tasks: TaskManager::new(),
+            log_lines,
+            running: None,
+            confirm_responder: None,
+            clipboard: Clipboard::new(),
+            connection_check: None,
+            session_path,
+            session_name: None,
+            naming: None,
+            asked_for_name: false,
+            listing: None,
+            started: Some(started),
         })
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        // Clear screen and enter alternate screen
-        let mut stdout = io::stdout();
-        execute!(stdout, Clear(ClearType::All), EnterAlternateScreen)?;
-
-        // Enable raw mode
-        enable_raw_mode()?;
-
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+        // An encrypted state dir gets nothing in the clear, crash reports
+        // included; they go to the temp dir instead.
+        let crash_dir = self.config.save_state_dir.clone().filter(|_| !self.config.encrypt_state);
+        terminal::install_panic_hook(crash_dir);
+        let guard = TerminalGuard::enter().map_err(CodepilotError::Ui)?;
+        let mut terminal =
+            Terminal::new(CrosstermBackend::new(io::stdout())).map_err(CodepilotError::Ui)?;
 
         let res = self.run_app(&mut terminal).await;
-
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            Clear(ClearType::All)
-        )?;
-        terminal.show_cursor()?;
-
-        if let Err(err) = res {
-            eprintln!("Error: {:?}", err);
-        }
-
-        Ok(())
+        self.tasks.shutdown();
+        self.save_session();
+        drop(guard);
+        // Printed by main with the others, hint and exit code included.
+        res
     }
 
     async fn run_app<B: ratatui::backend::Backend>(
@@ -76,11 +211,63 @@ impl App {
             "Ready. Target repo: {}",
             self.config.target_repo_path
         ));
+        self.start_listing();
 
         loop {
-            terminal.draw(|f| ui::render(f, &self.state))?;
+            self.poll_running_task().await;
+            if self.running.is_none() && !self.state.task_queue.is_empty() {
+                self.start_next_queued().await;
+            }
+            if self.state.quit_when_idle && self.running.is_none() {
+                return Ok(());
+            }
+            self.poll_connection_check().await;
+            self.poll_naming().await;
+            self.poll_listing().await;
+            while let Ok(line) = self.log_lines.try_recv() {
+                self.state.push_log(line);
+            }
+            let size = terminal.size().map_err(CodepilotError::Ui)?;
+            self.fit_to_terminal(ratatui::layout::Rect::new(0, 0, size.width, size.height));
+            let frame = terminal.draw(|f| ui::render(f, &self.state)).map_err(CodepilotError::Ui)?;
+            if let Some(started) = self.started.take() {
+                let elapsed_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+                if started.elapsed() > STARTUP_BUDGET {
+                    tracing::warn!(
+                        elapsed_ms,
+                        budget_ms = STARTUP_BUDGET.as_millis() as u64,
+                        "startup_over_budget"
+                    );
+                } else {
+                    tracing::info!(elapsed_ms, "first_frame");
+                }
+            }
+            if self.config.hyperlinks {
+                write_hyperlinks(&self.state, frame.buffer)?;
+            }
 
-            if let Event::Key(key) = event::read()? {
+            // Poll instead of blocking on read so a task running in the background
+            // can surface confirmations and results between key presses.
+            if !event::poll(TICK).map_err(CodepilotError::Ui)? {
+                continue;
+            }
+
+            let event = event::read().map_err(CodepilotError::Ui)?;
+            if let Event::Resize(..) = event {
+                // Re-wrapped to the new size at the top of the loop, then redrawn.
+                continue;
+            }
+            if let Event::Paste(text) = &event {
+                self.handle_paste(text);
+                continue;
+            }
+            if let Event::Mouse(mouse) = event {
+                let size = terminal.size().map_err(CodepilotError::Ui)?;
+                self.handle_mouse(mouse, ratatui::layout::Rect::new(0, 0, size.width, size.height));
+                continue;
+            }
+
+            if let Event::Key(key) = event {
                 // crossterm on Windows reports both press and release for a single
                 // key tap; only act on press, or every key would fire twice.
                 if key.kind != KeyEventKind::Press {
@@ -90,17 +277,62 @@ impl App {
                 // Handle Ctrl+C for graceful exit
                 if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
                     let now = Instant::now();
-                    if let Some(last_ctrl_c) = self.last_ctrl_c {
-                        if now.duration_since(last_ctrl_c).as_secs() < 2 {
-                            self.state.add_message("Exiting...".to_string());
-                            return Ok(());
-                        }
+                    if let Some(last_ctrl_c) = self.last_ctrl_c
+                        && now.duration_since(last_ctrl_c).as_secs() < 2
+                        && self.request_quit()
+                    {
+                        self.state.add_message(i18n::tr("quit-exiting"));
+                        return Ok(());
                     }
                     self.last_ctrl_c = Some(now);
-                    self.state.add_message("Press Ctrl+C again within 2 seconds to exit".to_string());
+                    self.state.add_message(i18n::tr("quit-ctrl-c"));
+                } else if self.state.quit_prompt {
+                    if self.handle_quit_prompt_key(key) {
+                        return Ok(());
+                    }
+                } else if self.state.show_help {
+                    self.handle_help_key(key);
+                } else if key.code == KeyCode::F(1)
+                    && self.state.setup.is_none()
+                    && self.state.session_picker.is_none()
+                {
+                    self.state.open_help();
+                } else if self.state.setup.is_some() {
+                    self.handle_setup_key(key);
+                } else if self.state.session_picker.is_some() {
+                    self.handle_session_picker_key(key);
+                } else if self.state.history_search.is_some() {
+                    self.handle_history_search_key(key);
+                } else if self.state.bookmark_cursor.is_some() {
+                    self.handle_bookmarks_key(key);
+                } else if self.state.stats_view.is_some() {
+                    // Read-only; any key closes it.
+                    self.state.stats_view = None;
+                } else if self.state.pending_confirmation.is_some() {
+                    // The confirmation modal swallows every other key until answered.
+                    match key.code {
+                        KeyCode::Char('y') | KeyCode::Enter => self.answer_confirmation(true),
+                        KeyCode::Char('n') | KeyCode::Esc => self.answer_confirmation(false),
+                        KeyCode::Char('e') => {
+                            self.edit_pending_confirmation()?;
+                            terminal.clear()?;
+                        }
+                        _ => {}
+                    }
                 } else if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('o') {
                     // Ctrl+O toggles the detail view for the most recent edit, from either mode.
                     self.state.show_details = !self.state.show_details;
+                } else if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && key.code == KeyCode::Char('t')
+                {
+                    self.state.new_tab();
+                    self.update_messages_display();
+                } else if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Tab
+                {
+                    // Only reaches us on terminals that report it; Tab does the
+                    // same in normal mode.
+                    self.state.next_tab();
+                    self.update_messages_display();
                 } else if self.state.is_input_mode {
                     // Input mode - only handle typing and basic input controls
                     match key.code {
@@ -108,122 +340,1510 @@ impl App {
                             self.state.is_input_mode = false;
                         }
                         KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                            self.state.input_text.push('\n');
-                            self.state.cursor_position = self.state.input_text.len();
+                            self.state.insert_char('\n');
+                        }
+                        KeyCode::Enter if commands::parse(&self.state.input_text).is_some() => {
+                            self.state.is_input_mode = false;
+                            let input = self.state.input_text.trim().to_string();
+                            self.state.set_input(String::new());
+                            self.state.record_input(&input);
+                            self.run_slash_command(&input);
+                        }
+                        KeyCode::Enter if !self.state.input_text.is_empty() => {
+                            self.state.is_input_mode = false;
+                            self.submit_task().await;
+                        }
+                        KeyCode::Tab => {
+                            self.state.accept_completion();
+                        }
+                        KeyCode::Down if !self.state.completions().is_empty() => {
+                            self.state.move_completion(true);
+                        }
+                        KeyCode::Up if !self.state.completions().is_empty() => {
+                            self.state.move_completion(false);
+                        }
+                        KeyCode::Up => {
+                            self.state.history_previous();
+                        }
+                        KeyCode::Down => {
+                            self.state.history_next();
+                        }
+                        KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.state.move_cursor_word_left();
+                        }
+                        KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.state.move_cursor_word_right();
+                        }
+                        KeyCode::Left => {
+                            self.state.move_cursor_left();
+                        }
+                        KeyCode::Right => {
+                            self.state.move_cursor_right();
+                        }
+                        KeyCode::Home => {
+                            self.state.move_cursor_line_start();
+                        }
+                        KeyCode::End => {
+                            self.state.move_cursor_line_end();
+                        }
+                        KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.state.delete_word_before_cursor();
+                        }
+                        KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.state.delete_to_end();
+                        }
+                        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.state.delete_to_start();
+                        }
+                        KeyCode::Char(c) => {
+                            self.state.insert_char(c);
+                        }
+                        KeyCode::Backspace
+                            if key
+                                .modifiers
+                                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                        {
+                            self.state.delete_word_before_cursor();
+                        }
+                        KeyCode::Backspace => {
+                            self.state.delete_before_cursor();
+                        }
+                        KeyCode::Delete => {
+                            self.state.delete_at_cursor();
+                        }
+                        _ => {}
+                    }
+                } else if self.state.is_command_mode {
+                    match key.code {
+                        KeyCode::Esc => {
+                            self.state.is_command_mode = false;
                         }
                         KeyCode::Enter => {
-                            if !self.state.input_text.is_empty() {
-                                self.state.is_input_mode = false;
-                                self.state.is_processing = true;
-                                // Redraw now so "Working..." actually shows before the
-                                // blocking LLM call below, instead of freezing on the
-                                // last frame until it returns.
-                                terminal.draw(|f| ui::render(f, &self.state))?;
-                                self.process_user_input().await;
+                            self.state.is_command_mode = false;
+                            let line = std::mem::take(&mut self.state.command_text);
+                            if self.run_command(&line) && self.request_quit() {
+                                return Ok(());
                             }
                         }
+                        // Backspacing past the ':' leaves the command line, as in vim.
+                        KeyCode::Backspace if self.state.command_text.pop().is_none() => {
+                            self.state.is_command_mode = false;
+                        }
                         KeyCode::Char(c) => {
-                            self.state.input_text.push(c);
-                            self.state.cursor_position = self.state.input_text.len();
+                            self.state.command_text.push(c);
+                        }
+                        _ => {}
+                    }
+                } else if self.state.is_search_mode {
+                    // Typing a search query; matches update as you type.
+                    match key.code {
+                        KeyCode::Esc => {
+                            self.state.clear_search();
+                        }
+                        KeyCode::Enter => {
+                            self.state.is_search_mode = false;
                         }
                         KeyCode::Backspace => {
-                            if !self.state.input_text.is_empty() {
-                                self.state.input_text.pop();
-                                self.state.cursor_position = self.state.cursor_position.saturating_sub(1);
-                            }
+                            self.state.search_query.pop();
+                            self.state.update_search();
+                        }
+                        KeyCode::Char(c) => {
+                            self.state.search_query.push(c);
+                            self.state.update_search();
+                        }
+                        _ => {}
+                    }
+                } else if self.state.show_queries {
+                    self.handle_queries_key(key);
+                } else if self.state.show_message {
+                    // Full-text view of the selected message.
+                    match key.code {
+                        KeyCode::Esc | KeyCode::Enter => {
+                            self.state.show_message = false;
+                        }
+                        KeyCode::Char('y') => {
+                            let text = self.state.selected_message_text();
+                            self.copy_to_clipboard(text, "message");
+                        }
+                        _ => {}
+                    }
+                } else if self.state.show_details && self.state.is_detail_filter_mode {
+                    // Typing a filter over the edit history.
+                    match key.code {
+                        KeyCode::Esc => {
+                            self.state.clear_detail_filter();
+                        }
+                        KeyCode::Enter => {
+                            self.state.is_detail_filter_mode = false;
+                        }
+                        KeyCode::Backspace => {
+                            self.state.detail_filter.pop();
+                            self.state.update_detail_filter();
+                        }
+                        KeyCode::Char(c) => {
+                            self.state.detail_filter.push(c);
+                            self.state.update_detail_filter();
                         }
                         _ => {}
                     }
                 } else if self.state.show_details {
                     // Detail overlay - closing and paging through edit history.
                     match key.code {
+                        KeyCode::Esc if !self.state.detail_filter.is_empty() => {
+                            self.state.clear_detail_filter();
+                        }
                         KeyCode::Esc => {
                             self.state.show_details = false;
                         }
+                        KeyCode::Char('f') => {
+                            self.state.is_detail_filter_mode = true;
+                        }
+                        KeyCode::Left => {
+                            self.state.detail_table_scroll =
+                                self.state.detail_table_scroll.saturating_sub(8);
+                        }
+                        KeyCode::Right => {
+                            self.state.detail_table_scroll += 8;
+                        }
                         KeyCode::PageUp | KeyCode::Char('k') => {
                             self.state.show_older_detail();
                         }
                         KeyCode::PageDown | KeyCode::Char('j') => {
                             self.state.show_newer_detail();
                         }
+                        KeyCode::Char('y') => {
+                            let content = self
+                                .state
+                                .edit_history
+                                .get(self.state.detail_cursor)
+                                .map(|detail| detail.content.clone());
+                            self.copy_to_clipboard(content, "edit content");
+                        }
                         _ => {}
                     }
                 } else {
-                    // Navigation mode
-                    match key.code {
-                        KeyCode::Char('q') => {
-                            return Ok(());
-                        }
-                        KeyCode::Char('i') => {
-                            self.state.is_input_mode = true;
-                        }
-                        KeyCode::Char('h') => {
-                            self.state.show_help = !self.state.show_help;
-                        }
-                        KeyCode::Esc => {
-                            if self.state.show_help {
-                                self.state.show_help = false;
+                    // Navigation mode: keys go through the configured keymap.
+                    match self.state.keymap.action_for(&key) {
+                        // Starting a task is async, so this can't go through `perform`.
+                        Some(Action::Rerun) => {
+                            if self.state.stage_rerun() {
+                                self.submit_task().await;
                             } else {
-                                return Ok(());
+                                self.state.add_message(i18n::tr("rerun-select"));
+                                self.update_messages_display();
                             }
                         }
-                        KeyCode::PageUp => {
-                            self.state.scroll_messages_up();
-                        }
-                        KeyCode::PageDown => {
-                            self.state.scroll_messages_down();
-                        }
-                        KeyCode::Char('j') => {
-                            self.state.scroll_messages_down();
+                        Some(action) if self.perform(action) && self.request_quit() => {
+                            return Ok(());
                         }
-                        KeyCode::Char('k') => {
-                            self.state.scroll_messages_up();
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Carry out a normal-mode action. Returns `true` if the app should quit.
+    fn perform(&mut self, action: Action) -> bool {
+        match action {
+            Action::Quit => return true,
+            Action::Insert => {
+                self.state.is_input_mode = true;
+            }
+            Action::ToggleHelp => {
+                self.state.open_help();
+            }
+            Action::Copy => match self.state.selected_message_text() {
+                Some(text) => {
+                    self.copy_to_clipboard(Some(text), "message");
+                }
+                None => self.copy_to_clipboard(self.state.last_response.clone(), "last result"),
+            },
+            Action::OpenMessage => {
+                if self.state.selected_message.is_some() {
+                    self.state.show_message = true;
+                }
+            }
+            // Handled in the event loop.
+            Action::Rerun => {}
+            Action::Cancel => {
+                match &self.running {
+                    // The result comes back as an error on the next tick.
+                    Some(running) => {
+                        running.handle.cancel();
+                        self.state.add_message(i18n::tr_args(
+                            "cancel-running",
+                            &[("task", &running.task)],
+                        ));
+                    }
+                    None => self.state.add_message(i18n::tr("cancel-idle")),
+                }
+                self.update_messages_display();
+            }
+            Action::Bookmark => {
+                match self.state.toggle_bookmark() {
+                    Some(true) => self.state.add_message(i18n::tr("bookmark-added")),
+                    Some(false) => self.state.add_message(i18n::tr("bookmark-removed")),
+                    None => self.state.add_message(i18n::tr("bookmark-select")),
+                }
+                self.save_bookmarks();
+                self.update_messages_display();
+            }
+            Action::Expand => {
+                let formatter = ResponseFormatter::fitted(
+                    &self.config,
+                    self.state.messages_width.saturating_sub(4),
+                );
+                if self.state.toggle_expanded(&formatter).is_none() {
+                    self.state.add_message(i18n::tr("expand-nothing"));
+                }
+                self.update_messages_display();
+            }
+            Action::EditRerun => {
+                if self.state.stage_rerun() {
+                    self.state.is_input_mode = true;
+                } else {
+                    self.state.add_message(i18n::tr("rerun-edit-select"));
+                    self.update_messages_display();
+                }
+            }
+            Action::NextTab => {
+                self.state.next_tab();
+                self.update_messages_display();
+            }
+            Action::PreviousTab => {
+                self.state.previous_tab();
+                self.update_messages_display();
+            }
+            Action::CloseTab => {
+                let running_here = self
+                    .running
+                    .as_ref()
+                    .is_some_and(|running| running.tab_id == self.state.active_tab_id());
+                if running_here {
+                    self.state.add_message(i18n::tr("tab-close-running"));
+                } else if !self.state.close_active_tab() {
+                    self.state.add_message(i18n::tr("tab-close-last"));
+                }
+                self.update_messages_display();
+            }
+            Action::ToggleLog => {
+                self.state.show_log_pane = !self.state.show_log_pane;
+            }
+            Action::NextTheme => {
+                self.set_theme(self.state.theme.next());
+            }
+            Action::CheckConnection => self.start_connection_check(),
+            Action::OpenLink => match self.state.link_to_open() {
+                Some(target) => match links::open(&target) {
+                    Ok(()) => self
+                        .state
+                        .add_message(i18n::tr_args("open-done", &[("target", &target)])),
+                    Err(e) => self.state.push_message(Message::new(
+                        MessageKind::Error,
+                        i18n::tr_args(
+                            "open-failed",
+                            &[("target", &target), ("error", &e.to_string())],
+                        ),
+                    )),
+                },
+                None => self.state.add_message(i18n::tr("open-nothing")),
+            },
+            Action::FilterMessages => {
+                self.state.set_message_filter(self.state.message_filter.next_preset());
+                self.update_messages_display();
+            }
+            Action::ToggleQueries => {
+                self.state.show_queries = !self.state.show_queries;
+                self.state.query_cursor = 0;
+            }
+            Action::Search => {
+                self.state.clear_search();
+                self.state.is_search_mode = true;
+            }
+            Action::NextMatch => {
+                self.state.next_search_match();
+            }
+            Action::PreviousMatch => {
+                self.state.previous_search_match();
+            }
+            Action::CommandLine => {
+                self.state.is_command_mode = true;
+                self.state.command_text.clear();
+            }
+            Action::Back => {
+                if !self.state.search_query.is_empty() {
+                    self.state.clear_search();
+                } else if self.state.selected_message.is_some() {
+                    self.state.clear_message_selection();
+                } else if self.state.keymap.back_quits {
+                    return true;
+                }
+            }
+            Action::ScrollUp => {
+                self.state.scroll_messages_up();
+            }
+            Action::ScrollDown => {
+                self.state.scroll_messages_down();
+            }
+            Action::SelectNext => {
+                self.state.select_next_message();
+            }
+            Action::SelectPrevious => {
+                self.state.select_previous_message();
+            }
+            Action::Top => {
+                self.state.message_scroll = 0;
+            }
+            Action::Bottom => {
+                self.state.message_scroll = self.state.max_message_scroll();
+            }
+        }
+        false
+    }
+
+    /// Run a `:` command line. Returns `true` if the app should quit.
+    fn run_command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return false;
+        };
+        let argument = words.next();
+        match command {
+            "q" | "q!" | "quit" | "qa" => return true,
+            "h" | "help" => self.state.open_help(),
+            "theme" => match argument {
+                None => self.set_theme(self.state.theme.next()),
+                Some(name) => match Theme::named(name) {
+                    Some(theme) => self.set_theme(theme),
+                    None => self.state.add_message(i18n::tr_args(
+                        "theme-unknown",
+                        &[
+                            ("name", name),
+                            (
+                                "themes",
+                                &Theme::BUILT_IN.map(|theme| theme.name).join(", "),
+                            ),
+                        ],
+                    )),
+                },
+            },
+            "log" => self.state.show_log_pane = !self.state.show_log_pane,
+            "tabnew" => self.state.new_tab(),
+            "tabn" | "tabnext" => self.state.next_tab(),
+            "tabp" | "tabprevious" => self.state.previous_tab(),
+            "tabc" | "tabclose" => return self.perform(Action::CloseTab),
+            // Anything else is tried as a slash command, so `:clear` works too.
+            _ => self.run_slash_command(&format!("/{line}")),
+        }
+        self.update_messages_display();
+        false
+    }
+
+    /// Run a `/command` from the input box.
+    fn run_slash_command(&mut self, input: &str) {
+        let Some((name, args)) = commands::parse(input) else {
+            return;
+        };
+        match name {
+            "help" => self.state.open_help(),
+            "clear" => {
+                self.state.messages.clear();
+                self.state.session.clear();
+                self.state.clear_message_selection();
+                self.state.clear_search();
+                self.state.message_scroll = 0;
+            }
+            "model" if args.is_empty() => {
+                self.state.add_message(i18n::tr_args(
+                    "model-show",
+                    &[
+                        ("model", &self.config.model),
+                        ("provider", &self.state.provider),
+                    ],
+                ));
+            }
+            "model" => {
+                self.config.model = args.to_string();
+                self.state.model = args.to_string();
+                self.state.add_message(i18n::tr_args("model-set", &[("model", args)]));
+            }
+            "mode" if args.is_empty() => {
+                self.state.add_message(i18n::tr_args(
+                    "mode-show",
+                    &[("mode", self.config.agent_mode.label())],
+                ));
+            }
+            "mode" => match AgentMode::parse(args) {
+                Some(mode) => self.set_agent_mode(mode),
+                None => self.state.add_message(i18n::tr_args("mode-unknown", &[("mode", args)])),
+            },
+            "dryrun" => {
+                let mode = if self.config.agent_mode == AgentMode::Plan {
+                    self.previous_agent_mode
+                } else {
+                    AgentMode::Plan
+                };
+                self.set_agent_mode(mode);
+            }
+            "theme" if args.is_empty() => self.set_theme(self.state.theme.next()),
+            "theme" => match Theme::named(args) {
+                Some(theme) => self.set_theme(theme),
+                None => self.state.add_message(i18n::tr_args(
+                    "theme-unknown",
+                    &[
+                        ("name", args),
+                        (
+                            "themes",
+                            &Theme::BUILT_IN.map(|theme| theme.name).join(", "),
+                        ),
+                    ],
+                )),
+            },
+            "filter" => match MessageFilter::parse(args) {
+                Ok(filter) => self.state.set_message_filter(filter),
+                Err(e) => self.state.push_message(Message::new(
+                    MessageKind::Error,
+                    i18n::tr_args("filter-invalid", &[("error", &e.to_string())]),
+                )),
+            },
+            "export" => {
+                let (format, _) = export::parse_args(args);
+                let message = match self.export_conversation(args) {
+                    Ok(path) if format.is_table() => {
+                        i18n::tr_args("export-table", &[("path", &path.display().to_string())])
+                    }
+                    Ok(path) => {
+                        i18n::tr_args("export-tab", &[("path", &path.display().to_string())])
+                    }
+                    Err(e) => i18n::tr_args("export-failed", &[("error", &e.to_string())]),
+                };
+                self.state.add_message(message);
+            }
+            "rename" if args.is_empty() => {
+                let title = self
+                    .session_name
+                    .clone()
+                    .or_else(|| {
+                        self.state
+                            .snapshot_tabs()
+                            .into_iter()
+                            .map(|tab| tab.title)
+                            .find(|title| !title.is_empty())
+                    })
+                    .unwrap_or_else(|| i18n::tr("session-untitled"));
+                self.state.add_message(i18n::tr_args("rename-show", &[("title", &title)]));
+            }
+            "rename" => {
+                self.session_name = Some(args.to_string());
+                self.save_session();
+                let renamed = if self.session_path.is_some() {
+                    "rename-done"
+                } else {
+                    "rename-unsaved"
+                };
+                self.state
+                    .add_message(i18n::tr_args(renamed, &[("title", args)]));
+            }
+            "bookmarks" if self.state.bookmarks.is_empty() => {
+                let key = self
+                    .state
+                    .keymap
+                    .keys_for(Action::Bookmark)
+                    .unwrap_or_else(|| i18n::tr("bookmarks-key"));
+                self.state
+                    .add_message(i18n::tr_args("bookmarks-none", &[("key", &key)]));
+            }
+            "bookmarks" => self.state.bookmark_cursor = Some(0),
+            "stats" => {
+                let tabs = self.state.snapshot_tabs();
+                let history = self.config.save_state_dir.as_deref().map(|dir| {
+                    // This run's own file would count it twice.
+                    let saved = persistence::load_all_sessions(dir)
+                        .into_iter()
+                        .filter(|(path, _)| Some(path) != self.session_path.as_ref())
+                        .map(|(_, session)| session.tabs)
+                        .collect::<Vec<_>>();
+                    Stats::collect(
+                        std::iter::once(tabs.as_slice()).chain(saved.iter().map(Vec::as_slice)),
+                    )
+                });
+                self.state.stats_view = Some(StatsView {
+                    session: Stats::collect([tabs.as_slice()]),
+                    history,
+                });
+            }
+            "history" => match (&self.config.save_state_dir, args) {
+                (None, _) => self
+                    .state
+                    .add_message(i18n::tr("history-unsaved")),
+                (Some(_), "") => self.state.add_message(i18n::tr("history-usage")),
+                (Some(dir), query) => {
+                    let hits = persistence::search(&persistence::load_all_sessions(dir), query);
+                    if hits.is_empty() {
+                        self.state.add_message(i18n::tr_args("history-none", &[("query", query)]));
+                    } else {
+                        self.state.history_search = Some(HistorySearch {
+                            query: query.to_string(),
+                            hits,
+                            cursor: 0,
+                        });
+                    }
+                }
+            },
+            "reset-state" => {
+                let message = match (&self.config.save_state_dir, args) {
+                    (None, _) => i18n::tr("reset-unsaved"),
+                    (Some(dir), "confirm") => match persistence::reset(dir) {
+                        Ok(removed) => {
+                            self.state.edit_history.clear();
+                            self.state.input_history.clear();
+                            self.state.history_cursor = None;
+                            self.state.favorites.clear();
+                            self.state.bookmarks.clear();
+                            i18n::tr_args(
+                                "reset-done",
+                                &[("count", &removed.to_string()), ("dir", dir)],
+                            )
                         }
-                        KeyCode::Home => {
-                            self.state.message_scroll = 0;
+                        Err(e) => i18n::tr_args(
+                            "reset-failed",
+                            &[("dir", dir), ("error", &e.to_string())],
+                        ),
+                    },
+                    (Some(dir), _) => i18n::tr_args("reset-confirm", &[("dir", dir)]),
+                };
+                self.state.add_message(message);
+            }
+            other => self
+                .state
+                .add_message(i18n::tr_args("unknown-command", &[("command", other)])),
+        }
+        self.update_messages_display();
+    }
+
+    /// Ask/agent/plan affects tasks started from now on; a running task keeps its mode.
+    fn set_agent_mode(&mut self, mode: AgentMode) {
+        if self.config.agent_mode != AgentMode::Plan {
+            self.previous_agent_mode = self.config.agent_mode;
+        }
+        self.config.agent_mode = mode;
+        self.state.agent_mode = mode;
+        self.state.add_message(i18n::tr_args("mode-show", &[("mode", mode.label())]));
+    }
+
+    /// Write the active tab's conversation as `/export` asks; see
+    /// `export::parse_args` for the arguments. CSV and TSV take the first
+    /// table in the selected message, else in the latest message that has one.
+    fn export_conversation(&self, args: &str) -> Result<std::path::PathBuf> {
+        let (format, path) = export::parse_args(args);
+        if format.is_table() {
+            let selected = self.state.selected_message.and_then(|i| self.state.messages.get(i));
+            let table = match selected {
+                Some(message) => message
+                    .response
+                    .iter()
+                    .flat_map(|response| response.tables())
+                    .next(),
+                None => self
+                    .state
+                    .messages
+                    .iter()
+                    .rev()
+                    .find_map(|message| message.response.as_ref()?.tables().into_iter().next()),
+            };
+            let Some((header, rows)) = table else {
+                anyhow::bail!(
+                    "no table in {}",
+                    if selected.is_some() {
+                        "the selected message"
+                    } else {
+                        "this tab"
+                    }
+                );
+            };
+            std::fs::write(&path, redact::text(&format.table(&header, &rows)))?;
+            return Ok(path);
+        }
+        let conversation = export::Conversation::new(
+            &self.config.target_repo_path,
+            &self.config.model,
+            &self.state.session.turns,
+            &self.state.edit_history,
+            &self.state.messages,
+        );
+        std::fs::write(&path, redact::text(&conversation.render(format)?))?;
+        Ok(path)
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.state.theme = theme;
+        self.state.add_message(i18n::tr_args("theme-show", &[("name", theme.name)]));
+        self.update_messages_display();
+    }
+
+    fn copy_to_clipboard(&mut self, text: Option<String>, what: &str) {
+        let Some(text) = text else {
+            self.state.add_message(i18n::tr_args("copy-nothing", &[("what", what)]));
+            self.update_messages_display();
+            return;
+        };
+        let message = match self.clipboard.copy(&text) {
+            Ok(CopyMethod::System) => i18n::tr_args("copy-done", &[("what", what)]),
+            Ok(CopyMethod::Osc52) => i18n::tr_args("copy-done-osc52", &[("what", what)]),
+            Err(e) => i18n::tr_args("copy-failed", &[("what", what), ("error", &e.to_string())]),
+        };
+        self.state.add_message(message);
+        self.update_messages_display();
+    }
+
+    /// Wheel scrolls the activity pane; a left click on it selects the message
+    /// under the pointer, and a click on the input box starts typing. Ignored
+    /// while any overlay is open.
+    fn handle_mouse(&mut self, mouse: MouseEvent, area: ratatui::layout::Rect) {
+        if self.state.setup.is_some()
+            || self.state.session_picker.is_some()
+            || self.state.history_search.is_some()
+            || self.state.stats_view.is_some()
+            || self.state.bookmark_cursor.is_some()
+            || self.state.pending_confirmation.is_some()
+            || self.state.show_details
+            || self.state.show_help
+            || self.state.show_queries
+            || self.state.show_message
+        {
+            return;
+        }
+
+        let layout = ui::main_layout(area);
+        let (input_area, (messages_area, _)) = (
+            layout[1],
+            ui::activity_layout(layout[2], self.state.show_log_pane),
+        );
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.state.scroll_messages_up(),
+            MouseEventKind::ScrollDown => self.state.scroll_messages_down(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if input_area.contains(ratatui::layout::Position::new(mouse.column, mouse.row)) {
+                    self.state.is_input_mode = true;
+                } else if let Some(line) =
+                    ui::message_line_at(&self.state, messages_area, mouse.row)
+                {
+                    self.state.selected_message = self.state.message_line_owner.get(line).copied();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pasted text goes into the input box at the cursor, newlines intact. Pasting
+    /// from navigation mode switches to input mode first; overlays ignore it.
+    /// During setup it fills the focused field, which is how API keys arrive.
+    fn handle_paste(&mut self, text: &str) {
+        if let Some(setup) = &mut self.state.setup {
+            if !setup.checking {
+                setup.focused().push_str(text.trim());
+            }
+            return;
+        }
+        if self.state.pending_confirmation.is_some()
+            || self.state.history_search.is_some()
+            || self.state.stats_view.is_some()
+            || self.state.bookmark_cursor.is_some()
+            || self.state.show_details
+            || self.state.show_help
+            || self.state.show_queries
+            || self.state.show_message
+        {
+            return;
+        }
+        self.state.is_input_mode = true;
+        self.state.insert_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+    }
+
+    /// Run the task in the input box, or queue it behind the running one. It
+    /// belongs to the tab it was typed in either way.
+    async fn submit_task(&mut self) {
+        let task = std::mem::take(&mut self.state.input_text);
+        self.state.cursor_position = 0;
+        let tab_id = self.state.active_tab_id();
+        let rerun_of = self
+            .state
+            .rerun_source
+            .take()
+            .filter(|&(source_tab, _)| source_tab == tab_id)
+            .map(|(_, original)| original);
+        self.state.record_input(&task);
+        if let Some(save_state_dir) = &self.config.save_state_dir
+            && let Err(e) = persistence::append_input(save_state_dir, &task)
+        {
+            self.state.add_message(i18n::tr_args(
+                "save-input-failed",
+                &[("error", &e.to_string())],
+            ));
+        }
+
+        if self.running.is_some() {
+            self.state.title_active_tab(&task);
+            self.state.add_message(i18n::tr_args(
+                "task-queued",
+                &[("task", &task), ("ahead", &(self.state.task_queue.len() + 1).to_string())],
+            ));
+            self.state.task_queue.push_back(QueuedTask { task, tab_id, rerun_of });
+            self.update_messages_display();
+        } else {
+            self.start_task(task, tab_id, rerun_of).await;
+        }
+    }
+
+    /// Start the oldest queued task whose tab is still open.
+    async fn start_next_queued(&mut self) {
+        while let Some(next) = self.state.task_queue.pop_front() {
+            if self.state.tab_index(next.tab_id).is_some() {
+                self.start_task(next.task, next.tab_id, next.rerun_of).await;
+                return;
+            }
+        }
+    }
+
+    /// Kick off `task` on a background tokio task, with tab `tab_id`'s session
+    /// as context. A rerun's messages thread under `rerun_of`.
+    async fn start_task(
+        &mut self,
+        task: String,
+        tab_id: u64,
+        rerun_of: Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        self.state.is_processing = true;
+        self.state.processing_started = Some(Instant::now());
+        self.state.current_step = Some("planning".to_string());
+
+        let mut session = None;
+        self.in_tab(tab_id, |app| {
+            app.state.title_active_tab(&task);
+            app.state.push_message(
+                Message::new(MessageKind::User, format!("Processing task: {}", task))
+                    .with_rerun_of(rerun_of),
+            );
+            app.update_messages_display();
+            session = Some(app.state.session.clone());
+        });
+        let session = session.unwrap_or_default();
+        self.start_naming(&task);
+
+        let (confirm_tx, confirmations) = mpsc::unbounded_channel();
+        let mut orchestrator = match CodeTaskOrchestrator::new(&self.config).await {
+            Ok(orchestrator) => orchestrator.with_confirmation_channel(confirm_tx),
+            Err(e) => {
+                self.in_tab(tab_id, |app| app.finish_task(task, Err(e), rerun_of));
+                return;
+            }
+        };
+        let events = orchestrator.subscribe();
+        let task_for_run = task.clone();
+        let handle = self
+            .tasks
+            .spawn(async move { orchestrator.run_task(&task_for_run, &session).await });
+
+        self.running = Some(RunningTask {
+            task,
+            tab_id,
+            rerun_of,
+            handle,
+            confirmations,
+            events,
+        });
+    }
+
+    /// Re-test the provider in the background, e.g. after it showed as unreachable.
+    /// The status bar updates in place once the check comes back.
+    fn start_connection_check(&mut self) {
+        self.spawn_connection_check(self.config.clone());
+    }
+
+    fn spawn_connection_check(&mut self, config: Config) {
+        if self.connection_check.is_some() {
+            return;
+        }
+        self.state.checking_connection = true;
+        tracing::info!(model = %config.model, "connection_check");
+        self.connection_check = Some(self.tasks.spawn(async move {
+            CodeTaskOrchestrator::new(&config).await?.check_connection().await
+        }));
+    }
+
+    /// Walk the repo for `@path` completion in the background: on a large
+    /// checkout it would otherwise hold up the first frame. Until it's done,
+    /// completion just has no files to offer.
+    fn start_listing(&mut self) {
+        let root = std::path::PathBuf::from(&self.config.target_repo_path);
+        let filter = crate::policy::PathFilter {
+            allow: self.config.allowed_paths.clone(),
+            deny: self.config.denied_paths.clone(),
+        };
+        self.listing = Some(self.tasks.spawn(async move {
+            tokio::task::spawn_blocking(move || {
+                crate::orchestrator::list_repo_files(&root, MAX_COMPLETION_FILES)
+                    .into_iter()
+                    .filter(|path| filter.permits(path))
+                    .collect()
+            })
+            .await
+            .unwrap_or_default()
+        }));
+    }
+
+    async fn poll_listing(&mut self) {
+        let Some(listing) = self.listing.take_if(|listing| listing.is_finished()) else {
+            return;
+        };
+        if let Ok(files) = listing.join().await {
+            self.state.repo_files = files;
+        }
+    }
+
+    /// Ask the LLM to name a saved session after its first task, in the
+    /// background; the task itself doesn't wait for it.
+    fn start_naming(&mut self, task: &str) {
+        if self.session_path.is_none() || self.session_name.is_some() || self.asked_for_name {
+            return;
+        }
+        self.asked_for_name = true;
+        let (config, task) = (self.config.clone(), task.to_string());
+        self.naming = Some(self.tasks.spawn(async move {
+            CodeTaskOrchestrator::new(&config).await?.summarize_title(&task).await
+        }));
+    }
+
+    async fn poll_naming(&mut self) {
+        let Some(handle) = self.naming.take_if(|handle| handle.is_finished()) else {
+            return;
+        };
+        match handle.join().await.and_then(|result| result) {
+            // A `/rename` while it was running wins.
+            Ok(name) if self.session_name.is_none() => {
+                tracing::info!(name = %name, "session_named");
+                self.session_name = Some(name);
+                self.save_session();
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(error = %e, "session_naming"),
+        }
+    }
+
+    async fn poll_connection_check(&mut self) {
+        let Some(handle) = self.connection_check.take_if(|handle| handle.is_finished()) else {
+            return;
+        };
+        self.state.checking_connection = false;
+        let result = handle.join().await.and_then(|result| result);
+        self.state.llm_reachable = Some(result.is_ok());
+
+        if let Some(setup) = &mut self.state.setup {
+            setup.checking = false;
+            match result {
+                Ok(()) => self.finish_setup(),
+                Err(e) => setup.error = Some(e.to_string()),
+            }
+            return;
+        }
+
+        let message = match result {
+            Ok(()) => Message::new(
+                MessageKind::System,
+                i18n::tr_args(
+                    "connection-ok",
+                    &[
+                        ("model", &self.state.model),
+                        ("provider", &self.state.provider),
+                    ],
+                ),
+            ),
+            Err(e) => Message::new(
+                MessageKind::Error,
+                i18n::tr_args("connection-failed", &[("error", &e.to_string())]),
+            ),
+        };
+        tracing::info!("{}", message.body);
+        self.state.push_message(message);
+        self.update_messages_display();
+    }
+
+    /// Quit now if nothing is running; otherwise ask what to do about the task
+    /// in flight rather than abandon it mid-edit. Returns `true` to quit now.
+    fn request_quit(&mut self) -> bool {
+        if self.running.is_none() {
+            return true;
+        }
+        self.state.quit_prompt = true;
+        false
+    }
+
+    /// The quit prompt: w waits for the running task and then quits, a aborts it
+    /// and quits now, k/Esc keeps working. Returns `true` to quit now.
+    fn handle_quit_prompt_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('w') => {
+                self.state.quit_prompt = false;
+                self.state.quit_when_idle = true;
+                let dropped = std::mem::take(&mut self.state.task_queue).len();
+                self.state.add_message(match dropped {
+                    0 => i18n::tr("quit-when-idle"),
+                    n => i18n::tr_args("quit-when-idle-dropped", &[("count", &n.to_string())]),
+                });
+                self.update_messages_display();
+                false
+            }
+            KeyCode::Char('a') => {
+                if let Some(running) = self.running.take() {
+                    running.handle.cancel();
+                }
+                true
+            }
+            KeyCode::Char('k') | KeyCode::Esc => {
+                self.state.quit_prompt = false;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// The help overlay: `/` filters it, j/k/↑/↓ scroll, and Esc, F1, or the
+    /// help key closes it.
+    fn handle_help_key(&mut self, key: KeyEvent) {
+        if self.state.is_help_search {
+            match key.code {
+                KeyCode::Esc => {
+                    self.state.help_query.clear();
+                    self.state.is_help_search = false;
+                }
+                KeyCode::Enter => self.state.is_help_search = false,
+                KeyCode::Backspace => {
+                    self.state.help_query.pop();
+                }
+                KeyCode::Char(c) => {
+                    self.state.help_query.push(c);
+                    self.state.help_scroll = 0;
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Char('/') => self.state.is_help_search = true,
+            KeyCode::Down | KeyCode::Char('j') => self.state.help_scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.help_scroll = self.state.help_scroll.saturating_sub(1)
+            }
+            KeyCode::PageDown => self.state.help_scroll += 10,
+            KeyCode::PageUp => self.state.help_scroll = self.state.help_scroll.saturating_sub(10),
+            KeyCode::Esc | KeyCode::F(1) => self.state.show_help = false,
+            _ if self.state.keymap.action_for(&key) == Some(Action::ToggleHelp) => {
+                self.state.show_help = false
+            }
+            _ => {}
+        }
+    }
+
+    /// The tasks panel: j/k/↑/↓ move, Enter puts the task in the input box, s
+    /// stars or unstars it, Esc or the panel's own key closes it.
+    fn handle_queries_key(&mut self, key: KeyEvent) {
+        let count = self.state.query_entries().len();
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.state.query_cursor =
+                    (self.state.query_cursor + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.query_cursor = self.state.query_cursor.saturating_sub(1)
+            }
+            KeyCode::Enter => {
+                if let Some(&(task, _)) = self.state.query_entries().get(self.state.query_cursor) {
+                    let task = task.to_string();
+                    self.state.set_input(task);
+                    self.state.is_input_mode = true;
+                    self.state.show_queries = false;
+                }
+            }
+            KeyCode::Char('s') => {
+                self.state.toggle_favorite();
+                if let Some(save_state_dir) = &self.config.save_state_dir
+                    && let Err(e) =
+                        persistence::save_favorites(save_state_dir, &self.state.favorites)
+                {
+                    self.state.add_message(i18n::tr_args(
+                        "save-favorites-failed",
+                        &[("error", &e.to_string())],
+                    ));
+                    self.update_messages_display();
+                }
+            }
+            KeyCode::Esc => self.state.show_queries = false,
+            _ if self.state.keymap.action_for(&key) == Some(Action::ToggleQueries) => {
+                self.state.show_queries = false
+            }
+            _ => {}
+        }
+    }
+
+    /// Edit the first-run form. Enter moves to the next field and, on the last
+    /// one, tests the values against the provider; Esc skips setup for this run.
+    fn handle_setup_key(&mut self, key: KeyEvent) {
+        let Some(setup) = &mut self.state.setup else {
+            return;
+        };
+        if setup.checking {
+            return;
+        }
+        match key.code {
+            KeyCode::Esc => {
+                self.state.setup = None;
+                self.state.add_message(i18n::tr("setup-skipped"));
+                self.update_messages_display();
+            }
+            KeyCode::Enter if setup.on_last_field() => {
+                let config = setup.apply(&self.config);
+                if let Err(e) = config.validate() {
+                    setup.error = Some(e.to_string());
+                    return;
+                }
+                setup.checking = true;
+                setup.error = None;
+                self.spawn_connection_check(config);
+            }
+            KeyCode::Enter | KeyCode::Tab | KeyCode::Down => setup.focus_next(),
+            KeyCode::BackTab | KeyCode::Up => setup.focus_previous(),
+            KeyCode::Backspace => {
+                setup.focused().pop();
+            }
+            KeyCode::Char(c) => setup.focused().push(c),
+            _ => {}
+        }
+    }
+
+    /// The entered values worked: save them to `.env` and start using them.
+    fn finish_setup(&mut self) {
+        let Some(setup) = self.state.setup.take() else {
+            return;
+        };
+        self.config = setup.apply(&self.config);
+        self.state.model = self.config.model.clone();
+        self.state.provider = provider_host(self.config.openai_base_url.as_deref());
+        let message = match setup::write_env_file(std::path::Path::new(".env"), &setup.entries()) {
+            Ok(()) => i18n::tr_args(
+                "setup-saved",
+                &[
+                    ("model", &self.state.model),
+                    ("provider", &self.state.provider),
+                ],
+            ),
+            Err(e) => i18n::tr_args("setup-save-failed", &[("error", &e.to_string())]),
+        };
+        self.state.add_message(message);
+        self.update_messages_display();
+    }
+
+    /// Surface any pending confirmation, and collect the result once the
+    /// background task has finished.
+    async fn poll_running_task(&mut self) {
+        let Some(running) = &mut self.running else {
+            return;
+        };
+
+        // Checked before draining so every event sent before the task ended is seen.
+        let finished = running.handle.is_finished();
+        let tab_id = running.tab_id;
+        let mut lines = Vec::new();
+        loop {
+            match running.events.try_recv() {
+                Ok(event) => {
+                    if let Some(step) = step_for_event(&event) {
+                        self.state.current_step = Some(step);
+                    }
+                    match &event {
+                        OrchestratorEvent::LlmResponded { prompt_tokens, completion_tokens } => {
+                            self.state.prompt_tokens += u64::from(*prompt_tokens);
+                            self.state.completion_tokens += u64::from(*completion_tokens);
+                            self.state.llm_reachable = Some(true);
                         }
-                        KeyCode::End => {
-                            let max_scroll = self.state.messages_expanded.len().saturating_sub(10);
-                            self.state.message_scroll = max_scroll;
+                        OrchestratorEvent::LlmFailed { .. } => {
+                            self.state.llm_reachable = Some(false)
                         }
                         _ => {}
                     }
+                    if !self.state.show_log_pane
+                        && let Some(line) = describe_event(&event)
+                    {
+                        lines.push(Message::from_event(&event, line));
+                    }
                 }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
             }
         }
+
+        if self.confirm_responder.is_none()
+            && let Ok(request) = running.confirmations.try_recv()
+        {
+            self.state.pending_confirmation = Some(PendingConfirmation {
+                path: request.path,
+                content: request.content,
+                class: request.class,
+                injection: request.injection,
+                edited: false,
+            });
+            self.confirm_responder = Some(request.respond);
+        }
+
+        if !lines.is_empty() {
+            self.in_tab(tab_id, |app| {
+                for message in lines {
+                    app.state.push_message(message);
+                }
+                app.update_messages_display();
+            });
+        }
+
+        if !finished {
+            return;
+        }
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        let result = match running.handle.join().await {
+            Ok(result) => result,
+            Err(e) => Err(anyhow::anyhow!("task {e}")),
+        };
+        self.state.pending_confirmation = None;
+        self.confirm_responder = None;
+        self.in_tab(tab_id, |app| app.finish_task(running.task, result, running.rerun_of));
+        self.save_session();
     }
 
-    async fn process_user_input(&mut self) {
-        let task = self.state.input_text.clone();
-        self.state.input_text.clear();
-        self.state.cursor_position = 0;
+    /// Write every tab to this run's session file, once a task has been run in
+    /// at least one of them - a run that did nothing isn't worth resuming.
+    fn save_session(&mut self) {
+        let Some(path) = &self.session_path else {
+            return;
+        };
+        let tabs = self.state.snapshot_tabs();
+        if tabs.iter().all(|tab| tab.title.is_empty()) {
+            return;
+        }
+        let session = persistence::SavedSession {
+            version: persistence::SESSION_VERSION,
+            saved_at: chrono::Utc::now(),
+            active_tab: self.state.active_tab,
+            tabs,
+            name: self.session_name.clone(),
+        };
+        if let Err(e) = persistence::save_session(path, &session) {
+            tracing::warn!(error = %e, "session_save");
+        }
+    }
+
+    /// Choose from the startup session list: j/k/↑/↓ move, Enter resumes, n/Esc
+    /// starts fresh.
+    fn handle_session_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.state.session_picker else {
+            return;
+        };
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                picker.cursor = (picker.cursor + 1).min(picker.sessions.len().saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') => picker.cursor = picker.cursor.saturating_sub(1),
+            KeyCode::Enter => {
+                let Some(picker) = self.state.session_picker.take() else {
+                    return;
+                };
+                if let Some((path, session)) = picker.sessions.into_iter().nth(picker.cursor) {
+                    self.resume_session(path, session);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('n') => self.state.session_picker = None,
+            _ => {}
+        }
+    }
+
+    /// Browse `/bookmarks`: j/k/↑/↓ move, y copies, d removes, Esc closes.
+    fn handle_bookmarks_key(&mut self, key: KeyEvent) {
+        let Some(cursor) = self.state.bookmark_cursor else {
+            return;
+        };
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.state.bookmark_cursor =
+                    Some((cursor + 1).min(self.state.bookmarks.len().saturating_sub(1)));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.state.bookmark_cursor = Some(cursor.saturating_sub(1))
+            }
+            KeyCode::Char('y') => {
+                let text = self.state.bookmarks.get(cursor).map(Message::line);
+                self.copy_to_clipboard(text, "bookmark");
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                self.state.remove_bookmark();
+                self.save_bookmarks();
+            }
+            KeyCode::Esc => self.state.bookmark_cursor = None,
+            _ => {}
+        }
+    }
+
+    fn save_bookmarks(&mut self) {
+        if let Some(save_state_dir) = &self.config.save_state_dir
+            && let Err(e) = persistence::save_bookmarks(save_state_dir, &self.state.bookmarks)
+        {
+            self.state.add_message(i18n::tr_args(
+                "save-bookmarks-failed",
+                &[("error", &e.to_string())],
+            ));
+            self.update_messages_display();
+        }
+    }
+
+    /// Choose from `/history` matches: j/k/↑/↓ move, Enter reopens the match's
+    /// tab alongside the current ones, `r` puts its task in the input box, Esc
+    /// closes.
+    fn handle_history_search_key(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.state.history_search else {
+            return;
+        };
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => {
+                search.cursor = (search.cursor + 1).min(search.hits.len().saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') => search.cursor = search.cursor.saturating_sub(1),
+            KeyCode::Enter => {
+                let Some(hit) = self
+                    .state
+                    .history_search
+                    .take()
+                    .and_then(|search| search.hits.into_iter().nth(search.cursor))
+                else {
+                    return;
+                };
+                match persistence::load_session(&hit.session)
+                    .and_then(|session| session.tabs.into_iter().nth(hit.tab))
+                {
+                    Some(tab) => {
+                        self.state.open_saved_tab(tab, hit.index);
+                        self.update_messages_display();
+                        self.state.scroll_to_selected();
+                    }
+                    None => self.state.add_message(i18n::tr_args(
+                        "history-unreadable",
+                        &[("path", &hit.session.display().to_string())],
+                    )),
+                }
+            }
+            KeyCode::Char('r') => {
+                let Some(task) = search
+                    .hits
+                    .get(search.cursor)
+                    .and_then(|hit| hit.task.clone())
+                else {
+                    return;
+                };
+                self.state.history_search = None;
+                self.state.set_input(task);
+                self.state.is_input_mode = true;
+            }
+            KeyCode::Esc => self.state.history_search = None,
+            _ => {}
+        }
+    }
+
+    /// Bring back a saved session's tabs and keep saving into its file.
+    fn resume_session(&mut self, path: std::path::PathBuf, session: persistence::SavedSession) {
+        let title = session.title().to_string();
+        let scroll = session.tabs.get(session.active_tab).map_or(0, |tab| tab.message_scroll);
+        self.state.restore_tabs(session.tabs, session.active_tab);
+        self.session_path = Some(path);
+        // Its first task was long ago; keep the name it has, or the tab title.
+        self.session_name = session.name;
+        self.asked_for_name = true;
+        self.state.add_message(i18n::tr_args("session-resumed", &[("title", &title)]));
+        self.update_messages_display();
+        self.state.message_scroll = scroll.min(self.state.max_message_scroll());
+    }
+
+    /// Run `f` with tab `tab_id` swapped in, so a task's output lands in the
+    /// conversation it was started from even if the user has switched away.
+    fn in_tab(&mut self, tab_id: u64, f: impl FnOnce(&mut Self)) {
+        let active = self.state.active_tab;
+        match self.state.tab_index(tab_id) {
+            Some(index) if index != active => {
+                self.state.switch_tab(index);
+                f(self);
+                self.state.switch_tab(active);
+                self.update_messages_display();
+            }
+            _ => f(self),
+        }
+    }
+
+    fn answer_confirmation(&mut self, approved: bool) {
+        let pending = self.state.pending_confirmation.take();
+        if let Some(respond) = self.confirm_responder.take() {
+            let response = match &pending {
+                _ if !approved => ConfirmationResponse::Decline,
+                Some(pending) if pending.edited => {
+                    ConfirmationResponse::ApproveEdited(pending.content.clone())
+                }
+                _ => ConfirmationResponse::Approve,
+            };
+            // The task may have gone away already; nothing left to tell it then.
+            let _ = respond.send(response);
+        }
+        if let Some(pending) = pending {
+            let verdict = match (approved, pending.edited) {
+                (false, _) => "confirm-declined",
+                (true, true) => "confirm-approved-edited",
+                (true, false) => "confirm-approved",
+            };
+            self.state.add_message(i18n::tr_args(
+                verdict,
+                &[("kind", pending.class.label()), ("path", &pending.path.display().to_string())],
+            ));
+            self.update_messages_display();
+        }
+    }
+
+    /// Open the edit awaiting confirmation in `$VISUAL`/`$EDITOR` (falling back to
+    /// `vi`), suspending the TUI until the editor exits. The file keeps the target's
+    /// extension so the editor picks the right syntax.
+    fn edit_pending_confirmation(&mut self) -> Result<()> {
+        let Some(pending) = &mut self.state.pending_confirmation else {
+            return Ok(());
+        };
+
+        let extension = pending
+            .path
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let scratch =
+            std::env::temp_dir().join(format!("codepilot-edit-{}{extension}", std::process::id()));
+        std::fs::write(&scratch, &pending.content)?;
+
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let mut parts = editor.split_whitespace();
+        let program = parts.next().unwrap_or("vi");
 
-        self.state.add_message(format!("Processing task: {}", task));
+        disable_raw_mode()?;
+        execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste, LeaveAlternateScreen)?;
+        let status = std::process::Command::new(program).args(parts).arg(&scratch).status();
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste, EnableMouseCapture)?;
+        enable_raw_mode()?;
+
+        let outcome = match status {
+            Ok(status) if status.success() => match std::fs::read_to_string(&scratch) {
+                Ok(content) if content != pending.content => {
+                    pending.content = content;
+                    pending.edited = true;
+                    i18n::tr("editor-edited")
+                }
+                Ok(_) => i18n::tr("editor-unchanged"),
+                Err(e) => i18n::tr_args("editor-unreadable", &[("error", &e.to_string())]),
+            },
+            Ok(status) => i18n::tr_args("editor-failed", &[("status", &status.to_string())]),
+            Err(e) => i18n::tr_args(
+                "editor-missing",
+                &[("program", program), ("error", &e.to_string())],
+            ),
+        };
+        let _ = std::fs::remove_file(&scratch);
+        self.state.add_message(outcome);
         self.update_messages_display();
+        Ok(())
+    }
 
-        let formatter = ResponseFormatter::new();
-        match self.run_code_task(&task).await {
+    fn finish_task(
+        &mut self,
+        task: String,
+        result: Result<TaskResult>,
+        rerun_of: Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        let formatter =
+            ResponseFormatter::fitted(&self.config, self.state.messages_width.saturating_sub(4));
+        let agent = self.config.agent_name.clone();
+        match &result {
+            Ok(result) => self.state.session.record_result(&task, result),
+            Err(e) => self.state.session.record_error(&task, &e.to_string()),
+        }
+        match result {
             Ok(result) => {
                 let verification_text = match &result.verification {
                     crate::runs::RunStatus::Succeeded => "tsc: passed".to_string(),
                     crate::runs::RunStatus::Failed(err) => format!("tsc: failed - {err}"),
+                    crate::runs::RunStatus::Skipped => "tsc: not run (plan mode)".to_string(),
+                };
+                let planned = matches!(result.verification, crate::runs::RunStatus::Skipped);
+
+                let attempts_text = if result.attempts > 1 {
+                    format!(
+                        " {}",
+                        i18n::tr_args(
+                            "result-attempts",
+                            &[("count", &result.attempts.to_string())]
+                        )
+                    )
+                } else {
+                    String::new()
                 };
 
-                if result.applied {
+                if planned {
+                    let summary = i18n::tr_args(
+                        "result-planned",
+                        &[
+                            ("path", &result.target_path.display().to_string()),
+                            ("bytes", &result.edit.content.len().to_string()),
+                        ],
+                    );
+                    self.state.last_response = Some(summary.clone());
+                    self.state.push_message(
+                        Message::new(MessageKind::Agent, summary)
+                            .with_agent(agent)
+                            .with_rerun_of(rerun_of),
+                    );
+                } else if result.applied {
                     let summary = format!(
-                        "Wrote {} ({} bytes) — {}",
-                        result.target_path.display(),
-                        result.edit.content.len(),
-                        verification_text
+                        "{}{attempts_text} — {verification_text}",
+                        i18n::tr_args(
+                            "result-applied",
+                            &[
+                                ("path", &result.target_path.display().to_string()),
+                                ("bytes", &result.edit.content.len().to_string()),
+                            ],
+                        )
+                    );
+                    let message = Message::rendered(
+                        MessageKind::Agent,
+                        &formatter,
+                        formatter.render_success(&summary),
                     );
-                    self.state.add_message(formatter.format_success(&summary));
+                    self.state.last_response = Some(message.body.clone());
+                    self.state.push_message(message.with_agent(agent).with_rerun_of(rerun_of));
                 } else {
                     let summary = format!(
-                        "Rejected edit to {} — {}",
-                        result.target_path.display(),
-                        verification_text
+                        "{}{attempts_text} — {verification_text}",
+                        i18n::tr_args(
+                            "result-rejected",
+                            &[("path", &result.target_path.display().to_string())]
+                        )
+                    );
+                    let message = Message::rendered(
+                        MessageKind::Error,
+                        &formatter,
+                        formatter.render_error(&summary),
                     );
-                    self.state.add_message(formatter.format_error(&summary));
+                    self.state.last_response = Some(message.body.clone());
+                    self.state.push_message(message.with_agent(agent).with_rerun_of(rerun_of));
                 }
 
                 let detail = crate::cli::state::EditDetail {
@@ -234,32 +1854,170 @@ impl App {
                     timestamp: chrono::Utc::now(),
                     applied: result.applied,
                     verification: Some(verification_text),
+                    attempts: result.attempts,
+                    planned,
                 };
-                if let Some(save_state_dir) = &self.config.save_state_dir {
-                    if let Err(e) = persistence::append_entry(save_state_dir, &detail) {
-                        self.state.add_message(formatter.format_error(&format!(
-                            "Could not save history: {e}"
-                        )));
-                    }
+                if let Some(save_state_dir) = &self.config.save_state_dir
+                    && let Err(e) = persistence::append_entry(save_state_dir, &detail)
+                {
+                    self.state.push_message(Message::new(
+                        MessageKind::Error,
+                        formatter.format_error(&i18n::tr_args(
+                            "save-history-failed",
+                            &[("error", &e.to_string())],
+                        )),
+                    ));
                 }
                 self.state.push_edit_detail(detail);
             }
             Err(e) => {
-                self.state.add_message(formatter.format_error(&e.to_string()));
+                let message =
+                    Message::rendered(MessageKind::Error, &formatter, formatter.render_failure(&e));
+                self.state.last_response = Some(message.body.clone());
+                self.state.push_message(message.with_agent(agent).with_rerun_of(rerun_of));
             }
         }
 
         self.update_messages_display();
         self.state.is_processing = false;
+        self.state.processing_started = None;
+        self.state.current_step = None;
     }
 
     fn update_messages_display(&mut self) {
-        let width = 100;
+        let width = self.state.messages_width;
         self.state.update_messages_expanded(width);
     }
 
-    async fn run_code_task(&self, task: &str) -> Result<crate::orchestrator::TaskResult> {
-        let mut orchestrator = CodeTaskOrchestrator::new(&self.config).await?;
-        orchestrator.run_task(task).await
+    /// Size the activity pane to what the next frame will actually draw.
+    fn fit_to_terminal(&mut self, area: ratatui::layout::Rect) {
+        let chunks = ui::main_layout(area);
+        let (messages_area, _) = ui::activity_layout(chunks[2], self.state.show_log_pane);
+        self.state.resize_messages(
+            messages_area.width as usize,
+            messages_area.height.saturating_sub(2) as usize,
+        );
+    }
+}
+
+/// Redraw the activity pane's links wrapped in OSC 8 escapes, which ratatui's
+/// buffer can't hold. Done after every frame, and only where the frame still
+/// shows the link text - overlays and popups drawn on top are left alone.
+fn write_hyperlinks(state: &AppState, buffer: &ratatui::buffer::Buffer) -> io::Result<()> {
+    use ratatui::crossterm::{cursor, queue, style};
+    use std::io::Write;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    if state.show_message || state.show_details {
+        return Ok(());
+    }
+    let layout = ui::main_layout(buffer.area);
+    let (messages_area, _) = ui::activity_layout(layout[2], state.show_log_pane);
+    let links = ui::activity_links(state, messages_area);
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout();
+    queue!(stdout, cursor::SavePosition)?;
+    for link in links {
+        let cells: Vec<&ratatui::buffer::Cell> = link
+            .text
+            .graphemes(true)
+            .enumerate()
+            .filter_map(|(i, _)| buffer.cell((link.x + i as u16, link.y)))
+            .collect();
+        let shown: String = cells.iter().map(|cell| cell.symbol()).collect();
+        let Some(first) = cells.first().filter(|_| shown == link.text) else {
+            continue;
+        };
+        queue!(
+            stdout,
+            cursor::MoveTo(link.x, link.y),
+            style::SetForegroundColor(first.fg.into()),
+            style::SetBackgroundColor(first.bg.into()),
+            style::SetAttribute(style::Attribute::Underlined),
+            style::Print(links::osc8(&link.text, &link.target)),
+            style::SetAttribute(style::Attribute::Reset),
+            style::ResetColor,
+        )?;
+    }
+    queue!(stdout, cursor::RestorePosition)?;
+    stdout.flush()
+}
+
+/// Short description of what the task is doing once `event` has happened, for
+/// the spinner line.
+fn step_for_event(event: &OrchestratorEvent) -> Option<String> {
+    match event {
+        OrchestratorEvent::TaskStarted { .. } => Some(i18n::tr("step-planning")),
+        OrchestratorEvent::FilesRequested { paths } => Some(i18n::tr_args(
+            "step-reading",
+            &[("paths", &paths.join(", "))],
+        )),
+        OrchestratorEvent::EditProposed { path, .. } => {
+            Some(i18n::tr_args("step-writing", &[("path", path)]))
+        }
+        OrchestratorEvent::ConfirmationRequested { .. } => Some(i18n::tr("step-confirming")),
+        OrchestratorEvent::GateStarted { kind, attempt } if *attempt > 1 => Some(i18n::tr_args(
+            "step-gate-retry",
+            &[("gate", kind.label()), ("attempt", &attempt.to_string())],
+        )),
+        OrchestratorEvent::GateStarted { kind, .. } => {
+            Some(i18n::tr_args("step-gate", &[("gate", kind.label())]))
+        }
+        OrchestratorEvent::Retrying { .. } => Some(i18n::tr("step-fixing")),
+        OrchestratorEvent::GateFinished { .. }
+        | OrchestratorEvent::InjectionSuspected { .. }
+        | OrchestratorEvent::TaskFinished { .. }
+        | OrchestratorEvent::LlmResponded { .. }
+        | OrchestratorEvent::LlmFailed { .. }
+        | OrchestratorEvent::Error { .. } => None,
+    }
+}
+
+/// "api.openai.com" out of `OPENAI_BASE_URL` (the default when unset).
+pub(crate) fn provider_host(base_url: Option<&str>) -> String {
+    let url = base_url.unwrap_or("https://api.openai.com/v1");
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    without_scheme.split('/').next().unwrap_or(without_scheme).to_string()
+}
+
+/// Activity-log line for a progress event. Start, finish, and errors are left to
+/// `finish_task`, which has the full result to summarize.
+pub(crate) fn describe_event(event: &OrchestratorEvent) -> Option<String> {
+    match event {
+        OrchestratorEvent::FilesRequested { paths } => Some(i18n::tr_args(
+            "event-reading",
+            &[("paths", &paths.join(", "))],
+        )),
+        OrchestratorEvent::InjectionSuspected { source, excerpt } => Some(i18n::tr_args(
+            "event-injection",
+            &[("source", source), ("excerpt", &format!("{excerpt:?}"))],
+        )),
+        OrchestratorEvent::EditProposed { path, attempt } if *attempt > 1 => Some(i18n::tr_args(
+            "event-proposed-retry",
+            &[("path", path), ("attempt", &attempt.to_string())],
+        )),
+        OrchestratorEvent::EditProposed { path, .. } => {
+            Some(i18n::tr_args("event-proposed", &[("path", path)]))
+        }
+        OrchestratorEvent::GateStarted { kind, .. } => {
+            Some(i18n::tr_args("event-gate", &[("gate", kind.label())]))
+        }
+        OrchestratorEvent::Retrying {
+            attempt,
+            max_attempts,
+        } => Some(i18n::tr_args(
+            "event-retrying",
+            &[("attempt", &attempt.to_string()), ("max", &max_attempts.to_string())],
+        )),
+        OrchestratorEvent::TaskStarted { .. }
+        | OrchestratorEvent::ConfirmationRequested { .. }
+        | OrchestratorEvent::GateFinished { .. }
+        | OrchestratorEvent::TaskFinished { .. }
+        | OrchestratorEvent::LlmResponded { .. }
+        | OrchestratorEvent::LlmFailed { .. }
+        | OrchestratorEvent::Error { .. } => None,
     }
 }
@@ -0,0 +1,301 @@
+use clap::{Arg, ArgAction, ArgMatches, Command, ValueHint};
+
+use crate::cli::completions;
+use crate::config::{AgentMode, Config, secrets};
+
+/// `codepilot` with no subcommand opens the TUI; the subcommands run without it.
+pub fn command() -> Command {
+    Command::new("codepilot")
+        .about("Verification-gated code edits for JS/TS repos")
+        .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("read-only")
+                .long("read-only")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Refuse every file write, whatever the mode, as READ_ONLY does"),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Create or check the settings in .env")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("init")
+                        .about("Write an annotated .env to fill in")
+                        .arg(
+                            Arg::new("path")
+                                .long("path")
+                                .value_name("PATH")
+                                .value_hint(ValueHint::FilePath)
+                                .default_value(".env"),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .action(ArgAction::SetTrue)
+                                .help("Overwrite the file if it exists"),
+                        ),
+                )
+                .subcommand(Command::new("validate").about("Check the settings without touching the network"))
+                .subcommand(
+                    Command::new("doctor").about("Validate, then test the LLM connection and the repo's tsc"),
+                ),
+        )
+        .subcommand(
+            Command::new("auth")
+                .about("Keep the API key or state passphrase in the OS keyring instead of .env")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("set")
+                        .about("Save a secret; you're asked for it, or it's read from piped stdin")
+                        .arg(service_arg()),
+                )
+                .subcommand(Command::new("clear").about("Delete a saved secret").arg(service_arg())),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Check that the LLM provider answers; exits 5 for a bad key, 6 if it's unreachable")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print provider, model, reachable, latency_ms, and error as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve a JSON API: POST /query, GET /services, GET /sessions/:id")
+                .arg(
+                    Arg::new("addr")
+                        .long("addr")
+                        .value_name("HOST:PORT")
+                        .default_value("127.0.0.1:7878")
+                        .help("Where to listen; keep it on localhost unless you trust the network"),
+                )
+                .args(run_args()),
+        )
+        .subcommand(
+            Command::new("stdio")
+                .about("Speak newline-delimited JSON on stdin/stdout, for editor extensions")
+                .args(run_args()),
+        )
+        .subcommand(
+            Command::new("repl")
+                .about("Read tasks line by line from stdin, without the full-screen TUI")
+                .args(run_args()),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Run the tasks in a script file one after another")
+                .after_help(
+                    "One task per line; blank lines and # comments are skipped, `confirm: <task>` asks \
+                     before running, and indented lines continue the task above. Exits like `ask` \
+                     for the first step that doesn't succeed.",
+                )
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_hint(ValueHint::FilePath)
+                        .help("The script to run"),
+                )
+                .arg(
+                    Arg::new("save")
+                        .long("save")
+                        .value_name("PATH")
+                        .value_hint(ValueHint::FilePath)
+                        .help("Write every step's result to PATH as a JSON array"),
+                )
+                .arg(
+                    Arg::new("keep-going")
+                        .long("keep-going")
+                        .action(ArgAction::SetTrue)
+                        .help("Carry on after a step fails instead of stopping"),
+                )
+                .args(run_args()),
+        )
+        .subcommand(
+            Command::new("ask")
+                .about("Run one task without the TUI, print the outcome, and exit")
+                .after_help(
+                    "Exit status: 0 if the edit was written and verified (or planned), 3 if every \
+                     attempt failed verification and was reverted, 4 for a configuration error, 5 if \
+                     the provider refused the API key, 6 if the LLM request failed otherwise, 7 if an \
+                     edit needing approval was declined, and 1 for any other failure.",
+                )
+                .arg(
+                    Arg::new("task")
+                        .required(true)
+                        .num_args(1..)
+                        .help("What to change, in plain words; - reads it from stdin"),
+                )
+                .args(run_args())
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print one JSON report (status, edit, usage, events) instead of text"),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Look through saved sessions (needs SAVE_STATE_DIR)")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("search")
+                        .about("Search the messages of every saved session")
+                        .arg(Arg::new("query").required(true).num_args(1..).help("Text to look for, ignoring case"))
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .action(ArgAction::SetTrue)
+                                .help("Print the matches as a JSON array"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("sessions")
+                .about("List, export, or import saved sessions (needs SAVE_STATE_DIR)")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("Show saved sessions and their ids, newest first"))
+                .subcommand(
+                    Command::new("export")
+                        .about("Write one session, with its edits and secrets redacted, to a file to share")
+                        .arg(Arg::new("id").required(true).help("The session's id, from `sessions list`"))
+                        .arg(
+                            Arg::new("out")
+                                .long("out")
+                                .short('o')
+                                .value_name("FILE")
+                                .value_hint(ValueHint::FilePath)
+                                .help("Where to write it [default: <id>.json]"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("Add a session from a `sessions export` file")
+                        .arg(Arg::new("file").required(true).value_hint(ValueHint::FilePath)),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Task counts, failure rate, tools run, and latency across saved sessions (needs SAVE_STATE_DIR)")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the numbers as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a shell completion script")
+                .arg(Arg::new("shell").required(true).value_parser(completions::SHELLS)),
+        )
+}
+
+fn service_arg() -> Arg {
+    Arg::new("service")
+        .value_parser(secrets::ACCOUNTS)
+        .default_value("openai")
+}
+
+/// Flags shared by every subcommand that runs tasks; see `apply_overrides`.
+fn run_args() -> [Arg; 2] {
+    [
+        Arg::new("mode")
+            .long("mode")
+            .value_name("MODE")
+            .value_parser(["ask", "agent", "plan"])
+            .ignore_case(true)
+            .help("Agent mode for this run, instead of AGENT_MODE"),
+        Arg::new("yes")
+            .long("yes")
+            .short('y')
+            .action(ArgAction::SetTrue)
+            .help("Approve edits that would otherwise ask first"),
+    ]
+}
+
+/// `config` with the flags from `args` applied on top of the environment.
+/// Flags `args` doesn't define are skipped, so the bare command's matches
+/// (only `--read-only`) work too.
+pub fn apply_overrides(mut config: Config, args: &ArgMatches) -> anyhow::Result<Config> {
+    if let Ok(Some(mode)) = args.try_get_one::<String>("mode") {
+        config.agent_mode = AgentMode::parse(mode)
+            .ok_or_else(|| anyhow::anyhow!("unknown mode '{mode}' (try ask, agent, plan)"))?;
+    }
+    if let Ok(Some(true)) = args.try_get_one::<bool>("yes") {
+        config.auto_approve = true;
+    }
+    if args.get_flag("read-only") {
+        config.read_only = true;
+    }
+    Ok(config)
+}
+
+/// The task words joined back up, so `codepilot ask add a sum function` works
+/// without quotes. A lone `-` reads the task from stdin instead.
+pub fn task(args: &ArgMatches) -> anyhow::Result<String> {
+    let task = args
+        .get_many::<String>("task")
+        .map(|words| words.map(String::as_str).collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    if task == "-" {
+        read_task(std::io::stdin())
+    } else {
+        Ok(task)
+    }
+}
+
+/// A task piped in, e.g. `echo "add a sum function" | codepilot ask -`.
+pub fn read_task(mut input: impl std::io::Read) -> anyhow::Result<String> {
+    let mut task = String::new();
+    input.read_to_string(&mut task)?;
+    let task = task.trim();
+    if task.is_empty() {
+        anyhow::bail!("no task on stdin");
+    }
+    Ok(task.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ask_takes_an_unquoted_task_and_flags() {
+        let matches = command()
+            .try_get_matches_from(["codepilot", "ask", "--mode", "plan", "add", "a", "sum"])
+            .unwrap();
+        let (name, ask) = matches.subcommand().unwrap();
+        assert_eq!(name, "ask");
+        assert_eq!(task(ask).unwrap(), "add a sum");
+        assert_eq!(
+            ask.get_one::<String>("mode").map(String::as_str),
+            Some("plan")
+        );
+        assert!(!ask.get_flag("yes"));
+        assert!(!ask.get_flag("json"));
+        assert!(
+            command()
+                .try_get_matches_from(["codepilot", "ask"])
+                .is_err()
+        );
+
+        let matches = command()
+            .try_get_matches_from(["codepilot", "ask", "--read-only", "add", "a", "sum"])
+            .unwrap();
+        assert!(matches.subcommand().unwrap().1.get_flag("read-only"));
+        let bare = command()
+            .try_get_matches_from(["codepilot", "--read-only"])
+            .unwrap();
+        let config = apply_overrides(Config::from_vars([]), &bare).unwrap();
+        assert!(config.read_only);
+    }
+
+    #[test]
+    fn read_task_trims_and_rejects_empty_input() {
+        assert_eq!(read_task("  add a sum\n".as_bytes()).unwrap(), "add a sum");
+        assert!(read_task("\n".as_bytes()).is_err());
+    }
+}
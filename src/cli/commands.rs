@@ -0,0 +1,117 @@
+use crate::i18n;
+
+/// A `/command` typed into the input box. Handled by the TUI itself instead of
+/// being sent to the orchestrator as a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlashCommand {
+    pub name: &'static str,
+    pub usage: &'static str,
+}
+
+impl SlashCommand {
+    /// What the command does, in the configured locale.
+    pub fn description(&self) -> String {
+        i18n::tr(&format!("command-{}", self.name))
+    }
+}
+
+pub const COMMANDS: [SlashCommand; 13] = [
+    SlashCommand {
+        name: "help",
+        usage: "/help",
+    },
+    SlashCommand {
+        name: "clear",
+        usage: "/clear",
+    },
+    SlashCommand {
+        name: "model",
+        usage: "/model [name]",
+    },
+    SlashCommand {
+        name: "mode",
+        usage: "/mode [ask|agent|plan]",
+    },
+    SlashCommand {
+        name: "dryrun",
+        usage: "/dryrun",
+    },
+    SlashCommand {
+        name: "theme",
+        usage: "/theme [name]",
+    },
+    SlashCommand {
+        name: "filter",
+        usage: "/filter [kind=.. agent=..|conversation|tools]",
+    },
+    SlashCommand {
+        name: "export",
+        usage: "/export [md|json|csv|tsv] [path]",
+    },
+    SlashCommand {
+        name: "history",
+        usage: "/history <text>",
+    },
+    SlashCommand {
+        name: "rename",
+        usage: "/rename [name]",
+    },
+    SlashCommand {
+        name: "bookmarks",
+        usage: "/bookmarks",
+    },
+    SlashCommand {
+        name: "stats",
+        usage: "/stats",
+    },
+    SlashCommand {
+        name: "reset-state",
+        usage: "/reset-state [confirm]",
+    },
+];
+
+/// Split "/model gpt-4o" into `("model", "gpt-4o")`. `None` if the input isn't
+/// a slash command at all, i.e. is an ordinary task.
+pub fn parse(input: &str) -> Option<(&str, &str)> {
+    let rest = input.trim().strip_prefix('/')?;
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((name, args.trim()))
+}
+
+pub fn find(name: &str) -> Option<&'static SlashCommand> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Commands matching what's been typed so far: every command whose name starts
+/// with the partial name, or just the one command once its arguments begin.
+pub fn matching(input: &str) -> Vec<&'static SlashCommand> {
+    let Some(rest) = input.trim_start().strip_prefix('/') else {
+        return Vec::new();
+    };
+    match rest.split_once(char::is_whitespace) {
+        Some((name, _)) => find(name).into_iter().collect(),
+        None => COMMANDS
+            .iter()
+            .filter(|command| command.name.starts_with(rest))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_arguments() {
+        assert_eq!(parse("/model gpt-4o"), Some(("model", "gpt-4o")));
+        assert_eq!(parse("  /clear "), Some(("clear", "")));
+        assert_eq!(parse("add a sum function"), None);
+    }
+
+    #[test]
+    fn matches_prefixes_then_the_typed_command() {
+        assert_eq!(matching("/mo").len(), 2);
+        assert_eq!(matching("/model x").len(), 1);
+        assert!(matching("model").is_empty());
+    }
+}
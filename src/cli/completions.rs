@@ -0,0 +1,257 @@
+use clap::{Arg, Command, ValueHint};
+
+/// Shells `codepilot completions` can write a script for.
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// A completion script for `shell`, generated from `command` so it can't drift
+/// from the real flags. Install it with e.g.
+/// `codepilot completions bash > ~/.local/share/bash-completion/completions/codepilot`,
+/// `source <(codepilot completions zsh)` in `.zshrc`, or
+/// `codepilot completions fish > ~/.config/fish/completions/codepilot.fish`.
+pub fn generate(shell: &str, mut command: Command) -> Option<String> {
+    command.build();
+    let mut nodes = Vec::new();
+    walk(&command, vec![command.get_name()], &mut nodes);
+    match shell {
+        "bash" => Some(bash(&nodes)),
+        // zsh runs the bash script through its compatibility layer, which covers
+        // everything the bash one does.
+        "zsh" => Some(format!(
+            "autoload -U +X bashcompinit && bashcompinit\n{}",
+            bash(&nodes)
+        )),
+        "fish" => Some(fish(&nodes)),
+        _ => None,
+    }
+}
+
+/// One command or subcommand, with the words that lead to it.
+struct Node<'a> {
+    path: Vec<&'a str>,
+    command: &'a Command,
+}
+
+impl Node<'_> {
+    fn subcommands(&self) -> impl Iterator<Item = &Command> {
+        self.command
+            .get_subcommands()
+            .filter(|sub| !sub.is_hide_set())
+    }
+
+    fn options(&self) -> impl Iterator<Item = &Arg> {
+        self.command
+            .get_arguments()
+            .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+    }
+
+    /// Fixed choices for positional arguments, like `completions`' shell.
+    fn positional_values(&self) -> Vec<String> {
+        self.command
+            .get_arguments()
+            .filter(|arg| arg.is_positional())
+            .flat_map(possible_values)
+            .collect()
+    }
+
+    /// Whether a positional argument here is a file, like `run`'s script.
+    fn takes_file(&self) -> bool {
+        self.command
+            .get_arguments()
+            .any(|arg| arg.is_positional() && is_path(arg))
+    }
+}
+
+fn walk<'a>(command: &'a Command, path: Vec<&'a str>, nodes: &mut Vec<Node<'a>>) {
+    nodes.push(Node {
+        path: path.clone(),
+        command,
+    });
+    // `help <subcommand>` mirrors the whole tree; its own name is enough.
+    for sub in command
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+    {
+        let mut path = path.clone();
+        path.push(sub.get_name());
+        walk(sub, path, nodes);
+    }
+}
+
+fn is_path(arg: &Arg) -> bool {
+    matches!(
+        arg.get_value_hint(),
+        ValueHint::FilePath | ValueHint::AnyPath | ValueHint::DirPath
+    )
+}
+
+fn possible_values(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values()
+        .iter()
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn flags(arg: &Arg) -> Vec<String> {
+    let long = arg.get_long().map(|long| format!("--{long}"));
+    let short = arg.get_short().map(|short| format!("-{short}"));
+    long.into_iter().chain(short).collect()
+}
+
+fn bash(nodes: &[Node]) -> String {
+    let paths = nodes[1..]
+        .iter()
+        .map(|node| format!("\"{}\"", node.path.join(" ")))
+        .collect::<Vec<_>>();
+
+    // What follows an option that takes a value, whichever subcommand it's on.
+    let mut values = Vec::new();
+    let mut seen = Vec::new();
+    for arg in nodes.iter().flat_map(|node| node.options()) {
+        if !arg.get_action().takes_values() {
+            continue;
+        }
+        let names = flags(arg);
+        if names.iter().any(|name| seen.contains(name)) {
+            continue;
+        }
+        seen.extend(names.iter().cloned());
+        let reply = match possible_values(arg) {
+            candidates if !candidates.is_empty() => {
+                format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                    candidates.join(" ")
+                )
+            }
+            _ if is_path(arg) => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+            _ => "COMPREPLY=()".to_string(),
+        };
+        values.push(format!("        {}) {reply}; return ;;", names.join("|")));
+    }
+
+    let words = nodes.iter().map(|node| {
+        let words = node
+            .subcommands()
+            .map(|sub| sub.get_name().to_string())
+            .chain(node.positional_values())
+            .chain(node.options().flat_map(flags))
+            .collect::<Vec<_>>();
+        let files = if node.takes_file() { " -f" } else { "" };
+        format!(
+            "        \"{}\") COMPREPLY=($(compgen -W \"{}\"{files} -- \"$cur\")) ;;",
+            node.path.join(" "),
+            words.join(" ")
+        )
+    });
+
+    format!(
+        r#"_codepilot() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}" path="codepilot" word
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        case "$path $word" in
+            {}) path="$path $word" ;;
+        esac
+    done
+    case "$prev" in
+{}
+    esac
+    case "$path" in
+{}
+    esac
+}}
+complete -F _codepilot codepilot
+"#,
+        paths.join("|"),
+        values.join("\n"),
+        words.collect::<Vec<_>>().join("\n")
+    )
+}
+
+fn fish(nodes: &[Node]) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    let about = |text: Option<&clap::builder::StyledStr>| {
+        text.map(|text| format!(" -d {}", quote(&text.to_string())))
+            .unwrap_or_default()
+    };
+
+    let mut lines = vec!["complete -c codepilot -f".to_string()];
+    for node in nodes {
+        let children = node
+            .subcommands()
+            .map(Command::get_name)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let here = match node.path.last() {
+            Some(name) if node.path.len() > 1 => format!("__fish_seen_subcommand_from {name}"),
+            _ => "__fish_use_subcommand".to_string(),
+        };
+        // Subcommand names only until one of them has been typed.
+        let choosing = if node.path.len() > 1 && !children.is_empty() {
+            format!("{here}; and not __fish_seen_subcommand_from {children}")
+        } else {
+            here.clone()
+        };
+
+        for sub in node.subcommands() {
+            lines.push(format!(
+                "complete -c codepilot -n {} -a {}{}",
+                quote(&choosing),
+                sub.get_name(),
+                about(sub.get_about())
+            ));
+        }
+        for arg in node.options() {
+            let mut line = format!("complete -c codepilot -n {}", quote(&here));
+            if let Some(long) = arg.get_long() {
+                line.push_str(&format!(" -l {long}"));
+            }
+            if let Some(short) = arg.get_short() {
+                line.push_str(&format!(" -s {short}"));
+            }
+            if arg.get_action().takes_values() {
+                match possible_values(arg) {
+                    candidates if !candidates.is_empty() => {
+                        line.push_str(&format!(" -x -a {}", quote(&candidates.join(" "))));
+                    }
+                    _ if is_path(arg) => line.push_str(" -r -F"),
+                    _ => line.push_str(" -x"),
+                }
+            }
+            line.push_str(&about(arg.get_help()));
+            lines.push(line);
+        }
+        let values = node.positional_values();
+        if !values.is_empty() {
+            lines.push(format!(
+                "complete -c codepilot -n {} -a {}",
+                quote(&here),
+                quote(&values.join(" "))
+            ));
+        }
+        if node.takes_file() {
+            lines.push(format!("complete -c codepilot -n {} -F", quote(&here)));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_cover_nested_subcommands_and_option_values() {
+        let bash = generate("bash", crate::cli::args::command()).unwrap();
+        assert!(bash.contains("\"codepilot config init\""));
+        assert!(bash.contains("--mode) COMPREPLY=($(compgen -W \"ask agent plan\""));
+        assert!(bash.contains("\"codepilot run\") COMPREPLY=($(compgen -W \""));
+        assert!(
+            bash.contains("\"codepilot completions\") COMPREPLY=($(compgen -W \"bash zsh fish")
+        );
+
+        let fish = generate("fish", crate::cli::args::command()).unwrap();
+        assert!(fish.contains("-n '__fish_seen_subcommand_from ask' -l json"));
+        assert!(fish.contains("-n '__fish_seen_subcommand_from run' -F"));
+        assert!(generate("powershell", crate::cli::args::command()).is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::cli::headless::Failure;
+use crate::cli::keymap::Keymap;
+use crate::cli::theme::Theme;
+use crate::config::Config;
+use crate::orchestrator::CodeTaskOrchestrator;
+use crate::runs::{self, RunKind, RunStatus};
+
+/// The annotated settings file `config init` writes.
+const TEMPLATE: &str = include_str!("../../.env.example");
+
+/// What a check found. Failures carry what to do about them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Ok(String),
+    /// Works, but probably not the way it was meant to.
+    Warn(String),
+    Fail {
+        problem: String,
+        fix: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+fn check(name: &'static str, outcome: Outcome) -> Check {
+    Check { name, outcome }
+}
+
+fn fail(problem: impl Into<String>, fix: impl Into<String>) -> Outcome {
+    Outcome::Fail {
+        problem: problem.into(),
+        fix: fix.into(),
+    }
+}
+
+/// Whether the LLM provider answers, for `codepilot status`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ProviderStatus {
+    pub provider: String,
+    pub model: String,
+    pub reachable: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub failure: Option<Failure>,
+}
+
+impl ProviderStatus {
+    /// Look up the configured model, timing the round trip.
+    pub async fn check(config: &Config) -> Self {
+        let started = Instant::now();
+        let connection = match CodeTaskOrchestrator::new(config).await {
+            Ok(orchestrator) => orchestrator.check_connection().await,
+            Err(e) => Err(e),
+        };
+        Self {
+            provider: crate::cli::app::provider_host(config.openai_base_url.as_deref()),
+            model: config.model.clone(),
+            reachable: connection.is_ok(),
+            latency_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            error: connection.as_ref().err().map(|e| e.to_string()),
+            failure: connection.as_ref().err().map(Failure::of),
+        }
+    }
+
+    /// `provider  model  up  120 ms` - one line, easy to grep from a cron log.
+    pub fn line(&self) -> String {
+        let state = match &self.error {
+            None => "up".to_string(),
+            Some(error) => format!("down: {error}"),
+        };
+        format!(
+            "{}  {}  {}  {} ms",
+            self.provider, self.model, state, self.latency_ms
+        )
+    }
+}
+
+/// `config init`: write the annotated template to `path`, keeping an existing
+/// file unless `force`.
+pub fn init(path: &Path, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    std::fs::write(path, TEMPLATE)?;
+    Ok(())
+}
+
+/// `config validate`: everything that can be checked without the network.
+pub fn validate(config: &Config) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    checks.push(check(
+        "API key",
+        match config.openai_api_key.as_deref().map(str::trim) {
+            None | Some("") => fail(
+                "OPENAI_API_KEY is not set",
+                "add it to .env, or start codepilot for the setup form",
+            ),
+            Some(key) if key.starts_with("your_") => fail(
+                "OPENAI_API_KEY is still the template placeholder",
+                "replace it with a real key in .env",
+            ),
+            Some(_) => Outcome::Ok("set".to_string()),
+        },
+    ));
+
+    checks.push(check(
+        "Base URL",
+        match config.openai_base_url.as_deref() {
+            None => Outcome::Ok("default (api.openai.com)".to_string()),
+            Some(url) if url.starts_with("https://") => Outcome::Ok(url.to_string()),
+            Some(url) if url.starts_with("http://") => Outcome::Warn(format!("{url} is not https")),
+            Some(url) => fail(
+                format!("OPENAI_BASE_URL '{url}' is not a URL"),
+                "use the full endpoint, e.g. https://api.openai.com/v1",
+            ),
+        },
+    ));
+
+    checks.push(check(
+        "Model",
+        if config.model.trim().is_empty() {
+            fail("OPENAI_MODEL is empty", "set it, e.g. OPENAI_MODEL=gpt-4o")
+        } else {
+            Outcome::Ok(config.model.clone())
+        },
+    ));
+
+    let repo = Path::new(&config.target_repo_path);
+    checks.push(check(
+        "Target repo",
+        if !repo.is_dir() {
+            fail(
+                format!("TARGET_REPO_PATH '{}' is not a directory", repo.display()),
+                "point it at the JS/TS project to edit",
+            )
+        } else if !repo.join("tsconfig.json").is_file() {
+            Outcome::Warn(format!(
+                "no tsconfig.json in {}; tsc checks with its defaults",
+                repo.display()
+            ))
+        } else {
+            Outcome::Ok(repo.display().to_string())
+        },
+    ));
+
+    let theme = match Theme::named(&config.theme) {
+        None => fail(
+            format!("unknown THEME '{}'", config.theme),
+            "use dark, light, or solarized",
+        ),
+        Some(theme) => match theme.with_overrides(&config.theme_overrides).1.as_slice() {
+            [] => Outcome::Ok(config.theme.clone()),
+            rejected => Outcome::Warn(format!("ignored overrides: {}", rejected.join(", "))),
+        },
+    };
+    checks.push(check("Theme", theme));
+
+    let keymap = match Keymap::named(&config.keymap) {
+        None => fail(
+            format!("unknown KEYMAP '{}'", config.keymap),
+            "use default or vim",
+        ),
+        Some(keymap) => match keymap.with_overrides(&config.keymap_overrides).1.as_slice() {
+            [] => Outcome::Ok(config.keymap.clone()),
+            rejected => Outcome::Warn(format!("ignored overrides: {}", rejected.join(", "))),
+        },
+    };
+    checks.push(check("Keymap", keymap));
+
+    let mut unusable: Vec<String> = config
+        .unreadable
+        .iter()
+        .map(|value| format!("{value} is not a valid value"))
+        .collect();
+    if config.max_loops == 0 {
+        unusable.push("MAX_LOOPS is 0".to_string());
+    }
+    if config.encrypt_state && config.save_state_dir.is_none() {
+        unusable.push("ENCRYPT_STATE without SAVE_STATE_DIR".to_string());
+    }
+    if config.record_llm && config.replay_llm {
+        unusable.push("both CODEPILOT_RECORD and CODEPILOT_REPLAY".to_string());
+    }
+    if config
+        .redact_patterns
+        .as_deref()
+        .is_some_and(|pattern| regex::Regex::new(pattern).is_err())
+    {
+        unusable.push("REDACT_PATTERNS is not a valid regex".to_string());
+    }
+    checks.push(check(
+        "Settings",
+        if unusable.is_empty() {
+            Outcome::Ok("readable and consistent".to_string())
+        } else {
+            fail(
+                unusable.join("; "),
+                "fix or remove them in .env; see .env.example for the accepted values",
+            )
+        },
+    ));
+
+    if let Some(dir) = &config.save_state_dir {
+        checks.push(check(
+            "State directory",
+            match Path::new(dir) {
+                path if path.is_dir() => Outcome::Ok(dir.clone()),
+                path if path.exists() => fail(
+                    format!("SAVE_STATE_DIR '{dir}' is a file"),
+                    "point it at a directory, or leave it unset to keep no history",
+                ),
+                _ => Outcome::Ok(format!("{dir} (created on first save)")),
+            },
+        ));
+    }
+
+    checks
+}
+
+/// `config doctor`: `validate`, then live checks against the LLM provider and
+/// the repo's `tsc`. The live ones are skipped while the settings can't work.
+pub async fn doctor(config: &Config) -> Vec<Check> {
+    let mut checks = validate(config);
+    if config.validate().is_err() {
+        return checks;
+    }
+
+    let status = ProviderStatus::check(config).await;
+    checks.push(check(
+        "LLM connection",
+        match status.error {
+            None => Outcome::Ok(format!("{} answered in {} ms", status.model, status.latency_ms)),
+            Some(error) => fail(
+                error,
+                "check OPENAI_API_KEY, OPENAI_BASE_URL, and that OPENAI_MODEL exists on that provider",
+            ),
+        },
+    ));
+
+    let repo = Path::new(&config.target_repo_path);
+    checks.push(check(
+        "tsc",
+        match runs::execute(RunKind::TypeCheck, repo).await {
+            Ok(RunStatus::Succeeded | RunStatus::Skipped) => {
+                Outcome::Ok("the repo typechecks".to_string())
+            }
+            Ok(RunStatus::Failed(output)) => Outcome::Warn(format!(
+                "the repo doesn't typecheck yet ({}); edits that leave it failing are rejected",
+                output.lines().next().unwrap_or("no output")
+            )),
+            Err(e) => fail(
+                format!("couldn't run tsc: {e}"),
+                "install TypeScript in the repo: npm i -D typescript",
+            ),
+        },
+    ));
+
+    checks
+}
+
+/// Print one line per check, fixes indented under failures. Fails if any check did.
+pub fn report(checks: &[Check]) -> ExitCode {
+    let mut failed = false;
+    for check in checks {
+        match &check.outcome {
+            Outcome::Ok(detail) => println!("✓ {:<16} {detail}", check.name),
+            Outcome::Warn(detail) => println!("! {:<16} {detail}", check.name),
+            Outcome::Fail { problem, fix } => {
+                failed = true;
+                println!("✗ {:<16} {problem}\n  {:<16} → {fix}", check.name, "");
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_flags_placeholders_and_bad_urls_with_fixes() {
+        let mut config = Config::from_vars([]);
+        config.openai_api_key = Some("your_openai_api_key_here".to_string());
+        config.openai_base_url = Some("api.openai.com".to_string());
+        config.theme = "sepia".to_string();
+        config.unreadable = vec!["MAX_LOOPS='ten'".to_string()];
+
+        let checks = validate(&config);
+        let outcome = |name| {
+            &checks
+                .iter()
+                .find(|check| check.name == name)
+                .unwrap()
+                .outcome
+        };
+        assert!(
+            matches!(outcome("API key"), Outcome::Fail { problem, .. } if problem.contains("placeholder"))
+        );
+        assert!(
+            matches!(outcome("Base URL"), Outcome::Fail { fix, .. } if fix.contains("https://"))
+        );
+        assert!(matches!(outcome("Theme"), Outcome::Fail { .. }));
+        assert!(
+            matches!(outcome("Settings"), Outcome::Fail { problem, .. } if problem.contains("MAX_LOOPS='ten'"))
+        );
+    }
+}
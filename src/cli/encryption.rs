@@ -0,0 +1,376 @@
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::config::{Config, secrets};
+use crate::error::CodepilotError;
+
+/// Marks sealed text, so files written before encryption was turned on still
+/// read as they are.
+const PREFIX: &str = "enc1:";
+
+/// Sealed into the header to tell a wrong passphrase from a right one.
+const CHECK: &str = "codepilot";
+
+const PBKDF2_ROUNDS: u32 = 200_000;
+
+/// The key everything under `SAVE_STATE_DIR` is sealed with, once `setup`
+/// unlocks it. Unset means state is stored in the clear.
+static KEY: OnceLock<LessSafeKey> = OnceLock::new();
+
+/// `{save_state_dir}/encryption.json`: the salt the key is derived with, and a
+/// sealed check value. Nothing in it is secret.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Header {
+    salt: String,
+    check: String,
+}
+
+pub fn header_path(save_state_dir: &str) -> PathBuf {
+    Path::new(save_state_dir).join("encryption.json")
+}
+
+/// With `ENCRYPT_STATE` on, ask for the passphrase and unlock the state in
+/// `SAVE_STATE_DIR`, sealing anything there that's still in the clear. Run
+/// before anything reads or writes saved state.
+pub fn setup(config: &Config) -> Result<()> {
+    let Some(dir) = config.save_state_dir.as_deref() else {
+        return Ok(());
+    };
+    let header = header_path(dir);
+    if !config.encrypt_state {
+        if header.exists() {
+            anyhow::bail!(CodepilotError::Config(format!(
+                "the saved state in {dir} is encrypted; set ENCRYPT_STATE=true to use it"
+            )));
+        }
+        return Ok(());
+    }
+    let passphrase = passphrase(dir)?;
+    let key = unlock(dir, &passphrase)?;
+    // Every time, not just when the header is new: a run that stopped halfway
+    // through left the rest in the clear.
+    encrypt_existing(dir, &key)?;
+    let _ = KEY.set(key);
+    Ok(())
+}
+
+/// `STATE_PASSPHRASE`, else the one `codepilot auth set state` saved, else
+/// asked for on the terminal.
+fn passphrase(dir: &str) -> Result<String> {
+    if let Some(passphrase) = std::env::var("STATE_PASSPHRASE")
+        .ok()
+        .filter(|value| !value.is_empty())
+    {
+        return Ok(passphrase);
+    }
+    if let Some(passphrase) = secrets::lookup("state") {
+        return Ok(passphrase);
+    }
+    if !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        anyhow::bail!(CodepilotError::Config(
+            "ENCRYPT_STATE is on but there's no passphrase: set STATE_PASSPHRASE or run `codepilot auth set state`"
+                .to_string()
+        ));
+    }
+    if header_path(dir).exists() {
+        return prompt(&format!("Passphrase for {dir}: "));
+    }
+    // A typo in a new passphrase would lock the state away for good.
+    let passphrase = prompt(&format!("New passphrase for {dir}: "))?;
+    if prompt("Type it again: ")? != passphrase {
+        anyhow::bail!(CodepilotError::Config(
+            "the passphrases didn't match; nothing was encrypted".to_string()
+        ));
+    }
+    Ok(passphrase)
+}
+
+/// Read a line from the terminal without echoing it.
+fn prompt(label: &str) -> Result<String> {
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::crossterm::terminal;
+
+    print!("{label}");
+    std::io::stdout().flush()?;
+    terminal::enable_raw_mode()?;
+    let mut passphrase = String::new();
+    let read = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("cancelled"));
+                }
+                KeyCode::Char(c) => passphrase.push(c),
+                KeyCode::Backspace => {
+                    passphrase.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    println!();
+    read.map(|()| passphrase)
+}
+
+/// The key for `dir`: checked against its header, or with a new header and
+/// salt if it has none yet.
+fn unlock(dir: &str, passphrase: &str) -> Result<LessSafeKey> {
+    let path = header_path(dir);
+    if let Ok(text) = std::fs::read_to_string(&path) {
+        let header: Header = serde_json::from_str(&text)?;
+        let salt = from_hex(&header.salt)
+            .ok_or_else(|| anyhow::anyhow!("{} is damaged", path.display()))?;
+        let key = derive(passphrase, &salt);
+        if open_with(&key, &header.check).ok().as_deref() != Some(CHECK) {
+            anyhow::bail!(CodepilotError::Config(format!(
+                "wrong passphrase for the saved state in {dir}"
+            )));
+        }
+        return Ok(key);
+    }
+
+    let mut salt = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| anyhow::anyhow!("no randomness available"))?;
+    let key = derive(passphrase, &salt);
+    let header = Header {
+        salt: to_hex(&salt),
+        check: seal_with(&key, CHECK),
+    };
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(path, serde_json::to_string_pretty(&header)?)?;
+    Ok(key)
+}
+
+fn derive(passphrase: &str, salt: &[u8]) -> LessSafeKey {
+    let mut bytes = [0u8; 32];
+    let rounds = NonZeroU32::new(PBKDF2_ROUNDS).expect("non-zero");
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        rounds,
+        salt,
+        passphrase.as_bytes(),
+        &mut bytes,
+    );
+    LessSafeKey::new(UnboundKey::new(&aead::CHACHA20_POLY1305, &bytes).expect("32-byte key"))
+}
+
+/// Seal every file in `dir` that isn't yet, so turning encryption on doesn't
+/// leave earlier history or logs readable. Files written a line at a time -
+/// JSON-lines history and the logs in `logs/` - are sealed line by line.
+/// What's already sealed is left alone, so running it again is harmless.
+fn encrypt_existing(dir: &str, key: &LessSafeKey) -> Result<()> {
+    let root = Path::new(dir);
+    let mut files = vec![
+        (root.join("history.jsonl"), true),
+        (root.join("input_history.jsonl"), true),
+        (root.join("favorites.json"), false),
+        (root.join("bookmarks.json"), false),
+    ];
+    if let Ok(sessions) = std::fs::read_dir(root.join("sessions")) {
+        files.extend(sessions.filter_map(|entry| Some((entry.ok()?.path(), false))));
+    }
+    if let Ok(logs) = std::fs::read_dir(root.join("logs")) {
+        files.extend(logs.filter_map(|entry| Some((entry.ok()?.path(), true))));
+    }
+    for (file, by_line) in files.iter().filter(|(file, _)| file.is_file()) {
+        let text = std::fs::read_to_string(file)?;
+        let seal_once = |text: &str| {
+            if text.starts_with(PREFIX) {
+                text.to_string()
+            } else {
+                seal_with(key, text)
+            }
+        };
+        let sealed = if *by_line {
+            text.lines().map(|line| seal_once(line) + "\n").collect()
+        } else {
+            seal_once(&text)
+        };
+        if sealed != text {
+            std::fs::write(file, sealed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `setup` has unlocked a key in this process.
+pub fn unlocked() -> bool {
+    KEY.get().is_some()
+}
+
+/// `text` as it should be written to disk: sealed once `setup` has unlocked a
+/// key, unchanged otherwise.
+pub fn seal(text: &str) -> String {
+    match KEY.get() {
+        Some(key) => seal_with(key, text),
+        None => text.to_string(),
+    }
+}
+
+/// The reverse of `seal`. Text that was never sealed comes back as it is.
+pub fn open(text: &str) -> Result<String> {
+    if !text.starts_with(PREFIX) {
+        return Ok(text.to_string());
+    }
+    let Some(key) = KEY.get() else {
+        anyhow::bail!("this state is encrypted; set ENCRYPT_STATE=true");
+    };
+    open_with(key, text)
+}
+
+fn seal_with(key: &LessSafeKey, text: &str) -> String {
+    let mut nonce = [0u8; aead::NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .expect("randomness available");
+    let mut sealed = text.as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::empty(),
+        &mut sealed,
+    )
+    .expect("message within ChaCha20-Poly1305 limits");
+    format!("{PREFIX}{}{}", to_hex(&nonce), to_hex(&sealed))
+}
+
+fn open_with(key: &LessSafeKey, text: &str) -> Result<String> {
+    let bytes = text
+        .trim_end()
+        .strip_prefix(PREFIX)
+        .and_then(from_hex)
+        .filter(|bytes| bytes.len() >= aead::NONCE_LEN)
+        .ok_or_else(|| anyhow::anyhow!("damaged encrypted state"))?;
+    let (nonce, sealed) = bytes.split_at(aead::NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| anyhow::anyhow!("damaged encrypted state"))?;
+    let mut sealed = sealed.to_vec();
+    let opened = key
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .map_err(|_| anyhow::anyhow!("encrypted state doesn't open with this passphrase"))?;
+    Ok(String::from_utf8(opened.to_vec())?)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_seals_under_the_passphrase_and_checks_it() {
+        let dir =
+            std::env::temp_dir().join(format!("codepilot-test-encryption-{}", std::process::id()));
+        let dir_str = dir.to_str().unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("input_history.jsonl"),
+            "\"add a sum\"\n\"fix the build\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.join("logs")).unwrap();
+        std::fs::write(
+            dir.join("logs/codepilot.log.1"),
+            "{\"task\":\"add a sum\"}\n",
+        )
+        .unwrap();
+
+        let key = unlock(dir_str, "correct horse").unwrap();
+        encrypt_existing(dir_str, &key).unwrap();
+        let history = std::fs::read_to_string(dir.join("input_history.jsonl")).unwrap();
+        assert!(!history.contains("add a sum"));
+        let lines: Vec<String> = history
+            .lines()
+            .map(|line| open_with(&key, line).unwrap())
+            .collect();
+        assert_eq!(lines, ["\"add a sum\"", "\"fix the build\""]);
+        let log = std::fs::read_to_string(dir.join("logs/codepilot.log.1")).unwrap();
+        assert_eq!(
+            open_with(&key, log.trim_end()).unwrap(),
+            "{\"task\":\"add a sum\"}"
+        );
+
+        // A line left in the clear, as by a run stopped halfway, is sealed
+        // next time; the rest isn't sealed twice.
+        std::fs::write(
+            dir.join("input_history.jsonl"),
+            format!("{history}\"add a test\"\n"),
+        )
+        .unwrap();
+        encrypt_existing(dir_str, &key).unwrap();
+        let lines: Vec<String> = std::fs::read_to_string(dir.join("input_history.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| open_with(&key, line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            ["\"add a sum\"", "\"fix the build\"", "\"add a test\""]
+        );
+
+        assert!(unlock(dir_str, "wrong").is_err());
+        let again = unlock(dir_str, "correct horse").unwrap();
+        assert_eq!(
+            open_with(&again, &seal_with(&key, "tsc: passed")).unwrap(),
+            "tsc: passed"
+        );
+        assert_eq!(open("plain text").unwrap(), "plain text");
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn state_saved_after_a_reset_opens_on_the_next_unlock() {
+        let dir = std::env::temp_dir().join(format!("codepilot-test-reset-{}", std::process::id()));
+        let dir_str = dir.to_str().unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = unlock(dir_str, "correct horse").unwrap();
+        std::fs::write(
+            dir.join("favorites.json"),
+            seal_with(&key, "[\"add a sum\"]"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            crate::cli::persistence::reset_files(dir_str, true).unwrap(),
+            1
+        );
+        std::fs::write(
+            dir.join("bookmarks.json"),
+            seal_with(&key, "[\"fix the build\"]"),
+        )
+        .unwrap();
+
+        let again = unlock(dir_str, "correct horse").unwrap();
+        let saved = std::fs::read_to_string(dir.join("bookmarks.json")).unwrap();
+        assert_eq!(open_with(&again, &saved).unwrap(), "[\"fix the build\"]");
+
+        crate::cli::persistence::reset_files(dir_str, false).unwrap();
+        assert!(!header_path(dir_str).exists());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
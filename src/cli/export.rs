@@ -0,0 +1,255 @@
+use std::path::PathBuf;
+
+use crate::cli::message::Message;
+use crate::cli::state::EditDetail;
+use crate::session::SessionTurn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    /// A table from one message's response rather than the whole tab.
+    Csv,
+    Tsv,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+
+    pub fn is_table(&self) -> bool {
+        matches!(self, ExportFormat::Csv | ExportFormat::Tsv)
+    }
+
+    /// `header` and `rows` as CSV (quoted where a cell needs it) or TSV (tabs
+    /// and line breaks in cells turned into spaces).
+    pub fn table(&self, header: &[String], rows: &[Vec<String>]) -> String {
+        let cell = |cell: &String| match self {
+            ExportFormat::Tsv => cell.replace(['\t', '\n', '\r'], " "),
+            _ if cell.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            }
+            _ => cell.clone(),
+        };
+        let separator = if *self == ExportFormat::Tsv {
+            "\t"
+        } else {
+            ","
+        };
+        std::iter::once(header)
+            .chain(rows.iter().map(Vec::as_slice))
+            .map(|row| row.iter().map(cell).collect::<Vec<_>>().join(separator) + "\n")
+            .collect()
+    }
+}
+
+/// `/export` arguments: an optional `md`, `json`, `csv`, or `tsv`, then an
+/// optional path. With no format, it's taken from the path's extension, and
+/// is Markdown for any other; with no path, a timestamped file in the current
+/// directory.
+pub fn parse_args(args: &str) -> (ExportFormat, PathBuf) {
+    let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let (format, path) = match first {
+        "md" | "markdown" => (Some(ExportFormat::Markdown), rest.trim()),
+        "json" => (Some(ExportFormat::Json), rest.trim()),
+        "csv" => (Some(ExportFormat::Csv), rest.trim()),
+        "tsv" => (Some(ExportFormat::Tsv), rest.trim()),
+        _ => (None, args.trim()),
+    };
+    let format = format.unwrap_or(
+        match path.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("json") => ExportFormat::Json,
+            Some("csv") => ExportFormat::Csv,
+            Some("tsv") => ExportFormat::Tsv,
+            _ => ExportFormat::Markdown,
+        },
+    );
+    let path = if path.is_empty() {
+        PathBuf::from(format!(
+            "codepilot-{}.{}",
+            chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+            format.extension()
+        ))
+    } else {
+        PathBuf::from(path)
+    };
+    (format, path)
+}
+
+/// Everything a tab's conversation produced: the activity log, one line per
+/// task with its outcome, and the edits those tasks made.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Conversation<'a> {
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub repo: &'a str,
+    pub model: &'a str,
+    pub tasks: &'a [SessionTurn],
+    pub edits: Vec<&'a EditDetail>,
+    pub messages: &'a [Message],
+}
+
+impl<'a> Conversation<'a> {
+    /// `edits` is narrowed to the ones made by this tab's tasks.
+    pub fn new(
+        repo: &'a str,
+        model: &'a str,
+        tasks: &'a [SessionTurn],
+        edits: &'a [EditDetail],
+        messages: &'a [Message],
+    ) -> Self {
+        Self {
+            exported_at: chrono::Utc::now(),
+            repo,
+            model,
+            tasks,
+            edits: edits
+                .iter()
+                .filter(|edit| tasks.iter().any(|turn| turn.task == edit.task))
+                .collect(),
+            messages,
+        }
+    }
+
+    pub fn render(&self, format: ExportFormat) -> anyhow::Result<String> {
+        Ok(match format {
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Json => serde_json::to_string_pretty(self)?,
+            ExportFormat::Csv | ExportFormat::Tsv => anyhow::bail!("a conversation isn't a table"),
+        })
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut markdown = format!(
+            "# CodePilot conversation\n\nRepo: `{}` · model: `{}` · exported {}\n",
+            self.repo,
+            self.model,
+            self.exported_at.format("%Y-%m-%d %H:%M UTC")
+        );
+        if !self.tasks.is_empty() {
+            markdown.push_str("\n## Tasks\n\n");
+            for turn in self.tasks {
+                markdown.push_str(&format!(
+                    "- {} — {}\n",
+                    turn.task.replace('\n', " "),
+                    turn.outcome
+                ));
+            }
+        }
+        if !self.edits.is_empty() {
+            markdown.push_str("\n## Edits\n");
+            for edit in &self.edits {
+                let status = if edit.planned {
+                    "planned"
+                } else if edit.applied {
+                    "applied"
+                } else {
+                    "rejected"
+                };
+                markdown.push_str(&format!(
+                    "\n### `{}` ({status}, {} attempt{})\n\n{}\n\n```\n{}\n```\n",
+                    edit.path.display(),
+                    edit.attempts,
+                    if edit.attempts == 1 { "" } else { "s" },
+                    edit.verification.as_deref().unwrap_or("not verified"),
+                    edit.content.trim_end()
+                ));
+            }
+        }
+        markdown.push_str("\n## Activity\n\n");
+        for message in self.messages {
+            let source = match &message.agent {
+                Some(agent) => format!("{} · {agent}", message.kind.label()),
+                None => message.kind.label().to_string(),
+            };
+            let body = match &message.response {
+                Some(response) => response.markdown(),
+                None => message.body.clone(),
+            };
+            markdown.push_str(&format!(
+                "- `{}` *{source}* {}\n",
+                message.timestamp.format("%H:%M:%S"),
+                body.replace('\n', "\n  ")
+            ));
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::message::MessageKind;
+
+    #[test]
+    fn format_comes_from_the_argument_or_the_extension() {
+        assert_eq!(
+            parse_args("json out.txt"),
+            (ExportFormat::Json, PathBuf::from("out.txt"))
+        );
+        assert_eq!(
+            parse_args("triage.json"),
+            (ExportFormat::Json, PathBuf::from("triage.json"))
+        );
+        assert_eq!(parse_args("notes.md").0, ExportFormat::Markdown);
+        assert!(parse_args("json").1.to_string_lossy().ends_with(".json"));
+        assert_eq!(parse_args("issues.tsv").0, ExportFormat::Tsv);
+        assert!(parse_args("csv").0.is_table());
+    }
+
+    #[test]
+    fn tables_export_as_quoted_csv_and_flattened_tsv() {
+        let header = vec!["title".to_string(), "state".to_string()];
+        let rows = vec![vec![
+            "Fix \"login\", again".to_string(),
+            "open\tnow".to_string(),
+        ]];
+        assert_eq!(
+            ExportFormat::Csv.table(&header, &rows),
+            "title,state\n\"Fix \"\"login\"\", again\",open\tnow\n"
+        );
+        assert_eq!(
+            ExportFormat::Tsv.table(&header, &rows),
+            "title\tstate\nFix \"login\", again\topen now\n"
+        );
+    }
+
+    #[test]
+    fn json_keeps_message_structure_and_only_this_tabs_edits() {
+        let tasks = vec![SessionTurn {
+            task: "add sum".to_string(),
+            outcome: "edited src/sum.ts".to_string(),
+        }];
+        let edit = |task: &str| EditDetail {
+            task: task.to_string(),
+            path: PathBuf::from("src/sum.ts"),
+            content: "export const sum = 1;\n".to_string(),
+            bytes: 22,
+            timestamp: chrono::Utc::now(),
+            applied: true,
+            verification: Some("tsc: passed".to_string()),
+            attempts: 1,
+            planned: false,
+        };
+        let edits = vec![edit("add sum"), edit("another tab's task")];
+        let messages = vec![Message::new(MessageKind::User, "Processing task: add sum")];
+
+        let conversation = Conversation::new(".", "gpt-4o", &tasks, &edits, &messages);
+        let json: serde_json::Value =
+            serde_json::from_str(&conversation.render(ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["messages"][0]["kind"], "user");
+        assert_eq!(json["messages"][0]["body"], "Processing task: add sum");
+        assert_eq!(json["edits"].as_array().unwrap().len(), 1);
+        assert!(
+            conversation
+                .render(ExportFormat::Markdown)
+                .unwrap()
+                .contains("### `src/sum.ts` (applied, 1 attempt)")
+        );
+    }
+}
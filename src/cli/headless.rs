@@ -0,0 +1,605 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use async_openai::error::OpenAIError;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::cli::script::Step;
+use crate::config::{AgentMode, Config};
+use crate::error::CodepilotError;
+use crate::events::OrchestratorEvent;
+use crate::formatter::{RenderedResponse, ResponseFormatter, remediation};
+use crate::orchestrator::{
+    CodeTaskOrchestrator, ConfirmationRequest, ConfirmationResponse, TaskResult,
+};
+use crate::redact;
+use crate::runs::RunStatus;
+use crate::session::SessionContext;
+
+/// The task failed for a reason not covered below.
+pub const EXIT_FAILED: u8 = 1;
+/// Every attempt failed verification and was reverted. (2 is clap's usage error.)
+pub const EXIT_REJECTED: u8 = 3;
+/// The settings can't work as given, e.g. no API key.
+pub const EXIT_CONFIG: u8 = 4;
+/// The provider refused the API key.
+pub const EXIT_AUTH: u8 = 5;
+/// The LLM request failed otherwise: unreachable, rate limited, unknown model.
+pub const EXIT_LLM: u8 = 6;
+/// An edit needing approval was declined, by a person or for want of one.
+pub const EXIT_DECLINED: u8 = 7;
+
+/// Why a task or command failed, so scripts can tell a bad key from a flaky
+/// network from a declined edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Failure {
+    Config,
+    Auth,
+    Llm,
+    Declined,
+    /// An unusable reply, a path outside the repo, a write that failed, ...
+    Other,
+}
+
+impl Failure {
+    pub fn of(error: &anyhow::Error) -> Self {
+        match CodepilotError::find(error) {
+            Some(CodepilotError::Config(_)) => Failure::Config,
+            Some(CodepilotError::Declined(_) | CodepilotError::ReadOnly { .. }) => {
+                Failure::Declined
+            }
+            Some(CodepilotError::Provider(OpenAIError::ApiError(response)))
+                if matches!(response.status_code.as_u16(), 401 | 403) =>
+            {
+                Failure::Auth
+            }
+            Some(CodepilotError::Provider(_)) => Failure::Llm,
+            Some(CodepilotError::Agent(_) | CodepilotError::Ui(_)) | None => Failure::Other,
+        }
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Failure::Config => EXIT_CONFIG,
+            Failure::Auth => EXIT_AUTH,
+            Failure::Llm => EXIT_LLM,
+            Failure::Declined => EXIT_DECLINED,
+            Failure::Other => EXIT_FAILED,
+        }
+    }
+}
+
+/// How `ask` reports the outcome on stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// A one-line summary (plus the proposed file in plan mode); progress on stderr.
+    Text,
+    /// One `Report` as JSON, and nothing on stderr.
+    Json,
+}
+
+/// Tokens spent across every LLM call the task made.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Everything `ask --json` knows about a run, for scripts and CI jobs.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Report {
+    pub task: String,
+    pub mode: String,
+    pub model: String,
+    /// `applied`, `planned`, `rejected`, or `failed`.
+    pub status: &'static str,
+    /// The repo-relative path the edit targeted; `None` if it never got that far.
+    pub path: Option<String>,
+    pub content: Option<String>,
+    pub attempts: Option<u32>,
+    /// The `tsc` output of the last attempt when it failed.
+    pub verification_error: Option<String>,
+    /// Why the task failed, when `status` is `failed`.
+    pub error: Option<String>,
+    /// What kind of failure `error` is; it decides the exit status.
+    pub failure: Option<Failure>,
+    /// What to do about `error`, when it's a failure CodePilot recognizes.
+    pub hint: Option<String>,
+    /// What the text output would have printed.
+    pub summary: String,
+    /// The outcome as the TUI shows it, in blocks.
+    pub response: RenderedResponse,
+    pub usage: Usage,
+    /// Every orchestrator event in order: files read, edits proposed, gate runs.
+    pub events: Vec<OrchestratorEvent>,
+}
+
+impl Report {
+    pub(crate) fn new(
+        config: &Config,
+        task: &str,
+        result: &Result<TaskResult>,
+        events: Vec<OrchestratorEvent>,
+    ) -> Self {
+        let mut usage = Usage::default();
+        for event in &events {
+            if let OrchestratorEvent::LlmResponded {
+                prompt_tokens,
+                completion_tokens,
+            } = event
+            {
+                usage.prompt_tokens += u64::from(*prompt_tokens);
+                usage.completion_tokens += u64::from(*completion_tokens);
+            }
+        }
+        let mut report = Self {
+            task: task.to_string(),
+            mode: config.agent_mode.label().to_ascii_lowercase(),
+            model: config.model.clone(),
+            status: "failed",
+            path: None,
+            content: None,
+            attempts: None,
+            verification_error: None,
+            error: None,
+            failure: None,
+            hint: None,
+            summary: String::new(),
+            response: RenderedResponse::default(),
+            usage,
+            events,
+        };
+        match result {
+            Ok(result) => {
+                report.status = match (&result.verification, result.applied) {
+                    (RunStatus::Skipped, _) => "planned",
+                    (_, true) => "applied",
+                    (_, false) => "rejected",
+                };
+                report.path = Some(result.edit.path.clone());
+                report.content = Some(result.edit.content.clone());
+                report.attempts = Some(result.attempts);
+                if let RunStatus::Failed(err) = &result.verification {
+                    report.verification_error = Some(err.clone());
+                }
+                report.summary = summary(result);
+                report.response = match report.status {
+                    "rejected" => ResponseFormatter::new().render_error(&report.summary),
+                    _ => ResponseFormatter::new().render_success(&report.summary),
+                };
+            }
+            Err(e) => {
+                report.error = Some(e.to_string());
+                report.failure = Some(Failure::of(e));
+                report.hint = remediation(e);
+                report.summary = format!("Error: {e}");
+                report.response = ResponseFormatter::new().render_failure(e);
+            }
+        }
+        report
+    }
+
+    /// The report as JSON with secrets masked in every string, which is the
+    /// only shape it leaves the process in.
+    pub(crate) fn redacted(&self) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        redact::value(&mut value, &[]);
+        Ok(value)
+    }
+
+    fn exit_code(&self) -> ExitCode {
+        match self.status {
+            "applied" | "planned" => ExitCode::SUCCESS,
+            "rejected" => ExitCode::from(EXIT_REJECTED),
+            _ => ExitCode::from(self.failure.unwrap_or(Failure::Other).exit_code()),
+        }
+    }
+}
+
+/// `codepilot ask`: run `task` once with no TUI and report the outcome on
+/// stdout, so it can be piped or captured. Nothing can be confirmed
+/// interactively here, so edits that would ask are declined unless
+/// `config.auto_approve` is set (`--yes`).
+pub async fn ask(config: &Config, task: &str, output: Output) -> ExitCode {
+    let (result, events) = run(config, task, &SessionContext::new(), output == Output::Text).await;
+    let report = Report::new(config, task, &result, events);
+    match output {
+        Output::Json => match report
+            .redacted()
+            .and_then(|json| serde_json::to_string_pretty(&json))
+        {
+            Ok(json) => println!("{json}"),
+            Err(e) => {
+                eprintln!("Error: could not encode the result: {e}");
+                return ExitCode::from(EXIT_FAILED);
+            }
+        },
+        Output::Text if report.error.is_some() => {
+            eprintln!("{}", redact::text(&report.summary));
+            if let Some(hint) = &report.hint {
+                eprintln!("→ {hint}");
+            }
+        }
+        Output::Text => {
+            println!("{}", redact::text(&report.summary));
+            if let (Some(content), "planned") = (&report.content, report.status) {
+                println!("\n{}", redact::text(content.trim_end()));
+            }
+        }
+    }
+    report.exit_code()
+}
+
+/// Run the task with no one to confirm edits, collecting its events - and
+/// printing them to stderr as they happen if `progress` is set.
+pub(crate) async fn run(
+    config: &Config,
+    task: &str,
+    session: &SessionContext,
+    progress: bool,
+) -> (Result<TaskResult>, Vec<OrchestratorEvent>) {
+    if let Err(e) = config.validate() {
+        return (Err(e), Vec::new());
+    }
+    let mut orchestrator = match CodeTaskOrchestrator::new(config).await {
+        Ok(orchestrator) => orchestrator,
+        Err(e) => return (Err(e), Vec::new()),
+    };
+    let collector = collect_events(orchestrator.subscribe(), progress);
+    let result = orchestrator.run_task(task, session).await;
+    // Closes the event channel, so the collector drains what's left and stops.
+    drop(orchestrator);
+    (result, collector.await.unwrap_or_default())
+}
+
+/// Gather `receiver`'s events until the orchestrator is dropped, printing each
+/// one's activity line to stderr as it comes in if `progress` is set.
+fn collect_events(
+    mut receiver: broadcast::Receiver<OrchestratorEvent>,
+    progress: bool,
+) -> JoinHandle<Vec<OrchestratorEvent>> {
+    tokio::spawn(async move {
+        let mut events = Vec::new();
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    if progress && let Some(line) = crate::cli::app::describe_event(&event) {
+                        eprintln!("{line}");
+                    }
+                    events.push(event);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        events
+    })
+}
+
+/// `codepilot repl`: tasks read line by line from stdin, with no alternate
+/// screen or raw mode - for CI shells, dumb terminals, and screen readers.
+/// Tasks share a session like a TUI tab's, and confirmations are asked inline.
+pub async fn repl(config: &Config) -> Result<()> {
+    config.validate()?;
+    let mut config = config.clone();
+    let formatter = ResponseFormatter::fitted(&config, terminal_width());
+    let mut session = SessionContext::new();
+    let mut input = BufReader::new(tokio::io::stdin()).lines();
+    println!(
+        "CodePilot {} · {} · {} mode. Type a task, /help, or /quit.",
+        env!("CARGO_PKG_VERSION"),
+        config.model,
+        config.agent_mode.label().to_ascii_lowercase()
+    );
+
+    loop {
+        print!("> ");
+        std::io::stdout().flush()?;
+        let Some(line) = input.next_line().await? else {
+            println!();
+            return Ok(());
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some((name, args)) = crate::cli::commands::parse(line) {
+            match name {
+                "quit" | "q" | "exit" => return Ok(()),
+                "help" => println!(
+                    "Type a task to run it. /clear forgets earlier tasks, /mode [ask|agent|plan] \
+                     shows or sets the mode, /quit (or Ctrl+D) exits."
+                ),
+                "clear" => {
+                    session.clear();
+                    println!("Session cleared.");
+                }
+                "mode" if args.is_empty() => println!("Mode: {}", config.agent_mode.label()),
+                "mode" => match AgentMode::parse(args) {
+                    Some(mode) => {
+                        config.agent_mode = mode;
+                        println!("Mode: {}", mode.label());
+                    }
+                    None => println!("Unknown mode '{args}' (try ask, agent, plan)"),
+                },
+                other => println!("Unknown command /{other} - try /help"),
+            }
+            continue;
+        }
+
+        let (result, _) = run_prompted(&config, line, &session, &mut input).await?;
+        print_outcome(&formatter, &mut session, line, &result);
+    }
+}
+
+/// `codepilot run`: the script's steps one after another in a shared session,
+/// as if typed into the REPL. Stops at the first step that doesn't succeed
+/// unless `keep_going`, and exits with that step's `ask` status. With `save`,
+/// every step's `Report` is written there as a JSON array.
+pub async fn run_script(
+    config: &Config,
+    steps: &[Step],
+    save: Option<&Path>,
+    keep_going: bool,
+) -> Result<ExitCode> {
+    config.validate()?;
+    let formatter = ResponseFormatter::fitted(config, terminal_width());
+    let mut session = SessionContext::new();
+    let mut input = BufReader::new(tokio::io::stdin()).lines();
+    let mut reports = Vec::new();
+    let mut status = ExitCode::SUCCESS;
+
+    for (i, step) in steps.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, steps.len(), step.task);
+        if step.confirm {
+            print!("Run this step? [y]es / [s]kip / [q]uit: ");
+            std::io::stdout().flush()?;
+            match input.next_line().await?.as_deref().map(str::trim) {
+                Some("y" | "yes") => {}
+                Some("s" | "skip") => continue,
+                _ => break,
+            }
+        }
+
+        let (result, events) = run_prompted(config, &step.task, &session, &mut input).await?;
+        print_outcome(&formatter, &mut session, &step.task, &result);
+        let report = Report::new(config, &step.task, &result, events);
+        let failed = report.exit_code() != ExitCode::SUCCESS;
+        if failed && status == ExitCode::SUCCESS {
+            status = report.exit_code();
+        }
+        reports.push(report);
+        if failed && !keep_going {
+            break;
+        }
+    }
+
+    if let Some(save) = save {
+        let mut saved = serde_json::to_value(&reports)?;
+        redact::value(&mut saved, &[]);
+        std::fs::write(save, serde_json::to_string_pretty(&saved)?)?;
+        println!("Saved {} result(s) to {}", reports.len(), save.display());
+    }
+    Ok(status)
+}
+
+/// Run one task with progress on stderr, asking on `input` about edits that
+/// need approval. Only reading `input` can fail the outer `Result`.
+async fn run_prompted(
+    config: &Config,
+    task: &str,
+    session: &SessionContext,
+    input: &mut Lines<BufReader<Stdin>>,
+) -> Result<(Result<TaskResult>, Vec<OrchestratorEvent>)> {
+    let (confirm_tx, mut confirmations) = mpsc::unbounded_channel();
+    let mut orchestrator = match CodeTaskOrchestrator::new(config).await {
+        Ok(orchestrator) => orchestrator.with_confirmation_channel(confirm_tx),
+        Err(e) => return Ok((Err(e), Vec::new())),
+    };
+    let collector = collect_events(orchestrator.subscribe(), true);
+    let result = {
+        let run = orchestrator.run_task(task, session);
+        tokio::pin!(run);
+        loop {
+            tokio::select! {
+                result = &mut run => break result,
+                Some(request) = confirmations.recv() => confirm(request, input).await?,
+            }
+        }
+    };
+    drop(orchestrator);
+    Ok((result, collector.await.unwrap_or_default()))
+}
+
+/// Columns to wrap printed responses to: the terminal's, or 80 when output
+/// isn't going to one.
+fn terminal_width() -> usize {
+    if !std::io::stdout().is_terminal() {
+        return 80;
+    }
+    ratatui::crossterm::terminal::size().map_or(80, |(width, _)| usize::from(width))
+}
+
+/// Print how `task` went and record it in `session` for the tasks after it.
+fn print_outcome(
+    formatter: &ResponseFormatter,
+    session: &mut SessionContext,
+    task: &str,
+    result: &Result<TaskResult>,
+) {
+    match result {
+        Ok(result) => {
+            session.record_result(task, result);
+            let summary = summary(result);
+            if result.applied || matches!(result.verification, RunStatus::Skipped) {
+                println!("{}", formatter.format_success(&summary));
+            } else {
+                println!("{}", formatter.format_error(&summary));
+            }
+        }
+        Err(e) => {
+            session.record_error(task, &e.to_string());
+            println!("{}", formatter.text(&formatter.render_failure(e)));
+        }
+    }
+}
+
+/// Ask on stdin whether the edit in `request` may be written. End of input declines.
+async fn confirm(request: ConfirmationRequest, input: &mut Lines<BufReader<Stdin>>) -> Result<()> {
+    let lines = request.content.lines().count();
+    println!(
+        "{} edit to {} ({lines} lines) needs approval.",
+        request.class.label(),
+        request.path.display()
+    );
+    if let Some(injection) = &request.injection {
+        println!("It asks because the model read instructions aimed at it: {injection}");
+    }
+    let response = loop {
+        print!("Write it? [y]es / [n]o / [s]how: ");
+        std::io::stdout().flush()?;
+        match input.next_line().await?.as_deref().map(str::trim) {
+            Some("y" | "yes") => break ConfirmationResponse::Approve,
+            Some("s" | "show") => println!("{}", request.content.trim_end()),
+            Some("n" | "no" | "") | None => break ConfirmationResponse::Decline,
+            Some(_) => {}
+        }
+    };
+    let _ = request.respond.send(response);
+    Ok(())
+}
+
+/// One line on what happened, worded like the TUI's activity pane.
+fn summary(result: &TaskResult) -> String {
+    let attempts = if result.attempts > 1 {
+        format!(" after {} attempts", result.attempts)
+    } else {
+        String::new()
+    };
+    let path = result.target_path.display();
+    match &result.verification {
+        RunStatus::Skipped => format!(
+            "Planned edit to {path} ({} bytes) — nothing written",
+            result.edit.content.len()
+        ),
+        RunStatus::Succeeded => format!(
+            "Wrote {path} ({} bytes){attempts} — tsc: passed",
+            result.edit.content.len()
+        ),
+        RunStatus::Failed(err) if result.applied => {
+            format!(
+                "Wrote {path} ({} bytes){attempts} — tsc: failed - {err}",
+                result.edit.content.len()
+            )
+        }
+        RunStatus::Failed(err) => {
+            format!("Rejected edit to {path}{attempts} — tsc: failed - {err}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::FileEdit;
+
+    #[test]
+    fn report_sums_usage_and_maps_rejections_to_their_exit_status() {
+        let config = Config::from_vars([]);
+        let result = Ok(TaskResult {
+            edit: FileEdit {
+                path: "src/sum.ts".to_string(),
+                content: "export const sum = 1;\n".to_string(),
+            },
+            target_path: "src/sum.ts".into(),
+            verification: RunStatus::Failed("TS2304".to_string()),
+            applied: false,
+            attempts: 2,
+        });
+        let events = vec![
+            OrchestratorEvent::LlmResponded {
+                prompt_tokens: 100,
+                completion_tokens: 20,
+            },
+            OrchestratorEvent::LlmResponded {
+                prompt_tokens: 150,
+                completion_tokens: 30,
+            },
+        ];
+
+        let report = Report::new(&config, "add sum", &result, events);
+        assert_eq!(report.status, "rejected");
+        assert_eq!(
+            report.usage,
+            Usage {
+                prompt_tokens: 250,
+                completion_tokens: 50
+            }
+        );
+        assert_eq!(
+            report.summary,
+            "Rejected edit to src/sum.ts after 2 attempts — tsc: failed - TS2304"
+        );
+        assert_eq!(report.exit_code(), ExitCode::from(EXIT_REJECTED));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["events"][0]["event"], "llm_responded");
+    }
+
+    #[test]
+    fn json_output_masks_keys_in_the_task_and_the_edit() {
+        let config = Config::from_vars([]);
+        let key = "sk-abcdefghijklmnopqrstuv";
+        let result = Ok(TaskResult {
+            edit: FileEdit {
+                path: "src/client.ts".to_string(),
+                content: format!("export const key = \"{key}\";\n"),
+            },
+            target_path: "src/client.ts".into(),
+            verification: RunStatus::Skipped,
+            applied: false,
+            attempts: 1,
+        });
+        let report = Report::new(&config, &format!("use the key {key}"), &result, Vec::new());
+
+        let json = serde_json::to_string_pretty(&report.redacted().unwrap()).unwrap();
+        assert!(!json.contains(key));
+        assert!(json.contains(redact::REDACTED));
+    }
+
+    #[test]
+    fn failures_are_told_apart_by_their_error_type() {
+        let mut config = Config::from_vars([]);
+        config.openai_api_key = None;
+        assert_eq!(
+            Failure::of(&config.validate().unwrap_err()),
+            Failure::Config
+        );
+
+        let declined =
+            anyhow::Error::new(CodepilotError::Declined(crate::orchestrator::Declined {
+                class: crate::policy::ActionClass::Destructive,
+                path: "src/a.ts".to_string(),
+            }))
+            .context("task failed");
+        assert_eq!(Failure::of(&declined), Failure::Declined);
+        assert_eq!(
+            Failure::of(
+                &CodepilotError::Provider(OpenAIError::InvalidArgument("model".to_string())).into()
+            ),
+            Failure::Llm
+        );
+        assert_eq!(
+            Failure::of(&anyhow::anyhow!("LLM returned an empty response")),
+            Failure::Other
+        );
+
+        let report = Report::new(&config, "t", &Err(declined), Vec::new());
+        assert_eq!(report.exit_code(), ExitCode::from(EXIT_DECLINED));
+    }
+}
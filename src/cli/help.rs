@@ -0,0 +1,175 @@
+use crate::cli::commands;
+use crate::cli::keymap::{Action, Keymap};
+use crate::i18n;
+
+/// Where the user was when they asked for help; that part of the help is
+/// listed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HelpContext {
+    Input,
+    #[default]
+    Normal,
+    Details,
+    Message,
+    Confirmation,
+    Tasks,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpSection {
+    pub title: String,
+    /// This section is about where help was opened from.
+    pub current: bool,
+    pub entries: Vec<(String, String)>,
+}
+
+/// `keys` paired with the catalog's `help-{section}-1`, `-2`, ... descriptions.
+fn entries(section: &str, keys: &[&str]) -> Vec<(String, String)> {
+    keys.iter()
+        .enumerate()
+        .map(|(i, keys)| {
+            (
+                keys.to_string(),
+                i18n::tr(&format!("help-{section}-{}", i + 1)),
+            )
+        })
+        .collect()
+}
+
+/// Every help section for `keymap`, the one for `context` first, narrowed to
+/// entries whose keys or description contain `query` (case-insensitive).
+/// Sections left empty by the query are dropped.
+pub fn sections(keymap: &Keymap, context: HelpContext, query: &str) -> Vec<HelpSection> {
+    let mut normal: Vec<(String, String)> = Action::ALL
+        .iter()
+        .filter_map(|&action| {
+            keymap
+                .keys_for(action)
+                .map(|keys| (keys, action.description()))
+        })
+        .collect();
+    if keymap.keys_for(Action::CommandLine).is_some() {
+        normal.push((
+            ":q :help".to_string(),
+            ":theme <name> :log :tabnew :tabn :tabp :tabclose".to_string(),
+        ));
+    }
+
+    let all = [
+        (
+            HelpContext::Input,
+            i18n::tr("help-input-title"),
+            entries(
+                "input",
+                &[
+                    "Enter",
+                    "Shift+Enter",
+                    "Up/Down",
+                    "←/→ Home/End",
+                    "Ctrl+W/K/U",
+                    "@path",
+                    "Esc",
+                ],
+            ),
+        ),
+        (
+            HelpContext::Input,
+            i18n::tr("help-commands-title"),
+            commands::COMMANDS
+                .iter()
+                .map(|command| (command.usage.to_string(), command.description()))
+                .collect(),
+        ),
+        (
+            HelpContext::Normal,
+            i18n::tr_args("help-normal-title", &[("keymap", keymap.name)]),
+            normal,
+        ),
+        (
+            HelpContext::Details,
+            i18n::tr("help-details-title"),
+            entries(
+                "details",
+                &["j/k PgUp/PgDn", "f", "←/→", "y", "Ctrl+O / Esc"],
+            ),
+        ),
+        (
+            HelpContext::Message,
+            i18n::tr("help-message-title"),
+            entries("message", &["y", "Enter / Esc"]),
+        ),
+        (
+            HelpContext::Confirmation,
+            i18n::tr("help-confirmation-title"),
+            entries("confirmation", &["y / Enter", "e", "n / Esc"]),
+        ),
+        (
+            HelpContext::Tasks,
+            i18n::tr("help-tasks-title"),
+            entries("tasks", &["j/k", "Enter", "s", "Esc"]),
+        ),
+        (
+            HelpContext::Normal,
+            i18n::tr("help-anywhere-title"),
+            entries(
+                "anywhere",
+                &["F1", "Ctrl+O", "Ctrl+T", "Ctrl+Tab", "Ctrl+C", "Mouse"],
+            ),
+        ),
+    ];
+
+    let query = query.to_ascii_lowercase();
+    let mut sections: Vec<HelpSection> = all
+        .into_iter()
+        .map(|(ctx, title, entries)| HelpSection {
+            title,
+            current: ctx == context,
+            entries: entries
+                .into_iter()
+                .filter(|(keys, desc)| {
+                    query.is_empty()
+                        || keys.to_ascii_lowercase().contains(&query)
+                        || desc.to_ascii_lowercase().contains(&query)
+                })
+                .collect(),
+        })
+        .collect();
+    // Stable, so a context with two sections keeps them in order.
+    sections.sort_by_key(|section| !section.current);
+    sections.retain(|section| !section.entries.is_empty());
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_context_comes_first_and_follows_the_keymap() {
+        let keymap = Keymap::default()
+            .with_overrides(&[("quit".to_string(), "ctrl+q".to_string())])
+            .0;
+        let sections = sections(&keymap, HelpContext::Details, "");
+        assert_eq!(sections[0].title, "Edit detail view");
+        assert!(sections[0].current);
+
+        let normal = sections
+            .iter()
+            .find(|s| s.title.starts_with("Normal mode"))
+            .unwrap();
+        assert!(
+            normal
+                .entries
+                .iter()
+                .any(|(keys, desc)| keys == "Ctrl+q" && desc == "quit")
+        );
+    }
+
+    #[test]
+    fn query_narrows_entries_and_drops_empty_sections() {
+        let sections = sections(&Keymap::default(), HelpContext::Normal, "EDITOR");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].title, "Confirming an edit");
+        assert_eq!(sections[0].entries.len(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::i18n;
+
+/// Something a key does in normal (navigation) mode. Input-mode editing keys and
+/// the global chords (Ctrl+C, Ctrl+O, Ctrl+T) aren't remappable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Insert,
+    ToggleHelp,
+    Copy,
+    OpenMessage,
+    SelectNext,
+    SelectPrevious,
+    ScrollUp,
+    ScrollDown,
+    Top,
+    Bottom,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    NextTab,
+    PreviousTab,
+    CloseTab,
+    ToggleLog,
+    NextTheme,
+    CheckConnection,
+    FilterMessages,
+    OpenLink,
+    ToggleQueries,
+    CommandLine,
+    /// Run the selected task again as it was.
+    Rerun,
+    /// Put the selected task in the input box to change before running it again.
+    EditRerun,
+    Bookmark,
+    Expand,
+    /// Stop the running task, declining any edit it's waiting on.
+    Cancel,
+    /// Esc: close help, then clear the search, then the selection, then (unless
+    /// `Keymap::back_quits` is off) quit.
+    Back,
+}
+
+impl Action {
+    /// Every action, in the order the help screen lists them.
+    pub const ALL: [Action; 30] = [
+        Action::Insert,
+        Action::ToggleHelp,
+        Action::Copy,
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::OpenMessage,
+        Action::OpenLink,
+        Action::Rerun,
+        Action::EditRerun,
+        Action::Bookmark,
+        Action::Expand,
+        Action::Cancel,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::Top,
+        Action::Bottom,
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::FilterMessages,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::CloseTab,
+        Action::ToggleLog,
+        Action::NextTheme,
+        Action::CheckConnection,
+        Action::ToggleQueries,
+        Action::CommandLine,
+        Action::Back,
+        Action::Quit,
+    ];
+
+    /// The name used in `KEYMAP_<NAME>` overrides, lowercased.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Insert => "insert",
+            Action::ToggleHelp => "help",
+            Action::Copy => "copy",
+            Action::OpenMessage => "open",
+            Action::SelectNext => "select_next",
+            Action::SelectPrevious => "select_previous",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Search => "search",
+            Action::NextMatch => "next_match",
+            Action::PreviousMatch => "previous_match",
+            Action::NextTab => "next_tab",
+            Action::PreviousTab => "previous_tab",
+            Action::CloseTab => "close_tab",
+            Action::ToggleLog => "toggle_log",
+            Action::NextTheme => "next_theme",
+            Action::CheckConnection => "check_connection",
+            Action::FilterMessages => "filter",
+            Action::OpenLink => "open_link",
+            Action::ToggleQueries => "queries",
+            Action::CommandLine => "command",
+            Action::Rerun => "rerun",
+            Action::EditRerun => "edit_rerun",
+            Action::Bookmark => "bookmark",
+            Action::Expand => "expand",
+            Action::Cancel => "cancel",
+            Action::Back => "back",
+        }
+    }
+
+    /// What the help screen says the action does, in the configured locale.
+    pub fn description(&self) -> String {
+        i18n::tr(&format!("action-{}", self.name()))
+    }
+
+    fn parse(name: &str) -> Option<Action> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// Normal-mode key → action bindings. Picked with `KEYMAP=default|vim`; any
+/// action can be rebound with `KEYMAP_<ACTION>=<keys>`, e.g.
+/// `KEYMAP_QUIT=ctrl+q` or `KEYMAP_SELECT_NEXT=j down` (space-separated).
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    pub name: &'static str,
+    /// Whether `Back` quits once there's nothing left to close.
+    pub back_quits: bool,
+    bindings: Vec<(KeyCode, KeyModifiers, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let none = KeyModifiers::NONE;
+        let bindings = vec![
+            (KeyCode::Char('q'), none, Action::Quit),
+            (KeyCode::Char('i'), none, Action::Insert),
+            (KeyCode::Char('h'), none, Action::ToggleHelp),
+            (KeyCode::Char('y'), none, Action::Copy),
+            (KeyCode::Enter, none, Action::OpenMessage),
+            (KeyCode::Char('o'), none, Action::OpenLink),
+            (KeyCode::Char('R'), none, Action::Rerun),
+            (KeyCode::Char('e'), none, Action::EditRerun),
+            (KeyCode::Char('b'), none, Action::Bookmark),
+            (KeyCode::Char('z'), none, Action::Expand),
+            (KeyCode::Char('X'), none, Action::Cancel),
+            (KeyCode::Char('j'), none, Action::SelectNext),
+            (KeyCode::Down, none, Action::SelectNext),
+            (KeyCode::Char('k'), none, Action::SelectPrevious),
+            (KeyCode::Up, none, Action::SelectPrevious),
+            (KeyCode::PageUp, none, Action::ScrollUp),
+            (KeyCode::PageDown, none, Action::ScrollDown),
+            (KeyCode::Home, none, Action::Top),
+            (KeyCode::End, none, Action::Bottom),
+            (KeyCode::Char('/'), none, Action::Search),
+            (KeyCode::Char('n'), none, Action::NextMatch),
+            (KeyCode::Char('N'), none, Action::PreviousMatch),
+            (KeyCode::Char('f'), none, Action::FilterMessages),
+            (KeyCode::Tab, none, Action::NextTab),
+            (KeyCode::BackTab, none, Action::PreviousTab),
+            (KeyCode::Char('x'), none, Action::CloseTab),
+            (KeyCode::Char('l'), none, Action::ToggleLog),
+            (KeyCode::Char('t'), none, Action::NextTheme),
+            (KeyCode::Char('c'), none, Action::CheckConnection),
+            (KeyCode::Char('r'), none, Action::ToggleQueries),
+            (KeyCode::Esc, none, Action::Back),
+        ];
+        Self {
+            name: "default",
+            back_quits: true,
+            bindings,
+        }
+    }
+}
+
+impl Keymap {
+    /// The default bindings plus vim motions: g/G for top/bottom, Ctrl+U/Ctrl+D
+    /// to scroll, and a `:` command line. Esc no longer quits - `:q` does.
+    pub fn vim() -> Self {
+        let mut keymap = Self {
+            name: "vim",
+            back_quits: false,
+            ..Self::default()
+        };
+        keymap.bindings.extend([
+            (KeyCode::Char('g'), KeyModifiers::NONE, Action::Top),
+            (KeyCode::Char('G'), KeyModifiers::NONE, Action::Bottom),
+            (KeyCode::Char('u'), KeyModifiers::CONTROL, Action::ScrollUp),
+            (
+                KeyCode::Char('d'),
+                KeyModifiers::CONTROL,
+                Action::ScrollDown,
+            ),
+            (KeyCode::Char(':'), KeyModifiers::NONE, Action::CommandLine),
+        ]);
+        keymap
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "default" => Some(Self::default()),
+            "vim" => Some(Self::vim()),
+            _ => None,
+        }
+    }
+
+    /// Rebind actions from `(action, keys)` pairs. A rebound action loses its
+    /// preset keys, and the new keys are taken away from whatever had them.
+    /// Returns the overrides that didn't apply.
+    pub fn with_overrides(mut self, overrides: &[(String, String)]) -> (Self, Vec<String>) {
+        let mut rejected = Vec::new();
+        for (name, keys) in overrides {
+            let parsed: Option<Vec<(KeyCode, KeyModifiers)>> =
+                keys.split_whitespace().map(parse_key).collect();
+            let (Some(action), Some(parsed)) =
+                (Action::parse(name), parsed.filter(|keys| !keys.is_empty()))
+            else {
+                rejected.push(format!("{name}={keys}"));
+                continue;
+            };
+            self.bindings.retain(|&(code, modifiers, bound)| {
+                bound != action && !parsed.contains(&(code, modifiers))
+            });
+            self.bindings.extend(
+                parsed
+                    .into_iter()
+                    .map(|(code, modifiers)| (code, modifiers, action)),
+            );
+        }
+        (self, rejected)
+    }
+
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        let (code, modifiers) = normalize(key.code, key.modifiers);
+        self.bindings
+            .iter()
+            .find(|&&(bound_code, bound_modifiers, _)| {
+                bound_code == code && bound_modifiers == modifiers
+            })
+            .map(|&(_, _, action)| action)
+    }
+
+    /// "j / Down" - every key bound to `action`, for the help screen.
+    pub fn keys_for(&self, action: Action) -> Option<String> {
+        let keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|&&(_, _, bound)| bound == action)
+            .map(|&(code, modifiers, _)| key_label(code, modifiers))
+            .collect();
+        (!keys.is_empty()).then(|| keys.join(" / "))
+    }
+}
+
+/// Shift is already folded into the character for `Char` keys and into `BackTab`,
+/// so only Ctrl and Alt take part in matching.
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    (
+        code,
+        modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT),
+    )
+}
+
+/// "ctrl+t", "shift+tab", "pagedown", "G", ... as a key code and modifiers.
+fn parse_key(spec: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut parts: Vec<&str> = spec.split('+').collect();
+    // "ctrl++" binds Ctrl and the plus key.
+    if spec.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let key = parts.pop()?;
+    for modifier in parts {
+        match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+            "alt" => modifiers |= KeyModifiers::ALT,
+            "shift" => modifiers |= KeyModifiers::SHIFT,
+            _ => return None,
+        }
+    }
+
+    let code = match key.to_ascii_lowercase().as_str() {
+        "enter" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => {
+            let mut chars = key.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                KeyCode::Char(c.to_ascii_uppercase())
+            } else {
+                KeyCode::Char(c)
+            }
+        }
+    };
+    Some(normalize(code, modifiers))
+}
+
+fn key_label(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let key = match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::BackTab => "Shift+Tab".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDn".to_string(),
+        other => format!("{other:?}"),
+    };
+    let mut label = String::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        label.push_str("Ctrl+");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        label.push_str("Alt+");
+    }
+    label.push_str(&key);
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn vim_preset_adds_motions_and_a_command_line() {
+        let vim = Keymap::vim();
+        assert_eq!(
+            vim.action_for(&key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+        assert_eq!(
+            vim.action_for(&key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(Action::ScrollDown)
+        );
+        assert_eq!(
+            vim.action_for(&key(KeyCode::Char(':'), KeyModifiers::NONE)),
+            Some(Action::CommandLine)
+        );
+        assert!(!vim.back_quits);
+        assert_eq!(
+            Keymap::default().action_for(&key(KeyCode::Char(':'), KeyModifiers::NONE)),
+            None
+        );
+    }
+
+    #[test]
+    fn overrides_move_keys_between_actions() {
+        let overrides = vec![
+            ("quit".to_string(), "ctrl+q".to_string()),
+            ("select_next".to_string(), "q".to_string()),
+            ("nope".to_string(), "z".to_string()),
+            ("copy".to_string(), "hyper+c".to_string()),
+        ];
+        let (keymap, rejected) = Keymap::default().with_overrides(&overrides);
+        assert_eq!(
+            keymap.action_for(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::SelectNext)
+        );
+        assert_eq!(
+            keymap.action_for(&key(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action_for(&key(KeyCode::Char('j'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keymap.keys_for(Action::Copy).as_deref(), Some("y"));
+        assert_eq!(rejected, vec!["nope=z", "copy=hyper+c"]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Something in a message worth jumping to: a URL, or a file in the target repo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Byte range of the link text within the searched string.
+    pub range: Range<usize>,
+    /// What opening it opens: the URL itself, or a `file://` URL for repo files.
+    pub target: String,
+}
+
+/// Links in `text`, left to right. Only whole whitespace-separated words count,
+/// minus surrounding quotes and trailing punctuation, and a path only if it's
+/// one of `repo_files` - so "src/sum.ts" links but "sum.ts or so" doesn't.
+pub fn find(text: &str, repo: &Path, repo_files: &[String]) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut offset = 0;
+    for word in text.split_inclusive(char::is_whitespace) {
+        let start = offset;
+        offset += word.len();
+
+        let trimmed = word.trim_end();
+        let lead = trimmed.len() - trimmed.trim_start_matches(['(', '[', '"', '\'', '`']).len();
+        let core = trimmed[lead..]
+            .trim_end_matches(['.', ',', ';', ':', ')', ']', '"', '\'', '`', '!', '?']);
+        if core.is_empty() {
+            continue;
+        }
+
+        let target = if core.starts_with("https://") || core.starts_with("http://") {
+            core.to_string()
+        } else if repo_files.iter().any(|file| file == core) {
+            let path = std::path::absolute(repo.join(core)).unwrap_or_else(|_| repo.join(core));
+            format!("file://{}", path.display())
+        } else {
+            continue;
+        };
+        links.push(Link {
+            range: start + lead..start + lead + core.len(),
+            target,
+        });
+    }
+    links
+}
+
+/// Hand `target` to the platform's opener (`open`, `xdg-open`, `start`) without
+/// waiting for it.
+pub fn open(target: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(target)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// `text` wrapped in an OSC 8 escape, which terminals that support it turn into
+/// a clickable link to `target`; others show `text` as-is.
+pub fn osc8(text: &str, target: &str) -> String {
+    format!("\x1b]8;;{target}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_urls_and_known_repo_files_without_punctuation() {
+        let text = "Wrote src/sum.ts (see https://example.com/docs). Not sum.ts";
+        let links = find(text, Path::new("/repo"), &["src/sum.ts".to_string()]);
+        assert_eq!(links.len(), 2);
+        assert_eq!(&text[links[0].range.clone()], "src/sum.ts");
+        assert_eq!(links[0].target, "file:///repo/src/sum.ts");
+        assert_eq!(&text[links[1].range.clone()], "https://example.com/docs");
+        assert_eq!(links[1].target, "https://example.com/docs");
+    }
+}
@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber, span};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::cli::encryption;
+use crate::config::Config;
+use crate::redact;
+
+/// A log file is rotated once it would grow past this.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated files kept besides the current one: `codepilot.log.1` (newest)
+/// up to `codepilot.log.3`.
+const KEEP_ROTATED: usize = 3;
+
+/// Send `tracing` events, filtered by `RUST_LOG`, to a JSON-lines file in
+/// `LOG_DIR` (or `SAVE_STATE_DIR/logs`), and to `pane` as log-pane lines when
+/// the TUI passes one. Without either, nothing is recorded.
+pub fn init(config: &Config, pane: Option<mpsc::UnboundedSender<String>>) -> Result<()> {
+    let file = match log_dir(config, encryption::unlocked()) {
+        Some((dir, sealed)) => Some(JsonLayer {
+            file: Mutex::new(RotatingFile::open(&dir.join("codepilot.log"))?),
+            sealed,
+        }),
+        None => None,
+    };
+    let filter = EnvFilter::try_new(&config.log_level).unwrap_or_else(|_| EnvFilter::new("info"));
+    // Set once per process; a second call (tests, an embedder's own
+    // subscriber) leaves the first in place.
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(file)
+        .with(pane.map(|sender| PaneLayer { sender }))
+        .try_init();
+    Ok(())
+}
+
+/// Where the log file goes, and whether its lines are sealed. `LOG_DIR` is
+/// written in the clear, as asked. The default under `SAVE_STATE_DIR` follows
+/// ENCRYPT_STATE like the rest of that directory: sealed once the key is
+/// `unlocked`, and skipped where it isn't (the commands that never ask for the
+/// passphrase).
+fn log_dir(config: &Config, unlocked: bool) -> Option<(PathBuf, bool)> {
+    if let Some(dir) = config.log_dir.as_deref() {
+        return Some((PathBuf::from(dir), false));
+    }
+    let dir = Path::new(config.save_state_dir.as_deref()?).join("logs");
+    match (config.encrypt_state, unlocked) {
+        (false, _) => Some((dir, false)),
+        (true, true) => Some((dir, true)),
+        (true, false) => None,
+    }
+}
+
+/// `codepilot.log`, moved aside to `codepilot.log.1` (and those before it
+/// along) when it fills up.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > MAX_LOG_BYTES {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = |n: usize| PathBuf::from(format!("{}.{n}", self.path.display()));
+        for n in (1..KEEP_ROTATED).rev() {
+            if rotated(n).exists() {
+                std::fs::rename(rotated(n), rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated(1))?;
+        *self = Self::open(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Fields recorded on a span when it's created, for the events inside it.
+struct SpanFields(Map<String, Value>);
+
+/// Collects an event's or span's fields as JSON values.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            Value::String(format!("{value:?}")),
+        );
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0
+            .insert(field.name().to_string(), Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+/// One JSON object per event: time, level, target, its fields, and the spans
+/// it happened in (outermost first) with theirs.
+struct JsonLayer {
+    file: Mutex<RotatingFile>,
+    sealed: bool,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for JsonLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        let spans: Vec<Value> = ctx
+            .event_scope(event)
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+            .map(|span| {
+                let mut fields = span
+                    .extensions()
+                    .get::<SpanFields>()
+                    .map(|f| f.0.clone())
+                    .unwrap_or_default();
+                fields.insert("name".to_string(), span.name().into());
+                Value::Object(fields)
+            })
+            .collect();
+        let metadata = event.metadata();
+        let mut line = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "fields": fields,
+            "spans": spans,
+        });
+        redact::value(&mut line, &[]);
+        let line = if self.sealed {
+            encryption::seal(&line.to_string())
+        } else {
+            line.to_string()
+        };
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_line(&line);
+        }
+    }
+}
+
+/// Hands each event to the TUI as a log-pane line: the message, then any
+/// other fields as `key=value`.
+struct PaneLayer {
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl<S: Subscriber> Layer<S> for PaneLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        let _ = self.sender.send(pane_line(fields));
+    }
+}
+
+fn pane_line(mut fields: Map<String, Value>) -> String {
+    let mut line = match fields.remove("message") {
+        Some(Value::String(message)) => message,
+        _ => String::new(),
+    };
+    for (key, value) in fields {
+        let value = match value {
+            Value::String(text) => format!("{text:?}"),
+            other => other.to_string(),
+        };
+        line.push_str(&format!(" {key}={value}"));
+    }
+    line.trim_start().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_files_rotate_and_pane_lines_read_as_key_values() {
+        let dir = std::env::temp_dir().join(format!("codepilot-test-logs-{}", std::process::id()));
+        let path = dir.join("codepilot.log");
+        let mut file = RotatingFile::open(&path).unwrap();
+        file.write_line("first").unwrap();
+        file.size = MAX_LOG_BYTES;
+        file.write_line("second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("codepilot.log.1")).unwrap(),
+            "first\n"
+        );
+        std::fs::remove_dir_all(dir).ok();
+
+        let fields = serde_json::json!({"message": "gate_finished", "kind": "tsc", "passed": true});
+        let Value::Object(fields) = fields else {
+            unreachable!()
+        };
+        assert_eq!(pane_line(fields), "gate_finished kind=\"tsc\" passed=true");
+    }
+
+    #[test]
+    fn the_default_log_dir_follows_encrypt_state() {
+        let mut config = Config::from_vars([]);
+        config.log_dir = None;
+        config.save_state_dir = Some("state".to_string());
+        config.encrypt_state = false;
+        assert_eq!(
+            log_dir(&config, false),
+            Some((Path::new("state").join("logs"), false))
+        );
+        config.encrypt_state = true;
+        assert_eq!(
+            log_dir(&config, true),
+            Some((Path::new("state").join("logs"), true))
+        );
+        assert_eq!(log_dir(&config, false), None);
+        config.log_dir = Some("logs".to_string());
+        assert_eq!(
+            log_dir(&config, false),
+            Some((PathBuf::from("logs"), false))
+        );
+    }
+}
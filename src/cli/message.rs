@@ -0,0 +1,279 @@
+use crate::events::OrchestratorEvent;
+use crate::formatter::{RenderedResponse, ResponseFormatter};
+
+/// What produced an activity-pane message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    /// A task the user submitted.
+    User,
+    /// The outcome of a task.
+    Agent,
+    /// Progress from the orchestrator: files read, edits proposed, gate runs.
+    Tool,
+    /// The app talking about itself: startup notes, mode changes, copies.
+    #[default]
+    System,
+    Error,
+}
+
+impl MessageKind {
+    pub const ALL: [MessageKind; 5] = [
+        MessageKind::User,
+        MessageKind::Agent,
+        MessageKind::Tool,
+        MessageKind::System,
+        MessageKind::Error,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MessageKind::User => "user",
+            MessageKind::Agent => "agent",
+            MessageKind::Tool => "tool",
+            MessageKind::System => "system",
+            MessageKind::Error => "error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.label().eq_ignore_ascii_case(value.trim()))
+    }
+}
+
+/// One entry in a tab's activity pane.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Message {
+    pub kind: MessageKind,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Which part of the agent spoke: the gate (`tsc`) for gate results, the
+    /// agent's name for task outcomes. `None` for the user and the app itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    pub body: String,
+    /// The orchestrator event behind a tool message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_json: Option<serde_json::Value>,
+    /// On a task run again and on its result: when the original user message
+    /// was sent, which picks it out within the tab. They're shown threaded
+    /// under it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerun_of: Option<chrono::DateTime<chrono::Utc>>,
+    /// The blocks `body` was rendered from, kept for exports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<RenderedResponse>,
+    /// Whether `body` shows `response` with nothing cut short.
+    #[serde(skip)]
+    pub expanded: bool,
+}
+
+impl Message {
+    /// What a user message asked for: its body without the "Processing task:"
+    /// prefix. `None` for every other kind.
+    pub fn task(&self) -> Option<&str> {
+        (self.kind == MessageKind::User).then(|| {
+            self.body
+                .strip_prefix("Processing task: ")
+                .unwrap_or(&self.body)
+        })
+    }
+
+    pub fn new(kind: MessageKind, body: impl Into<String>) -> Self {
+        Self {
+            kind,
+            timestamp: chrono::Utc::now(),
+            agent: None,
+            body: body.into(),
+            raw_json: None,
+            rerun_of: None,
+            response: None,
+            expanded: false,
+        }
+    }
+
+    /// A message showing `response` as `formatter` lays it out.
+    pub fn rendered(
+        kind: MessageKind,
+        formatter: &ResponseFormatter,
+        response: RenderedResponse,
+    ) -> Self {
+        Self {
+            body: formatter.text(&response),
+            response: Some(response),
+            ..Self::new(kind, "")
+        }
+    }
+
+    pub fn with_agent(mut self, agent: impl Into<String>) -> Self {
+        self.agent = Some(agent.into());
+        self
+    }
+
+    pub fn with_rerun_of(mut self, original: Option<chrono::DateTime<chrono::Utc>>) -> Self {
+        self.rerun_of = original;
+        self
+    }
+
+    /// A tool message describing `event`, carrying the event itself as JSON.
+    pub fn from_event(event: &OrchestratorEvent, body: String) -> Self {
+        let agent = match event {
+            OrchestratorEvent::GateStarted { kind, .. }
+            | OrchestratorEvent::GateFinished { kind, .. } => Some(kind.label().to_string()),
+            _ => None,
+        };
+        Self {
+            agent,
+            raw_json: serde_json::to_value(event).ok(),
+            ..Self::new(MessageKind::Tool, body)
+        }
+    }
+
+    /// The plain `[HH:MM:SS] body` line messages were before they had any
+    /// structure - what gets wrapped, searched, and copied.
+    pub fn line(&self) -> String {
+        format!("[{}] {}", self.timestamp.format("%H:%M:%S"), self.body)
+    }
+}
+
+/// Which messages the activity pane shows. Empty `kinds` means every kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageFilter {
+    pub kinds: Vec<MessageKind>,
+    pub agent: Option<String>,
+}
+
+impl MessageFilter {
+    /// Just the exchange: what was asked, what came of it, and what went wrong.
+    pub fn conversation() -> Self {
+        Self {
+            kinds: vec![MessageKind::User, MessageKind::Agent, MessageKind::Error],
+            agent: None,
+        }
+    }
+
+    /// Just the tool calls: files read, edits proposed, gate runs.
+    pub fn tools() -> Self {
+        Self {
+            kinds: vec![MessageKind::Tool, MessageKind::Error],
+            agent: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty() && self.agent.is_none()
+    }
+
+    pub fn matches(&self, message: &Message) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&message.kind))
+            && self.agent.as_ref().is_none_or(|agent| {
+                message
+                    .agent
+                    .as_ref()
+                    .is_some_and(|a| a.eq_ignore_ascii_case(agent))
+            })
+    }
+
+    /// The filter key cycles everything → conversation → tools → everything.
+    pub fn next_preset(&self) -> Self {
+        if self.is_empty() {
+            Self::conversation()
+        } else if *self == Self::conversation() {
+            Self::tools()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// `/filter` arguments: `all`, `conversation`, `tools`, or any of
+    /// `kind=tool,error` and `agent=tsc`. An empty argument clears the filter.
+    pub fn parse(args: &str) -> Result<Self, String> {
+        let mut filter = Self::default();
+        for part in args.split_whitespace() {
+            match part.split_once('=') {
+                Some(("kind", kinds)) => {
+                    for kind in kinds.split(',').filter(|kind| !kind.is_empty()) {
+                        let kind = MessageKind::parse(kind)
+                            .ok_or_else(|| format!("unknown kind '{kind}'"))?;
+                        if !filter.kinds.contains(&kind) {
+                            filter.kinds.push(kind);
+                        }
+                    }
+                }
+                Some(("agent", agent)) if !agent.is_empty() => {
+                    filter.agent = Some(agent.to_string())
+                }
+                None if part == "all" => filter = Self::default(),
+                None if part == "conversation" => filter = Self::conversation(),
+                None if part == "tools" => filter = Self::tools(),
+                _ => return Err(format!("don't know '{part}'")),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// "conversation", "tools", or "kind=tool agent=tsc"; "all" when empty.
+    pub fn label(&self) -> String {
+        if self.is_empty() {
+            return "all".to_string();
+        } else if *self == Self::conversation() {
+            return "conversation".to_string();
+        } else if *self == Self::tools() {
+            return "tools".to_string();
+        }
+        let mut parts = Vec::new();
+        if !self.kinds.is_empty() {
+            let kinds: Vec<&str> = self.kinds.iter().map(MessageKind::label).collect();
+            parts.push(format!("kind={}", kinds.join(",")));
+        }
+        if let Some(agent) = &self.agent {
+            parts.push(format!("agent={agent}"));
+        }
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runs::RunKind;
+
+    #[test]
+    fn gate_events_name_the_gate_and_keep_the_raw_event() {
+        let event = OrchestratorEvent::GateFinished {
+            kind: RunKind::TypeCheck,
+            attempt: 1,
+            passed: true,
+        };
+        let message = Message::from_event(&event, "tsc passed".to_string());
+        assert_eq!(message.kind, MessageKind::Tool);
+        assert_eq!(message.agent.as_deref(), Some("tsc"));
+        assert_eq!(message.raw_json.as_ref().unwrap()["passed"], true);
+        assert!(message.line().ends_with("] tsc passed"));
+    }
+
+    #[test]
+    fn kinds_parse_from_their_labels() {
+        assert_eq!(MessageKind::parse("Tool"), Some(MessageKind::Tool));
+        assert_eq!(MessageKind::parse("nope"), None);
+    }
+
+    #[test]
+    fn filters_parse_and_match_on_kind_and_agent() {
+        let filter = MessageFilter::parse("agent=tsc kind=tool").unwrap();
+        assert_eq!(filter.label(), "kind=tool agent=tsc");
+        assert!(filter.matches(&Message::new(MessageKind::Tool, "tsc passed").with_agent("tsc")));
+        assert!(!filter.matches(&Message::new(MessageKind::Tool, "Reading src/a.ts")));
+        assert!(
+            !filter.matches(&Message::new(MessageKind::System, "Theme: dark").with_agent("tsc"))
+        );
+
+        assert_eq!(
+            MessageFilter::parse("conversation").unwrap(),
+            MessageFilter::conversation()
+        );
+        assert!(MessageFilter::parse("").unwrap().is_empty());
+        assert!(MessageFilter::parse("kind=chatter").is_err());
+    }
+}
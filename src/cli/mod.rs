@@ -1,8 +1,30 @@
 // CLI application module
 pub mod app;
+pub mod archive;
+pub mod args;
+pub mod clipboard;
+pub mod commands;
+pub mod completions;
+pub mod doctor;
+pub mod encryption;
+pub mod export;
+pub mod headless;
+pub mod help;
+pub mod keymap;
+pub mod links;
+pub mod logging;
+pub mod message;
 pub mod persistence;
+pub mod script;
+pub mod serve;
+pub mod setup;
+pub mod stats;
+pub mod stdio;
+pub mod tasks;
+pub mod terminal;
 pub mod ui;
 pub mod state;
+pub mod theme;
 
 pub use app::App;
 pub use state::AppState; 
\ No newline at end of file
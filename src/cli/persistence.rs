@@ -1,20 +1,78 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use crate::cli::state::EditDetail;
+use crate::cli::encryption;
+use crate::cli::message::Message;
+use crate::cli::state::{ConversationTab, EditDetail};
+use crate::redact;
+
+/// How many submitted tasks are kept for Up/Down recall.
+pub const MAX_INPUT_HISTORY: usize = 100;
+
+/// How many saved sessions the startup picker offers.
+pub const MAX_LISTED_SESSIONS: usize = 20;
+
+/// The `SavedSession` format this build writes. Sessions from a newer build
+/// are left out of the picker rather than half-read.
+pub const SESSION_VERSION: u32 = 1;
+
+/// Longer messages are cut short in `history` output.
+const MAX_HIT_CHARS: usize = 100;
+
+/// One run's tabs, saved to `{save_state_dir}/sessions/` so a later run can pick
+/// up where it left off.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SavedSession {
+    /// Missing in files saved before sessions were versioned, which are version 1.
+    #[serde(default = "first_version")]
+    pub version: u32,
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+    pub active_tab: usize,
+    pub tabs: Vec<ConversationTab>,
+    /// Set with `/rename`, or summarized by the LLM from the first task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+impl SavedSession {
+    /// The session's name, else the first titled tab's title - the first task
+    /// run in the session.
+    pub fn title(&self) -> &str {
+        self.name.as_deref().unwrap_or_else(|| {
+            self.tabs
+                .iter()
+                .map(|tab| tab.title.as_str())
+                .find(|title| !title.is_empty())
+                .unwrap_or("untitled")
+        })
+    }
+
+    pub fn message_count(&self) -> usize {
+        self.tabs.iter().map(|tab| tab.messages.len()).sum()
+    }
+}
 
 fn history_path(save_state_dir: &str) -> PathBuf {
     Path::new(save_state_dir).join("history.jsonl")
 }
 
-/// Append one edit to `{save_state_dir}/history.jsonl`, one JSON object per line.
+fn input_history_path(save_state_dir: &str) -> PathBuf {
+    Path::new(save_state_dir).join("input_history.jsonl")
+}
+
+/// Append one edit to `{save_state_dir}/history.jsonl`, one JSON object per line,
+/// with secrets masked in the task and the content alike.
 pub fn append_entry(save_state_dir: &str, entry: &EditDetail) -> anyhow::Result<()> {
     std::fs::create_dir_all(save_state_dir)?;
     let mut file = std::fs::OpenOptions::new()
         .create(true)
         .append(true)
         .open(history_path(save_state_dir))?;
-    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    writeln!(file, "{}", encryption::seal(&scrubbed(entry, false)?))?;
     Ok(())
 }
 
@@ -27,10 +85,231 @@ pub fn load_entries(save_state_dir: &str) -> Vec<EditDetail> {
     };
     content
         .lines()
-        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter_map(|line| serde_json::from_str(&encryption::open(line).ok()?).ok())
         .collect()
 }
 
+/// Append one submitted task to `{save_state_dir}/input_history.jsonl`, stored as a
+/// JSON string per line so multi-line tasks survive.
+pub fn append_input(save_state_dir: &str, input: &str) -> anyhow::Result<()> {
+    std::fs::create_dir_all(save_state_dir)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(input_history_path(save_state_dir))?;
+    writeln!(file, "{}", encryption::seal(&serde_json::to_string(&redact::text(input))?))?;
+    Ok(())
+}
+
+/// The most recent `MAX_INPUT_HISTORY` submitted tasks, oldest first. Best-effort,
+/// like `load_entries`.
+pub fn load_inputs(save_state_dir: &str) -> Vec<String> {
+    let Ok(content) = std::fs::read_to_string(input_history_path(save_state_dir)) else {
+        return Vec::new();
+    };
+    let inputs: Vec<String> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(&encryption::open(line).ok()?).ok())
+        .collect();
+    let start = inputs.len().saturating_sub(MAX_INPUT_HISTORY);
+    inputs[start..].to_vec()
+}
+
+fn favorites_path(save_state_dir: &str) -> PathBuf {
+    Path::new(save_state_dir).join("favorites.json")
+}
+
+/// Overwrite `{save_state_dir}/favorites.json` with the starred tasks.
+pub fn save_favorites(save_state_dir: &str, favorites: &[String]) -> anyhow::Result<()> {
+    std::fs::create_dir_all(save_state_dir)?;
+    std::fs::write(favorites_path(save_state_dir), encryption::seal(&scrubbed(favorites, true)?))?;
+    Ok(())
+}
+
+/// Starred tasks, best-effort like `load_entries`.
+pub fn load_favorites(save_state_dir: &str) -> Vec<String> {
+    read_json(&favorites_path(save_state_dir)).unwrap_or_default()
+}
+
+fn bookmarks_path(save_state_dir: &str) -> PathBuf {
+    Path::new(save_state_dir).join("bookmarks.json")
+}
+
+/// Overwrite `{save_state_dir}/bookmarks.json` with the bookmarked messages.
+pub fn save_bookmarks(save_state_dir: &str, bookmarks: &[Message]) -> anyhow::Result<()> {
+    std::fs::create_dir_all(save_state_dir)?;
+    std::fs::write(bookmarks_path(save_state_dir), encryption::seal(&scrubbed(bookmarks, true)?))?;
+    Ok(())
+}
+
+/// Bookmarked messages, best-effort like `load_entries`.
+pub fn load_bookmarks(save_state_dir: &str) -> Vec<Message> {
+    read_json(&bookmarks_path(save_state_dir)).unwrap_or_default()
+}
+
+/// A whole-file JSON value, opened first if it was sealed by `encryption`.
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = encryption::open(&std::fs::read_to_string(path).ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn sessions_dir(save_state_dir: &str) -> PathBuf {
+    Path::new(save_state_dir).join("sessions")
+}
+
+/// Where a run started at `started` keeps its session. Rewritten as the run goes on.
+pub fn session_path(save_state_dir: &str, started: chrono::DateTime<chrono::Utc>) -> PathBuf {
+    sessions_dir(save_state_dir).join(format!("{}.json", started.format("%Y%m%dT%H%M%S")))
+}
+
+/// The file saved session `id` lives in; ids are file stems, as `session_id` gives them.
+pub fn session_file(save_state_dir: &str, id: &str) -> PathBuf {
+    sessions_dir(save_state_dir).join(format!("{id}.json"))
+}
+
+pub fn session_id(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+pub fn save_session(path: &Path, session: &SavedSession) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, encryption::seal(&scrubbed(session, false)?))?;
+    Ok(())
+}
+
+/// `state` as JSON with secrets masked in every string.
+fn scrubbed(state: &(impl serde::Serialize + ?Sized), pretty: bool) -> anyhow::Result<String> {
+    let mut value = serde_json::to_value(state)?;
+    redact::value(&mut value, &[]);
+    Ok(if pretty { serde_json::to_string_pretty(&value)? } else { serde_json::to_string(&value)? })
+}
+
+/// Saved sessions, newest first, at most `MAX_LISTED_SESSIONS`. Best-effort like
+/// `load_entries`: unreadable files are skipped.
+pub fn load_sessions(save_state_dir: &str) -> Vec<(PathBuf, SavedSession)> {
+    let mut sessions = load_all_sessions(save_state_dir);
+    sessions.truncate(MAX_LISTED_SESSIONS);
+    sessions
+}
+
+/// Every saved session, newest first.
+pub fn load_all_sessions(save_state_dir: &str) -> Vec<(PathBuf, SavedSession)> {
+    let Ok(dir) = std::fs::read_dir(sessions_dir(save_state_dir)) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<(PathBuf, SavedSession)> = dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some((path.clone(), load_session(&path)?)))
+        .collect();
+    sessions.sort_by_key(|(_, session)| std::cmp::Reverse(session.saved_at));
+    sessions
+}
+
+/// One saved session, unless it's unreadable or from a newer CodePilot.
+pub fn load_session(path: &Path) -> Option<SavedSession> {
+    let session: SavedSession = read_json(path)?;
+    (session.version <= SESSION_VERSION).then_some(session)
+}
+
+/// Delete everything kept under `save_state_dir`: edit and input history,
+/// starred tasks, bookmarks, sessions, and the encryption header. Anything
+/// else in the directory is left alone. Returns how many files were removed.
+pub fn reset(save_state_dir: &str) -> anyhow::Result<usize> {
+    reset_files(save_state_dir, encryption::unlocked())
+}
+
+/// `reset`, keeping the encryption header when `keep_header`: while this run
+/// holds the key, whatever it saves next is sealed with it, and only that
+/// header's salt derives the same key again.
+pub(crate) fn reset_files(save_state_dir: &str, keep_header: bool) -> anyhow::Result<usize> {
+    let mut files = vec![
+        history_path(save_state_dir),
+        input_history_path(save_state_dir),
+        favorites_path(save_state_dir),
+        bookmarks_path(save_state_dir),
+    ];
+    if !keep_header {
+        files.push(encryption::header_path(save_state_dir));
+    }
+    if let Ok(dir) = std::fs::read_dir(sessions_dir(save_state_dir)) {
+        files.extend(
+            dir.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json")),
+        );
+    }
+    let mut removed = 0;
+    for file in files.iter().filter(|file| file.is_file()) {
+        std::fs::remove_file(file)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// A saved message that matched a `search`, and where it's from.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SearchHit {
+    pub session: PathBuf,
+    pub title: String,
+    /// Which of the session's tabs, and which message in it.
+    pub tab: usize,
+    pub index: usize,
+    pub message: Message,
+    /// The task of the exchange the message belongs to: the latest user
+    /// message at or before it.
+    pub task: Option<String>,
+}
+
+impl SearchHit {
+    /// `2026-10-12 14:03  fix the build  agent: Applied edit to src/sum.ts`,
+    /// with the message cut to its first line.
+    pub fn line(&self) -> String {
+        let body = self.message.body.lines().next().unwrap_or_default();
+        let body = match body.char_indices().nth(MAX_HIT_CHARS) {
+            Some((end, _)) => format!("{}…", &body[..end]),
+            None => body.to_string(),
+        };
+        format!(
+            "{}  {}  {}: {body}",
+            self.message.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            self.title,
+            self.message.kind.label()
+        )
+    }
+}
+
+/// Messages across `sessions` whose text contains `query`, ignoring case; newest
+/// session first, in order within each.
+pub fn search(sessions: &[(PathBuf, SavedSession)], query: &str) -> Vec<SearchHit> {
+    let query = query.to_lowercase();
+    let mut hits = Vec::new();
+    for (path, session) in sessions {
+        for (tab_index, tab) in session.tabs.iter().enumerate() {
+            let mut task = None;
+            for (index, message) in tab.messages.iter().enumerate() {
+                if let Some(asked) = message.task() {
+                    task = Some(asked);
+                }
+                if message.body.to_lowercase().contains(&query) {
+                    hits.push(SearchHit {
+                        session: path.clone(),
+                        title: session.title().to_string(),
+                        tab: tab_index,
+                        index,
+                        message: message.clone(),
+                        task: task.map(str::to_string),
+                    });
+                }
+            }
+        }
+    }
+    hits
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,15 +327,19 @@ mod tests {
             timestamp: chrono::Utc::now(),
             applied: true,
             verification: Some("tsc: passed".to_string()),
+            attempts: 1,
+            planned: false,
         };
         let second = EditDetail {
-            task: "add a test".to_string(),
+            task: "add a test with key sk-abcdefghijklmnopqrstuv".to_string(),
             path: PathBuf::from("src/lib.test.ts"),
-            content: "test('add', () => {});\n".to_string(),
+            content: "const key = 'sk-abcdefghijklmnopqrstuv';\n".to_string(),
             bytes: 24,
             timestamp: chrono::Utc::now(),
             applied: false,
             verification: Some("tsc: failed - TS2304: Cannot find name 'test'".to_string()),
+            attempts: 3,
+            planned: false,
         };
 
         append_entry(dir_str, &first).unwrap();
@@ -66,10 +349,122 @@ mod tests {
         assert_eq!(loaded.len(), 2);
         assert_eq!(loaded[0].task, "add a function");
         assert_eq!(loaded[1].path, PathBuf::from("src/lib.test.ts"));
+        assert_eq!(loaded[1].task, "add a test with key [redacted]");
+        assert!(!loaded[1].content.contains("sk-abcdefghijklmnopqrstuv"));
 
         std::fs::remove_dir_all(dir).ok();
     }
 
+    #[test]
+    fn input_history_keeps_multiline_tasks() {
+        let dir =
+            std::env::temp_dir().join(format!("codepilot-test-inputs-{}", std::process::id()));
+        let dir_str = dir.to_str().unwrap();
+
+        append_input(dir_str, "fix the build").unwrap();
+        append_input(dir_str, "add a test\nfor sum()").unwrap();
+
+        assert_eq!(load_inputs(dir_str), vec!["fix the build", "add a test\nfor sum()"]);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn sessions_load_newest_first() {
+        let dir =
+            std::env::temp_dir().join(format!("codepilot-test-sessions-{}", std::process::id()));
+        let dir_str = dir.to_str().unwrap();
+        let older = chrono::Utc::now() - chrono::Duration::hours(1);
+        let newer = chrono::Utc::now();
+        let session = |saved_at, title: &str| SavedSession {
+            version: SESSION_VERSION,
+            saved_at,
+            active_tab: 0,
+            tabs: vec![ConversationTab {
+                title: title.to_string(),
+                messages: vec![crate::cli::message::Message::new(
+                    crate::cli::message::MessageKind::User,
+                    "Processing task",
+                )],
+                ..ConversationTab::default()
+            }],
+            name: None,
+        };
+
+        save_session(&session_path(dir_str, older), &session(older, "fix the build")).unwrap();
+        save_session(&session_path(dir_str, newer), &session(newer, "add a test")).unwrap();
+        let future = SavedSession {
+            version: SESSION_VERSION + 1,
+            ..session(newer, "from a newer build")
+        };
+        save_session(&dir.join("sessions").join("future.json"), &future).unwrap();
+
+        let loaded = load_sessions(dir_str);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].1.title(), "add a test");
+        let named = SavedSession {
+            name: Some("Sum helper".to_string()),
+            ..loaded[0].1.clone()
+        };
+        assert_eq!(named.title(), "Sum helper");
+        assert_eq!(loaded[1].1.message_count(), 1);
+
+        append_input(dir_str, "fix the build").unwrap();
+        std::fs::write(dir.join("notes.txt"), "mine").unwrap();
+        assert_eq!(reset(dir_str).unwrap(), 4);
+        assert!(load_sessions(dir_str).is_empty());
+        assert!(dir.join("notes.txt").exists());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn search_matches_messages_across_sessions_ignoring_case() {
+        use crate::cli::message::MessageKind;
+        let session = |title: &str, bodies: &[&str]| SavedSession {
+            version: SESSION_VERSION,
+            saved_at: chrono::Utc::now(),
+            active_tab: 0,
+            tabs: vec![ConversationTab {
+                title: title.to_string(),
+                messages: bodies
+                    .iter()
+                    .map(|body| Message::new(MessageKind::Agent, *body))
+                    .collect(),
+                ..ConversationTab::default()
+            }],
+            name: None,
+        };
+        let sessions = vec![
+            (
+                PathBuf::from("b.json"),
+                session("add a test", &["Applied edit to src/Sum.test.ts"]),
+            ),
+            (
+                PathBuf::from("a.json"),
+                session(
+                    "fix the build",
+                    &["Applied edit to src/sum.ts", "tsc: passed"],
+                ),
+            ),
+        ];
+
+        let hits = search(&sessions, "SUM");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].title, "add a test");
+        assert_eq!(hits[1].session, PathBuf::from("a.json"));
+        assert_eq!(hits[1].task, None);
+
+        let mut asked = session("fix the build", &["tsc: passed"]);
+        asked.tabs[0].messages.insert(
+            0,
+            Message::new(MessageKind::User, "Processing task: fix the build"),
+        );
+        let hits = search(&[(PathBuf::from("c.json"), asked)], "passed");
+        assert_eq!((hits[0].index, hits[0].task.as_deref()), (1, Some("fix the build")));
+        assert!(search(&sessions, "eslint").is_empty());
+    }
+
     #[test]
     fn missing_history_file_loads_as_empty() {
         let dir = std::env::temp_dir().join(format!("codepilot-test-missing-{}", std::process::id()));
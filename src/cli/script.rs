@@ -0,0 +1,71 @@
+/// One task in a `codepilot run` script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub task: String,
+    /// Ask before running this step, so a script can pause ahead of anything
+    /// the user wants to eyeball first.
+    pub confirm: bool,
+}
+
+/// A script is one task per line. Blank lines and `#` comments are skipped, a
+/// line starting with `confirm:` asks before it runs, and indented lines carry
+/// on the task above them:
+///
+/// ```text
+/// # weekly chores
+/// bump the copyright year in src/footer.ts
+/// confirm: remove the deprecated helpers from src/legacy.ts
+///   and update their call sites
+/// ```
+pub fn parse(script: &str) -> Vec<Step> {
+    let mut steps: Vec<Step> = Vec::new();
+    for line in script.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace)
+            && let Some(step) = steps.last_mut()
+        {
+            step.task.push('\n');
+            step.task.push_str(trimmed);
+            continue;
+        }
+        let (task, confirm) = match trimmed.strip_prefix("confirm:") {
+            Some(task) => (task.trim(), true),
+            None => (trimmed, false),
+        };
+        if !task.is_empty() {
+            steps.push(Step {
+                task: task.to_string(),
+                confirm,
+            });
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comments_confirm_steps_and_continuations() {
+        let steps = parse(
+            "# chores\nbump the year\n\nconfirm: drop legacy helpers\n  and their call sites\n",
+        );
+        assert_eq!(
+            steps,
+            vec![
+                Step {
+                    task: "bump the year".to_string(),
+                    confirm: false,
+                },
+                Step {
+                    task: "drop legacy helpers\nand their call sites".to_string(),
+                    confirm: true,
+                },
+            ]
+        );
+    }
+}
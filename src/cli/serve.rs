@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+use crate::cli::doctor::ProviderStatus;
+use crate::cli::headless::{self, Report};
+use crate::config::Config;
+use crate::redact;
+use crate::session::SessionContext;
+
+/// Requests bigger than this (headers and body together) are refused.
+const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// How long a client gets to send its whole request before it's answered 408
+/// and dropped, so a stalled one doesn't hold its task open.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a request asked for, by method and path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route {
+    /// `POST /query`: run a task.
+    Query,
+    /// `GET /services`: whether the LLM provider answers.
+    Services,
+    /// `GET /sessions`: ids of the sessions so far.
+    Sessions,
+    /// `GET /sessions/:id`: one session's tasks and outcomes.
+    Session(u64),
+    MethodNotAllowed,
+    NotFound,
+}
+
+fn route(method: &str, path: &str) -> Route {
+    let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+    let expected = match path {
+        "/query" => Some(("POST", Route::Query)),
+        "/services" => Some(("GET", Route::Services)),
+        "/sessions" => Some(("GET", Route::Sessions)),
+        _ => path
+            .strip_prefix("/sessions/")
+            .and_then(|id| id.parse().ok())
+            .map(|id| ("GET", Route::Session(id))),
+    };
+    match expected {
+        Some((allowed, route)) if allowed == method => route,
+        Some(_) => Route::MethodNotAllowed,
+        None => Route::NotFound,
+    }
+}
+
+/// The body of `POST /query`. Without `session`, a new one is started; its id
+/// comes back in the response for follow-ups.
+#[derive(Debug, serde::Deserialize)]
+struct QueryRequest {
+    task: String,
+    session: Option<u64>,
+}
+
+#[derive(serde::Serialize)]
+struct QueryResponse {
+    session: u64,
+    #[serde(flatten)]
+    report: Report,
+}
+
+/// State shared by every connection.
+struct Server {
+    config: Config,
+    sessions: Mutex<HashMap<u64, SessionContext>>,
+    /// Held while a task runs: the repo has one working tree, so tasks from
+    /// different clients take turns rather than editing it at once.
+    running: Mutex<()>,
+}
+
+/// `codepilot serve`: a small JSON API over HTTP/1.1 for editors, bots, and
+/// dashboards to share one long-lived process. Nobody is there to confirm
+/// edits, so ones that would ask are declined unless `config.auto_approve`.
+pub async fn serve(config: Config, addr: SocketAddr) -> Result<()> {
+    config.validate()?;
+    let listener = TcpListener::bind(addr).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    if !addr.ip().is_loopback() {
+        eprintln!(
+            "Warning: {addr} is reachable from other machines, and anything that can connect can edit the repo"
+        );
+    }
+
+    let server = Arc::new(Server {
+        config,
+        sessions: Mutex::new(HashMap::new()),
+        running: Mutex::new(()),
+    });
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &server).await {
+                eprintln!("Request failed: {e}");
+            }
+        });
+    }
+}
+
+/// A request as read off the connection, or why there's nothing to run.
+enum Incoming {
+    Request {
+        method: String,
+        path: String,
+        body: Vec<u8>,
+    },
+    /// The client hung up before sending a whole request.
+    Closed,
+    /// Answered with this status and message instead of being routed.
+    Refused(u16, String),
+}
+
+/// Read one request: headers until they parse, then `Content-Length` bytes of
+/// body, within `MAX_REQUEST_BYTES` in all.
+async fn read_request(stream: &mut (impl AsyncRead + Unpin)) -> Result<Incoming> {
+    let too_large = || Incoming::Refused(413, "request too large".to_string());
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let (method, path, body_start, content_length) = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(Incoming::Closed);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Ok(too_large());
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut request = httparse::Request::new(&mut headers);
+        match request.parse(&buffer) {
+            Ok(httparse::Status::Complete(body_start)) => {
+                let content_length = request
+                    .headers
+                    .iter()
+                    .find(|header| header.name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|header| std::str::from_utf8(header.value).ok()?.trim().parse().ok())
+                    .unwrap_or(0usize);
+                break (
+                    request.method.unwrap_or_default().to_string(),
+                    request.path.unwrap_or_default().to_string(),
+                    body_start,
+                    content_length,
+                );
+            }
+            Ok(httparse::Status::Partial) => {}
+            Err(e) => return Ok(Incoming::Refused(400, format!("not an HTTP request: {e}"))),
+        }
+    };
+    let Some(end) = body_start
+        .checked_add(content_length)
+        .filter(|end| *end <= MAX_REQUEST_BYTES)
+    else {
+        return Ok(too_large());
+    };
+    while buffer.len() < end {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body = buffer[body_start..buffer.len().min(end)].to_vec();
+    Ok(Incoming::Request { method, path, body })
+}
+
+async fn handle(mut stream: TcpStream, server: &Server) -> Result<()> {
+    let (method, path, body) =
+        match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+            Ok(Ok(Incoming::Request { method, path, body })) => (method, path, body),
+            Ok(Ok(Incoming::Closed)) => return Ok(()),
+            Ok(Ok(Incoming::Refused(status, message))) => {
+                return respond(&mut stream, status, &error(&message)).await;
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(_) => {
+                return respond(
+                    &mut stream,
+                    408,
+                    &error("the request took too long to send"),
+                )
+                .await;
+            }
+        };
+
+    let (status, response) = match route(&method, &path) {
+        Route::Query => match serde_json::from_slice::<QueryRequest>(&body) {
+            Ok(query) => query_response(server, query).await,
+            Err(e) => (
+                400,
+                error(&format!(
+                    "expected {{\"task\": \"...\", \"session\": id?}}: {e}"
+                )),
+            ),
+        },
+        Route::Services => {
+            let llm = ProviderStatus::check(&server.config).await;
+            (200, serde_json::json!({ "llm": llm }))
+        }
+        Route::Sessions => {
+            let mut ids: Vec<u64> = server.sessions.lock().await.keys().copied().collect();
+            ids.sort_unstable();
+            (200, serde_json::json!({ "sessions": ids }))
+        }
+        Route::Session(id) => match server.sessions.lock().await.get(&id) {
+            Some(session) => (200, serde_json::json!({ "id": id, "turns": session.turns })),
+            None => (404, error(&format!("no session {id}"))),
+        },
+        Route::MethodNotAllowed => (405, error(&format!("{method} isn't supported on {path}"))),
+        Route::NotFound => (
+            404,
+            error(&format!(
+                "no route {path}; try POST /query, GET /services, GET /sessions/:id"
+            )),
+        ),
+    };
+    respond(&mut stream, status, &response).await
+}
+
+async fn query_response(server: &Server, query: QueryRequest) -> (u16, serde_json::Value) {
+    let (id, session) = {
+        let mut sessions = server.sessions.lock().await;
+        match query.session {
+            Some(id) => match sessions.get(&id) {
+                Some(session) => (id, session.clone()),
+                None => return (404, error(&format!("no session {id}"))),
+            },
+            None => {
+                let id = sessions.keys().max().map_or(1, |id| id + 1);
+                sessions.insert(id, SessionContext::new());
+                (id, SessionContext::new())
+            }
+        }
+    };
+
+    let (result, events) = {
+        let _turn = server.running.lock().await;
+        headless::run(&server.config, &query.task, &session, false).await
+    };
+    let report = Report::new(&server.config, &query.task, &result, events);
+
+    let mut sessions = server.sessions.lock().await;
+    let session = sessions.entry(id).or_default();
+    match &result {
+        Ok(result) => session.record_result(&query.task, result),
+        Err(e) => session.record_error(&query.task, &e.to_string()),
+    }
+    let status = if report.error.is_some() { 500 } else { 200 };
+    match serde_json::to_value(QueryResponse {
+        session: id,
+        report,
+    }) {
+        Ok(value) => (status, value),
+        Err(e) => (500, error(&e.to_string())),
+    }
+}
+
+fn error(message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": message })
+}
+
+/// Send `body`, secrets masked, and close the connection.
+async fn respond(stream: &mut TcpStream, status: u16, body: &serde_json::Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    };
+    let mut body = body.clone();
+    redact::value(&mut body, &[]);
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_by_method_and_path() {
+        assert_eq!(route("POST", "/query"), Route::Query);
+        assert_eq!(route("GET", "/query"), Route::MethodNotAllowed);
+        assert_eq!(route("GET", "/sessions/3"), Route::Session(3));
+        assert_eq!(route("GET", "/sessions/?all"), Route::Sessions);
+        assert_eq!(route("GET", "/sessions/abc"), Route::NotFound);
+    }
+
+    #[tokio::test]
+    async fn oversized_and_malformed_requests_are_refused() {
+        let status = |incoming: Incoming| match incoming {
+            Incoming::Refused(status, _) => Some(status),
+            _ => None,
+        };
+        let huge = format!(
+            "POST /query HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            usize::MAX
+        );
+        let read = read_request(&mut huge.as_bytes()).await.unwrap();
+        assert_eq!(status(read), Some(413));
+        let read = read_request(&mut &b"NOT HTTP\r\n\r\n"[..]).await.unwrap();
+        assert_eq!(status(read), Some(400));
+
+        let query = b"POST /query HTTP/1.1\r\nContent-Length: 15\r\n\r\n{\"task\": \"go\"}";
+        match read_request(&mut &query[..]).await.unwrap() {
+            Incoming::Request { method, body, .. } => {
+                assert_eq!(method, "POST");
+                assert_eq!(body, b"{\"task\": \"go\"}");
+            }
+            _ => panic!("expected a request"),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::Config;
+
+/// One line of the first-run form, and the `.env` variable it's saved as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupField {
+    /// Catalog id of the label, e.g. `setup-field-model`.
+    pub label: &'static str,
+    pub var: &'static str,
+    pub value: String,
+    /// Shown as dots - the value is an API key.
+    pub secret: bool,
+}
+
+/// First-run setup, shown instead of the main view while `OPENAI_API_KEY` is
+/// missing or still the template placeholder. The values are tested against
+/// the provider before anything is written to `.env`.
+#[derive(Debug, Clone)]
+pub struct SetupWizard {
+    pub fields: Vec<SetupField>,
+    pub focus: usize,
+    /// The connection test for the entered values is in flight.
+    pub checking: bool,
+    /// Why the last test failed, if it did.
+    pub error: Option<String>,
+}
+
+impl SetupWizard {
+    /// Prefilled from whatever `config` already has, so only the missing key
+    /// needs typing.
+    pub fn new(config: &Config) -> Self {
+        let field = |label, var, value: &str, secret| SetupField {
+            label,
+            var,
+            value: value.to_string(),
+            secret,
+        };
+        Self {
+            fields: vec![
+                field(
+                    "setup-field-key",
+                    "OPENAI_API_KEY",
+                    config.openai_api_key.as_deref().unwrap_or(""),
+                    true,
+                ),
+                field(
+                    "setup-field-url",
+                    "OPENAI_BASE_URL",
+                    config
+                        .openai_base_url
+                        .as_deref()
+                        .unwrap_or("https://api.openai.com/v1"),
+                    false,
+                ),
+                field("setup-field-model", "OPENAI_MODEL", &config.model, false),
+            ],
+            focus: 0,
+            checking: false,
+            error: None,
+        }
+    }
+
+    pub fn focused(&mut self) -> &mut String {
+        &mut self.fields[self.focus].value
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focus = (self.focus + 1) % self.fields.len();
+    }
+
+    pub fn focus_previous(&mut self) {
+        self.focus = (self.focus + self.fields.len() - 1) % self.fields.len();
+    }
+
+    pub fn on_last_field(&self) -> bool {
+        self.focus + 1 == self.fields.len()
+    }
+
+    /// `config` with the entered values applied. Blank fields keep what was there.
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        for field in &self.fields {
+            let value = field.value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match field.var {
+                "OPENAI_API_KEY" => config.openai_api_key = Some(value.to_string()),
+                "OPENAI_BASE_URL" => config.openai_base_url = Some(value.to_string()),
+                "OPENAI_MODEL" => config.model = value.to_string(),
+                _ => {}
+            }
+        }
+        config
+    }
+
+    /// The non-blank fields as `(var, value)` pairs for `write_env_file`.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        self.fields
+            .iter()
+            .filter(|field| !field.value.trim().is_empty())
+            .map(|field| (field.var, field.value.trim().to_string()))
+            .collect()
+    }
+}
+
+/// Set `entries` in the `.env` file at `path`, creating it if needed. Existing
+/// assignments are replaced in place, so comments and unrelated settings survive.
+pub fn write_env_file(path: &Path, entries: &[(&str, String)]) -> Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut lines: Vec<String> = existing.lines().map(str::to_string).collect();
+    for (var, value) in entries {
+        let assignment = format!("{var}={value}");
+        let prefix = format!("{var}=");
+        match lines
+            .iter_mut()
+            .find(|line| line.trim_start().starts_with(&prefix))
+        {
+            Some(line) => *line = assignment,
+            None => lines.push(assignment),
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_file_updates_in_place_and_appends() {
+        let dir = std::env::temp_dir().join(format!("codepilot-setup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(".env");
+        std::fs::write(&path, "# OpenAI\nOPENAI_API_KEY=old\nTHEME=light\n").unwrap();
+
+        write_env_file(
+            &path,
+            &[
+                ("OPENAI_API_KEY", "sk-new".to_string()),
+                ("OPENAI_MODEL", "gpt-4o".to_string()),
+            ],
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "# OpenAI\nOPENAI_API_KEY=sk-new\nTHEME=light\nOPENAI_MODEL=gpt-4o\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,9 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::cli::message::{Message, MessageFilter, MessageKind};
+use crate::formatter::{Block, DiffLine, ResponseFormatter, display_width, split_to_width};
+use crate::redact;
+
 /// A single completed edit, shown in the Ctrl+O detail view and persisted to
 /// `{save_state_dir}/history.jsonl` so it survives a restart.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -15,26 +21,215 @@ pub struct EditDetail {
     /// Human-readable gate result, e.g. "tsc: passed" or "tsc: failed - <error>".
     #[serde(default)]
     pub verification: Option<String>,
+    /// Edits proposed before this one was kept or given up on (1 = first try).
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// Proposed in plan mode: never written, never verified.
+    #[serde(default)]
+    pub planned: bool,
 }
 
 fn default_applied() -> bool {
     true
 }
 
-#[derive(Debug)]
+fn default_attempts() -> u32 {
+    1
+}
+
+/// An edit the orchestrator is holding until the user answers the confirmation modal.
+#[derive(Debug, Clone)]
+pub struct PendingConfirmation {
+    pub path: std::path::PathBuf,
+    pub content: String,
+    pub class: crate::policy::ActionClass,
+    /// The suspect line that made this ask; see `ConfirmationRequest::injection`.
+    pub injection: Option<String>,
+    /// `content` was changed in `$EDITOR` and is no longer what the model proposed.
+    pub edited: bool,
+}
+
+/// One conversation tab. Titles and ids always live here; the content fields
+/// only hold the tab's messages and session while it's in the background - the
+/// active tab's are swapped out into `AppState` itself.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ConversationTab {
+    /// Stable across closing other tabs, so a running task can find its way home.
+    /// Not saved: a resumed session's tabs are numbered afresh.
+    #[serde(skip)]
+    pub id: u64,
+    /// The first task run in the tab, shortened; empty until then.
+    pub title: String,
+    pub messages: Vec<Message>,
+    pub session: crate::session::SessionContext,
+    pub message_scroll: usize,
+    #[serde(skip)]
+    pub selected_message: Option<usize>,
+}
+
+/// A task submitted while another was running, waiting its turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedTask {
+    pub task: String,
+    /// The tab it was typed in, where its output goes.
+    pub tab_id: u64,
+    /// The user message it runs again, if it's a rerun.
+    pub rerun_of: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// The startup list of saved sessions to resume, newest first.
+#[derive(Debug, Clone)]
+pub struct SessionPicker {
+    pub sessions: Vec<(std::path::PathBuf, crate::cli::persistence::SavedSession)>,
+    pub cursor: usize,
+}
+
+/// `/history <term>` results across saved sessions: Enter reopens the
+/// exchange in a new tab, `r` puts its task back in the input box.
+#[derive(Debug, Clone)]
+pub struct HistorySearch {
+    pub query: String,
+    pub hits: Vec<crate::cli::persistence::SearchHit>,
+    pub cursor: usize,
+}
+
+/// The `/stats` overlay: this run's tabs, and every saved session besides.
+#[derive(Debug, Clone)]
+pub struct StatsView {
+    pub session: crate::cli::stats::Stats,
+    /// `None` without `SAVE_STATE_DIR`.
+    pub history: Option<crate::cli::stats::Stats>,
+}
+
+/// How many unstarred recent tasks the tasks panel lists.
+const MAX_RECENT_QUERIES: usize = 20;
+
+/// The completion popup shows at most this many entries.
+const MAX_COMPLETIONS: usize = 8;
+
+/// Oldest log-pane lines are dropped past this.
+const MAX_LOG_LINES: usize = 500;
+
+/// Tab titles are cut to this many characters in the tab strip.
+const MAX_TAB_TITLE_CHARS: usize = 16;
+
+#[derive(Debug, Default)]
 pub struct AppState {
     pub input_text: String,
     pub cursor_position: usize,
+    /// Set by `stage_rerun`: the tab and original user message the input box
+    /// is running again. Dropped whenever the input is replaced.
+    pub rerun_source: Option<(u64, chrono::DateTime<chrono::Utc>)>,
     pub is_input_mode: bool,
-    pub messages: Vec<String>,
+    pub messages: Vec<Message>,
     pub is_processing: bool,
     pub show_help: bool,
+    /// Where help was opened from, the filter typed into it, and its scroll.
+    pub help_context: crate::cli::help::HelpContext,
+    pub help_query: String,
+    pub is_help_search: bool,
+    pub help_scroll: usize,
     pub message_scroll: usize, // Scroll position for messages
     pub messages_expanded: Vec<String>, // Expanded messages with line wrapping
+    /// Width and inner height of the activity pane in the last frame; messages are
+    /// wrapped to the width and scrolling is clamped to the height.
+    pub messages_width: usize,
+    pub messages_height: usize,
+    /// For each line of `messages_expanded`, the index of the message it came from.
+    pub message_line_owner: Vec<usize>,
+    /// For each line of `messages_expanded`, what it is if it's part of a diff
+    /// in its message's response.
+    pub message_line_diff: Vec<Option<DiffLine>>,
+    /// Messages not matching this are left out of `messages_expanded`.
+    pub message_filter: MessageFilter,
+    /// Message under the selection cursor in the activity pane, if any.
+    pub selected_message: Option<usize>,
+    /// Whether the selected message is open in the full-text overlay.
+    pub show_message: bool,
+    /// Typing a `/` search query over the activity pane.
+    pub is_search_mode: bool,
+    pub search_query: String,
+    /// Lines of `messages_expanded` matching `search_query`, in order.
+    pub search_matches: Vec<usize>,
+    /// Which entry of `search_matches` n/N last jumped to.
+    pub search_cursor: usize,
     pub target_repo_path: String,
     pub show_details: bool,
     pub edit_history: Vec<EditDetail>,
     pub detail_cursor: usize,
+    /// Typing an `f` filter over the edit history in the detail view.
+    pub is_detail_filter_mode: bool,
+    /// Fuzzy filter on task and path; browsing skips entries that don't match.
+    pub detail_filter: String,
+    /// Horizontal scroll of the message column in the detail view's tsc error table.
+    pub detail_table_scroll: usize,
+    pub agent_mode: crate::config::AgentMode,
+    /// `READ_ONLY`: shown next to the agent mode, since it overrides it.
+    pub read_only: bool,
+    pub pending_confirmation: Option<PendingConfirmation>,
+    /// Earlier tasks this session, fed into each new task's prompt.
+    pub session: crate::session::SessionContext,
+    /// Structured orchestrator events, oldest first, for the log pane.
+    pub logs: Vec<String>,
+    /// Split the activity area: conversation on the left, `logs` on the right.
+    /// While it's open, progress events only go to the log pane.
+    pub show_log_pane: bool,
+    pub tabs: Vec<ConversationTab>,
+    /// Index into `tabs` of the conversation whose content is in the fields above.
+    pub active_tab: usize,
+    next_tab_id: u64,
+    /// When the running task started, for the elapsed-time readout.
+    pub processing_started: Option<std::time::Instant>,
+    /// What the running task is doing right now, e.g. "running tsc".
+    pub current_step: Option<String>,
+    /// Submitted tasks, oldest first, for Up/Down recall in input mode.
+    pub input_history: Vec<String>,
+    /// Index into `input_history` while browsing it; `None` when editing fresh input.
+    pub history_cursor: Option<usize>,
+    /// What was in the input box before browsing started, restored on Down past the end.
+    pub history_draft: String,
+    /// The latest task's result summary, without the timestamp - what `y` copies.
+    pub last_response: Option<String>,
+    /// `OPENAI_MODEL` and the provider host it's served from, for the status bar.
+    pub model: String,
+    pub provider: String,
+    /// Token usage summed over every completion this session.
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Whether the last LLM request succeeded; `None` before the first one.
+    pub llm_reachable: Option<bool>,
+    /// A connection check started with `r` is in flight.
+    pub checking_connection: bool,
+    /// The first-run form, while no API key is configured.
+    pub setup: Option<crate::cli::setup::SetupWizard>,
+    pub session_picker: Option<SessionPicker>,
+    pub history_search: Option<HistorySearch>,
+    pub stats_view: Option<StatsView>,
+    /// Tasks waiting for the running one to finish, oldest first. They run one
+    /// at a time, in order.
+    pub task_queue: std::collections::VecDeque<QueuedTask>,
+    /// Asked to quit with a task running: wait, abort, or keep going?
+    pub quit_prompt: bool,
+    /// Chose to wait: quit as soon as the running task is done.
+    pub quit_when_idle: bool,
+    /// The recent/starred tasks panel.
+    pub show_queries: bool,
+    pub query_cursor: usize,
+    /// Starred tasks, oldest first; listed above the recent ones.
+    pub favorites: Vec<String>,
+    /// Bookmarked messages from any tab or run, oldest first.
+    pub bookmarks: Vec<Message>,
+    /// The `/bookmarks` list's cursor, while it's open.
+    pub bookmark_cursor: Option<usize>,
+    pub theme: crate::cli::theme::Theme,
+    pub keymap: crate::cli::keymap::Keymap,
+    /// Typing a `:` command (vim keymap).
+    pub is_command_mode: bool,
+    pub command_text: String,
+    /// Files in the target repo, for `@path` completion. Loaded once at startup.
+    pub repo_files: Vec<String>,
+    /// Highlighted entry of the completion popup; clamped to whatever it offers.
+    pub completion_cursor: usize,
 }
 
 impl AppState {
@@ -42,43 +237,801 @@ impl AppState {
         Self {
             input_text: String::new(),
             cursor_position: 0,
+            rerun_source: None,
             is_input_mode: false,
             messages: Vec::new(),
             is_processing: false,
             show_help: false,
+            help_context: crate::cli::help::HelpContext::default(),
+            help_query: String::new(),
+            is_help_search: false,
+            help_scroll: 0,
             message_scroll: 0,
             messages_expanded: Vec::new(),
+            messages_width: 100,
+            messages_height: 10,
+            message_line_owner: Vec::new(),
+            message_line_diff: Vec::new(),
+            message_filter: MessageFilter::default(),
+            selected_message: None,
+            show_message: false,
+            is_search_mode: false,
+            search_query: String::new(),
+            search_matches: Vec::new(),
+            search_cursor: 0,
             target_repo_path: String::new(),
             show_details: false,
             edit_history: Vec::new(),
             detail_cursor: 0,
+            is_detail_filter_mode: false,
+            detail_filter: String::new(),
+            detail_table_scroll: 0,
+            agent_mode: crate::config::AgentMode::default(),
+            read_only: false,
+            pending_confirmation: None,
+            session: crate::session::SessionContext::new(),
+            logs: Vec::new(),
+            show_log_pane: false,
+            tabs: vec![ConversationTab::default()],
+            active_tab: 0,
+            next_tab_id: 1,
+            processing_started: None,
+            current_step: None,
+            input_history: Vec::new(),
+            history_cursor: None,
+            history_draft: String::new(),
+            last_response: None,
+            model: String::new(),
+            provider: String::new(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            llm_reachable: None,
+            checking_connection: false,
+            setup: None,
+            session_picker: None,
+            history_search: None,
+            stats_view: None,
+            task_queue: std::collections::VecDeque::new(),
+            quit_prompt: false,
+            quit_when_idle: false,
+            show_queries: false,
+            query_cursor: 0,
+            favorites: Vec::new(),
+            bookmarks: Vec::new(),
+            bookmark_cursor: None,
+            theme: crate::cli::theme::Theme::default(),
+            keymap: crate::cli::keymap::Keymap::default(),
+            is_command_mode: false,
+            command_text: String::new(),
+            repo_files: Vec::new(),
+            completion_cursor: 0,
         }
     }
 
+    /// Remember a submitted task for recall, skipping immediate repeats.
+    pub fn record_input(&mut self, input: &str) {
+        self.history_cursor = None;
+        if self.input_history.last().is_some_and(|last| last == input) {
+            return;
+        }
+        self.input_history.push(input.to_string());
+        if self.input_history.len() > crate::cli::persistence::MAX_INPUT_HISTORY {
+            self.input_history.remove(0);
+        }
+    }
+
+    /// What the tasks panel lists: starred tasks, then the most recent others,
+    /// newest first. The flag says whether the entry is starred.
+    pub fn query_entries(&self) -> Vec<(&str, bool)> {
+        let mut entries: Vec<(&str, bool)> = self
+            .favorites
+            .iter()
+            .map(|task| (task.as_str(), true))
+            .collect();
+        for task in self.input_history.iter().rev() {
+            if entries.len() >= self.favorites.len() + MAX_RECENT_QUERIES {
+                break;
+            }
+            if !entries.iter().any(|&(listed, _)| listed == task) {
+                entries.push((task, false));
+            }
+        }
+        entries
+    }
+
+    /// Star or unstar the highlighted panel entry.
+    pub fn toggle_favorite(&mut self) {
+        let Some((task, starred)) = self
+            .query_entries()
+            .get(self.query_cursor)
+            .map(|&(task, s)| (task.to_string(), s))
+        else {
+            return;
+        };
+        if starred {
+            self.favorites.retain(|favorite| *favorite != task);
+        } else {
+            self.favorites.push(task);
+        }
+        self.query_cursor = self.query_cursor.min(self.query_entries().len().saturating_sub(1));
+    }
+
+    /// Bookmark the selected message, or drop its bookmark if it has one.
+    /// `Some(true)` if it's bookmarked now, `None` with nothing selected.
+    pub fn toggle_bookmark(&mut self) -> Option<bool> {
+        let message = self.selected_message.and_then(|i| self.messages.get(i))?.clone();
+        let before = self.bookmarks.len();
+        self.bookmarks.retain(|bookmark| {
+            (bookmark.timestamp, &bookmark.body) != (message.timestamp, &message.body)
+        });
+        if self.bookmarks.len() < before {
+            return Some(false);
+        }
+        self.bookmarks.push(message);
+        Some(true)
+    }
+
+    /// Show the selected message's shortened values in full, or shortened
+    /// again. `None` if no message is selected or nothing in it is cut short.
+    pub fn toggle_expanded(&mut self, formatter: &ResponseFormatter) -> Option<bool> {
+        let message = self.selected_message.and_then(|i| self.messages.get_mut(i))?;
+        let response = message.response.as_ref()?;
+        if !message.expanded && !formatter.truncates(response) {
+            return None;
+        }
+        message.expanded = !message.expanded;
+        message.body = if message.expanded {
+            formatter.clone().expanded().text(response)
+        } else {
+            formatter.text(response)
+        };
+        Some(message.expanded)
+    }
+
+    /// Drop the bookmark under the `/bookmarks` cursor, closing the list once
+    /// it's empty.
+    pub fn remove_bookmark(&mut self) {
+        let Some(cursor) = self.bookmark_cursor else {
+            return;
+        };
+        if cursor < self.bookmarks.len() {
+            self.bookmarks.remove(cursor);
+        }
+        self.bookmark_cursor =
+            (!self.bookmarks.is_empty()).then(|| cursor.min(self.bookmarks.len() - 1));
+    }
+
+    /// Up in input mode: step back to the previous submitted task.
+    pub fn history_previous(&mut self) {
+        let index = match self.history_cursor {
+            None if self.input_history.is_empty() => return,
+            None => {
+                self.history_draft = self.input_text.clone();
+                self.input_history.len() - 1
+            }
+            Some(index) => index.saturating_sub(1),
+        };
+        self.history_cursor = Some(index);
+        self.set_input(self.input_history[index].clone());
+    }
+
+    /// Down in input mode: step forward, back to the draft after the newest entry.
+    pub fn history_next(&mut self) {
+        let Some(index) = self.history_cursor else {
+            return;
+        };
+        if index + 1 < self.input_history.len() {
+            self.history_cursor = Some(index + 1);
+            self.set_input(self.input_history[index + 1].clone());
+        } else {
+            self.history_cursor = None;
+            let draft = std::mem::take(&mut self.history_draft);
+            self.set_input(draft);
+        }
+    }
+
+    pub fn set_input(&mut self, text: String) {
+        self.input_text = text;
+        self.cursor_position = self.input_text.len();
+        self.rerun_source = None;
+    }
+
+    /// Put the selected user message's task in the input box, remembering
+    /// where it came from so the new run threads under it. `false` if the
+    /// selection isn't a task.
+    pub fn stage_rerun(&mut self) -> bool {
+        let Some(message) = self.selected_message.and_then(|i| self.messages.get(i)) else {
+            return false;
+        };
+        let Some(task) = message.task().map(str::to_string) else {
+            return false;
+        };
+        // Rerunning a rerun threads under the same original.
+        let original = message.rerun_of.unwrap_or(message.timestamp);
+        self.set_input(task);
+        self.rerun_source = Some((self.active_tab_id(), original));
+        true
+    }
+
+    // Input editing. `cursor_position` is a byte offset into `input_text` and is
+    // always kept on a grapheme boundary, so an emoji or a letter with combining
+    // accents moves and deletes as one unit.
+
+    pub fn insert_char(&mut self, c: char) {
+        self.input_text.insert(self.cursor_position, c);
+        self.cursor_position += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.input_text.insert_str(self.cursor_position, text);
+        self.cursor_position += text.len();
+    }
+
+    /// Backspace: delete the char before the cursor.
+    pub fn delete_before_cursor(&mut self) {
+        let start = self.previous_boundary(self.cursor_position);
+        self.input_text.replace_range(start..self.cursor_position, "");
+        self.cursor_position = start;
+    }
+
+    /// Delete: delete the char under the cursor.
+    pub fn delete_at_cursor(&mut self) {
+        let end = self.next_boundary(self.cursor_position);
+        self.input_text.replace_range(self.cursor_position..end, "");
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        self.cursor_position = self.previous_boundary(self.cursor_position);
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        self.cursor_position = self.next_boundary(self.cursor_position);
+    }
+
+    /// Home: start of the current line.
+    pub fn move_cursor_line_start(&mut self) {
+        self.cursor_position = self.input_text[..self.cursor_position]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+    }
+
+    /// End: end of the current line.
+    pub fn move_cursor_line_end(&mut self) {
+        self.cursor_position = self.input_text[self.cursor_position..]
+            .find('\n')
+            .map_or(self.input_text.len(), |i| self.cursor_position + i);
+    }
+
+    /// Ctrl+Left: start of the previous word.
+    pub fn move_cursor_word_left(&mut self) {
+        self.cursor_position = self.word_start_before(self.cursor_position);
+    }
+
+    /// Ctrl+Right: end of the next word.
+    pub fn move_cursor_word_right(&mut self) {
+        let rest = &self.input_text[self.cursor_position..];
+        let word_begins = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        let word_len = rest[word_begins..]
+            .find(char::is_whitespace)
+            .unwrap_or(rest.len() - word_begins);
+        self.cursor_position += word_begins + word_len;
+    }
+
+    /// Ctrl+W / Ctrl+Backspace: delete the word before the cursor.
+    pub fn delete_word_before_cursor(&mut self) {
+        let start = self.word_start_before(self.cursor_position);
+        self.input_text.replace_range(start..self.cursor_position, "");
+        self.cursor_position = start;
+    }
+
+    /// Ctrl+K: delete from the cursor to the end of the input.
+    pub fn delete_to_end(&mut self) {
+        self.input_text.truncate(self.cursor_position);
+    }
+
+    /// Ctrl+U: delete from the start of the input to the cursor.
+    pub fn delete_to_start(&mut self) {
+        self.input_text.replace_range(..self.cursor_position, "");
+        self.cursor_position = 0;
+    }
+
+    fn previous_boundary(&self, pos: usize) -> usize {
+        self.input_text[..pos]
+            .graphemes(true)
+            .next_back()
+            .map_or(pos, |g| pos - g.len())
+    }
+
+    fn next_boundary(&self, pos: usize) -> usize {
+        self.input_text[pos..]
+            .graphemes(true)
+            .next()
+            .map_or(pos, |g| pos + g.len())
+    }
+
+    fn word_start_before(&self, pos: usize) -> usize {
+        let before = self.input_text[..pos].trim_end();
+        before
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + before[i..].chars().next().map_or(1, char::len_utf8))
+    }
+
+    /// What the completion popup offers for the word before the cursor: command
+    /// names for a leading `/word`, repo files for `@word` anywhere.
+    pub fn completions(&self) -> Vec<String> {
+        let (start, word) = self.word_before_cursor();
+        if let Some(query) = word.strip_prefix('@') {
+            let query = query.to_ascii_lowercase();
+            self.repo_files
+                .iter()
+                .filter(|path| path.to_ascii_lowercase().contains(&query))
+                .take(MAX_COMPLETIONS)
+                .map(|path| format!("@{path}"))
+                .collect()
+        } else if word.starts_with('/') && self.input_text[..start].trim().is_empty() {
+            crate::cli::commands::matching(word)
+                .into_iter()
+                .map(|command| format!("/{}", command.name))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn move_completion(&mut self, forward: bool) {
+        let count = self.completions().len();
+        if count == 0 {
+            return;
+        }
+        let current = self.completion_cursor.min(count - 1);
+        self.completion_cursor = if forward {
+            (current + 1) % count
+        } else {
+            (current + count - 1) % count
+        };
+    }
+
+    /// Replace the word before the cursor with the highlighted completion.
+    /// Returns `false` if there was nothing to complete.
+    pub fn accept_completion(&mut self) -> bool {
+        let completions = self.completions();
+        let Some(choice) = completions.get(
+            self.completion_cursor
+                .min(completions.len().saturating_sub(1)),
+        ) else {
+            return false;
+        };
+        let (start, _) = self.word_before_cursor();
+        let replacement = format!("{choice} ");
+        self.input_text.replace_range(start..self.cursor_position, &replacement);
+        self.cursor_position = start + replacement.len();
+        self.completion_cursor = 0;
+        true
+    }
+
+    /// Byte offset where the word before the cursor starts, and the word itself.
+    fn word_before_cursor(&self) -> (usize, &str) {
+        let before = &self.input_text[..self.cursor_position.min(self.input_text.len())];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| {
+            i + before[i..].chars().next().map_or(1, char::len_utf8)
+        });
+        (start, &before[start..])
+    }
+
+    pub fn push_log(&mut self, line: String) {
+        let timestamp = chrono::Utc::now().format("%H:%M:%S");
+        self.logs.push(format!("{timestamp} {}", redact::text(&line)));
+        if self.logs.len() > MAX_LOG_LINES {
+            self.logs.remove(0);
+        }
+    }
+
+    pub fn active_tab_id(&self) -> u64 {
+        self.tabs.get(self.active_tab).map_or(0, |tab| tab.id)
+    }
+
+    pub fn tab_index(&self, id: u64) -> Option<usize> {
+        self.tabs.iter().position(|tab| tab.id == id)
+    }
+
+    /// Name the active tab after its first task.
+    pub fn title_active_tab(&mut self, task: &str) {
+        if let Some(tab) = self.tabs.get_mut(self.active_tab)
+            && tab.title.is_empty()
+        {
+            let first_line = task.lines().next().unwrap_or("").trim();
+            tab.title = first_line.chars().take(MAX_TAB_TITLE_CHARS).collect();
+            if tab.title.len() < first_line.len() {
+                tab.title.push('…');
+            }
+        }
+    }
+
+    /// Park the active tab's content and bring in tab `index`'s. The caller
+    /// re-wraps `messages_expanded` afterwards, since only it knows the width.
+    pub fn switch_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+        if let Some(current) = self.tabs.get_mut(self.active_tab) {
+            current.messages = std::mem::take(&mut self.messages);
+            current.session = std::mem::take(&mut self.session);
+            current.message_scroll = self.message_scroll;
+            current.selected_message = self.selected_message.take();
+        }
+        let next = &mut self.tabs[index];
+        self.messages = std::mem::take(&mut next.messages);
+        self.session = std::mem::take(&mut next.session);
+        self.message_scroll = next.message_scroll;
+        self.selected_message = next.selected_message;
+        self.show_message = false;
+        self.active_tab = index;
+    }
+
+    pub fn new_tab(&mut self) {
+        self.tabs.push(ConversationTab {
+            id: self.next_tab_id,
+            ..ConversationTab::default()
+        });
+        self.next_tab_id += 1;
+        self.clear_search();
+        self.switch_tab(self.tabs.len() - 1);
+    }
+
+    /// Open a tab from a saved session next to the current ones, with message
+    /// `selected` picked out. The caller re-wraps, then scrolls to it.
+    pub fn open_saved_tab(&mut self, tab: ConversationTab, selected: usize) {
+        self.tabs.push(ConversationTab {
+            id: self.next_tab_id,
+            selected_message: Some(selected),
+            ..tab
+        });
+        self.next_tab_id += 1;
+        self.clear_search();
+        self.switch_tab(self.tabs.len() - 1);
+    }
+
+    pub fn next_tab(&mut self) {
+        if self.tabs.len() > 1 {
+            self.clear_search();
+            self.switch_tab((self.active_tab + 1) % self.tabs.len());
+        }
+    }
+
+    pub fn previous_tab(&mut self) {
+        if self.tabs.len() > 1 {
+            self.clear_search();
+            self.switch_tab((self.active_tab + self.tabs.len() - 1) % self.tabs.len());
+        }
+    }
+
+    /// Close the active tab and show its left neighbour. The last tab can't be closed.
+    pub fn close_active_tab(&mut self) -> bool {
+        if self.tabs.len() <= 1 {
+            return false;
+        }
+        let closing = self.active_tab;
+        self.clear_search();
+        self.switch_tab(if closing == 0 { 1 } else { closing - 1 });
+        self.tabs.remove(closing);
+        if self.active_tab > closing {
+            self.active_tab -= 1;
+        }
+        true
+    }
+
+    /// Open the help overlay with the section for whatever has focus first.
+    pub fn open_help(&mut self) {
+        use crate::cli::help::HelpContext;
+        self.help_context = if self.pending_confirmation.is_some() {
+            HelpContext::Confirmation
+        } else if self.show_details {
+            HelpContext::Details
+        } else if self.show_message {
+            HelpContext::Message
+        } else if self.show_queries {
+            HelpContext::Tasks
+        } else if self.is_input_mode {
+            HelpContext::Input
+        } else {
+            HelpContext::Normal
+        };
+        self.help_query.clear();
+        self.is_help_search = false;
+        self.help_scroll = 0;
+        self.show_help = true;
+    }
+
+    /// Every tab with its current contents, the active one's included - what a
+    /// saved session holds.
+    pub fn snapshot_tabs(&self) -> Vec<ConversationTab> {
+        let mut tabs = self.tabs.clone();
+        if let Some(active) = tabs.get_mut(self.active_tab) {
+            active.messages = self.messages.clone();
+            active.session = self.session.clone();
+            active.message_scroll = self.message_scroll;
+        }
+        tabs
+    }
+
+    /// Replace every tab with the ones from a saved session, `active` in front.
+    pub fn restore_tabs(&mut self, tabs: Vec<ConversationTab>, active: usize) {
+        if tabs.is_empty() {
+            return;
+        }
+        self.tabs = tabs
+            .into_iter()
+            .map(|tab| {
+                let id = self.next_tab_id;
+                self.next_tab_id += 1;
+                ConversationTab { id, ..tab }
+            })
+            .collect();
+        self.active_tab = active.min(self.tabs.len() - 1);
+        let tab = &mut self.tabs[self.active_tab];
+        self.messages = std::mem::take(&mut tab.messages);
+        self.session = std::mem::take(&mut tab.session);
+        self.message_scroll = tab.message_scroll;
+        self.selected_message = None;
+        self.clear_search();
+    }
+
     /// Record a new edit and point the detail view at it.
     pub fn push_edit_detail(&mut self, detail: EditDetail) {
         self.edit_history.push(detail);
         self.detail_cursor = self.edit_history.len() - 1;
     }
 
+    /// Indices into `edit_history` that match `detail_filter`, oldest first.
+    pub fn visible_detail_indices(&self) -> Vec<usize> {
+        self.edit_history
+            .iter()
+            .enumerate()
+            .filter(|(_, detail)| {
+                fuzzy_match(&self.detail_filter, &detail.task)
+                    || fuzzy_match(&self.detail_filter, &detail.path.to_string_lossy())
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Re-apply `detail_filter`, moving the cursor to the newest match if the
+    /// current entry no longer matches.
+    pub fn update_detail_filter(&mut self) {
+        let visible = self.visible_detail_indices();
+        if !visible.contains(&self.detail_cursor)
+            && let Some(&newest) = visible.last()
+        {
+            self.detail_cursor = newest;
+        }
+    }
+
+    pub fn clear_detail_filter(&mut self) {
+        self.is_detail_filter_mode = false;
+        self.detail_filter.clear();
+    }
+
     pub fn show_older_detail(&mut self) {
-        self.detail_cursor = self.detail_cursor.saturating_sub(1);
+        self.detail_table_scroll = 0;
+        if let Some(&older) = self
+            .visible_detail_indices()
+            .iter()
+            .rev()
+            .find(|&&i| i < self.detail_cursor)
+        {
+            self.detail_cursor = older;
+        }
     }
 
     pub fn show_newer_detail(&mut self) {
-        if self.detail_cursor + 1 < self.edit_history.len() {
-            self.detail_cursor += 1;
+        self.detail_table_scroll = 0;
+        if let Some(&newer) = self
+            .visible_detail_indices()
+            .iter()
+            .find(|&&i| i > self.detail_cursor)
+        {
+            self.detail_cursor = newer;
         }
     }
 
+    /// A message from the app itself; see `push_message` for the other kinds.
     pub fn add_message(&mut self, message: String) {
-        // Add timestamp to messages
-        let timestamp = chrono::Utc::now().format("%H:%M:%S");
-        let formatted_message = format!("[{}] {}", timestamp, message);
-        self.messages.push(formatted_message);
+        self.push_message(Message::new(MessageKind::System, message));
+    }
+
+    /// Secrets in the body and raw JSON are masked on the way in, so they're
+    /// neither shown nor saved with the session.
+    pub fn push_message(&mut self, mut message: Message) {
+        message.body = redact::text(&message.body);
+        if let Some(raw_json) = &mut message.raw_json {
+            redact::value(raw_json, &[]);
+        }
+        self.messages.push(message);
         if self.messages.len() > 100 {
             self.messages.remove(0);
+            // Keep the cursor on the same message as the indices shift down.
+            self.selected_message = match self.selected_message {
+                Some(0) => {
+                    self.show_message = false;
+                    None
+                }
+                other => other.map(|i| i - 1),
+            };
+        }
+    }
+
+    /// Indices of the messages `message_filter` lets through.
+    fn visible_messages(&self) -> Vec<usize> {
+        (0..self.messages.len())
+            .filter(|&i| self.message_filter.matches(&self.messages[i]))
+            .collect()
+    }
+
+    /// j in the activity pane: select the next shown message (or the newest one).
+    pub fn select_next_message(&mut self) {
+        let visible = self.visible_messages();
+        let Some(&last) = visible.last() else {
+            return;
+        };
+        self.selected_message = Some(match self.selected_message {
+            Some(current) => visible.iter().copied().find(|&i| i > current).unwrap_or(last),
+            None => last,
+        });
+        self.scroll_to_selected();
+    }
+
+    /// k in the activity pane: select the previous shown message (or the newest one).
+    pub fn select_previous_message(&mut self) {
+        let visible = self.visible_messages();
+        let (Some(&first), Some(&last)) = (visible.first(), visible.last()) else {
+            return;
+        };
+        self.selected_message = Some(match self.selected_message {
+            Some(current) => visible.iter().copied().rev().find(|&i| i < current).unwrap_or(first),
+            None => last,
+        });
+        self.scroll_to_selected();
+    }
+
+    /// What `o` opens: the first link in the selected message, or with nothing
+    /// selected, the last link in the newest shown message that has one.
+    pub fn link_to_open(&self) -> Option<String> {
+        let repo = std::path::Path::new(&self.target_repo_path);
+        let links_in =
+            |i: usize| crate::cli::links::find(&self.messages[i].body, repo, &self.repo_files);
+        match self.selected_message.filter(|&i| i < self.messages.len()) {
+            Some(selected) => links_in(selected).into_iter().next(),
+            None => self.visible_messages().into_iter().rev().find_map(|i| links_in(i).pop()),
+        }
+        .map(|link| link.target)
+    }
+
+    /// Show only the messages matching `filter`, dropping a selection it hides.
+    /// The caller re-wraps, as after any other change to the messages.
+    pub fn set_message_filter(&mut self, filter: MessageFilter) {
+        self.message_filter = filter;
+        if let Some(selected) = self.selected_message
+            && !self
+                .messages
+                .get(selected)
+                .is_some_and(|message| self.message_filter.matches(message))
+        {
+            self.clear_message_selection();
+        }
+    }
+
+    pub fn clear_message_selection(&mut self) {
+        self.selected_message = None;
+        self.show_message = false;
+    }
+
+    pub fn selected_message_text(&self) -> Option<String> {
+        self.selected_message
+            .and_then(|i| self.messages.get(i))
+            .map(Message::line)
+    }
+
+    /// Recompute `search_matches` (ASCII case-insensitive) and jump to the first
+    /// match at or below the current scroll position.
+    pub fn update_search(&mut self) {
+        let query = self.search_query.to_ascii_lowercase();
+        self.search_matches = if query.is_empty() {
+            Vec::new()
+        } else {
+            self.messages_expanded
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.to_ascii_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect()
+        };
+        self.search_cursor = self
+            .search_matches
+            .iter()
+            .position(|&line| line >= self.message_scroll)
+            .unwrap_or(0);
+        self.scroll_to_search_match();
+    }
+
+    /// n: jump to the next match, wrapping around.
+    pub fn next_search_match(&mut self) {
+        if !self.search_matches.is_empty() {
+            self.search_cursor = (self.search_cursor + 1) % self.search_matches.len();
+            self.scroll_to_search_match();
+        }
+    }
+
+    /// N: jump to the previous match, wrapping around.
+    pub fn previous_search_match(&mut self) {
+        if !self.search_matches.is_empty() {
+            let len = self.search_matches.len();
+            self.search_cursor = (self.search_cursor + len - 1) % len;
+            self.scroll_to_search_match();
+        }
+    }
+
+    pub fn clear_search(&mut self) {
+        self.is_search_mode = false;
+        self.search_query.clear();
+        self.search_matches.clear();
+        self.search_cursor = 0;
+    }
+
+    fn scroll_to_search_match(&mut self) {
+        let Some(&line) = self.search_matches.get(self.search_cursor) else {
+            return;
+        };
+        if line < self.message_scroll || line >= self.message_scroll + self.messages_height {
+            self.message_scroll = line
+                .saturating_sub(self.messages_height / 2)
+                .min(self.max_message_scroll());
+        }
+    }
+
+    /// Scroll just enough that the selected message's first line is on screen.
+    pub fn scroll_to_selected(&mut self) {
+        let Some(selected) = self.selected_message else {
+            return;
+        };
+        let Some(first_line) = self
+            .message_line_owner
+            .iter()
+            .position(|&owner| owner == selected)
+        else {
+            return;
+        };
+        if first_line < self.message_scroll {
+            self.message_scroll = first_line;
+        } else if first_line >= self.message_scroll + self.messages_height {
+            self.message_scroll = first_line.saturating_sub(self.messages_height.saturating_sub(1));
+        }
+    }
+
+    /// Furthest the activity pane can scroll: the last line at the bottom edge.
+    pub fn max_message_scroll(&self) -> usize {
+        self.messages_expanded.len().saturating_sub(self.messages_height)
+    }
+
+    /// Follow a terminal resize (or the log pane opening): re-wrap to the new
+    /// width, keeping the message at the top of the pane in view.
+    pub fn resize_messages(&mut self, width: usize, height: usize) {
+        if width == self.messages_width && height == self.messages_height {
+            return;
+        }
+        let at_bottom = self.message_scroll >= self.max_message_scroll();
+        let top_message = self.message_line_owner.get(self.message_scroll).copied();
+        self.messages_width = width;
+        self.messages_height = height.max(1);
+        self.rewrap_messages();
+        self.message_scroll = match top_message {
+            Some(owner) if !at_bottom => self
+                .message_line_owner
+                .iter()
+                .position(|&o| o == owner)
+                .unwrap_or(0),
+            _ => usize::MAX,
         }
+        .min(self.max_message_scroll());
     }
 
     pub fn scroll_messages_up(&mut self) {
@@ -88,25 +1041,77 @@ impl AppState {
     }
 
     pub fn scroll_messages_down(&mut self) {
-        let max_scroll = self.messages_expanded.len().saturating_sub(10);
+        let max_scroll = self.max_message_scroll();
         if self.message_scroll < max_scroll {
             self.message_scroll = (self.message_scroll + 3).min(max_scroll); // Scroll faster
         }
     }
 
     pub fn update_messages_expanded(&mut self, width: usize) {
+        self.messages_width = width;
+        self.rewrap_messages();
+        // Auto-scroll to bottom when new messages are added, unless the user is
+        // browsing with the selection cursor.
+        if self.selected_message.is_some() {
+            self.scroll_to_selected();
+        } else {
+            self.message_scroll = self.max_message_scroll();
+        }
+    }
+
+    fn rewrap_messages(&mut self) {
         self.messages_expanded.clear();
-        for message in &self.messages {
-            let wrapped = self.wrap_message(message, width.saturating_sub(4));
+        self.message_line_owner.clear();
+        self.message_line_diff.clear();
+        for (index, message) in self.messages.iter().enumerate() {
+            if !self.message_filter.matches(message) {
+                continue;
+            }
+            let line = match message.rerun_of {
+                Some(original) if message.kind == MessageKind::User => format!(
+                    "↳ {} (again, from {})",
+                    message.line(),
+                    original.format("%H:%M:%S")
+                ),
+                Some(_) => format!("↳ {}", message.line()),
+                None => message.line(),
+            };
+            let wrapped = self.wrap_message(&line, self.messages_width.saturating_sub(4));
+            self.message_line_owner.extend(std::iter::repeat_n(index, wrapped.len()));
+            let diff_lines: Vec<&str> = message
+                .response
+                .iter()
+                .flat_map(|response| &response.blocks)
+                .filter_map(|block| match block {
+                    Block::Diff { diff } => Some(diff.lines()),
+                    _ => None,
+                })
+                .flatten()
+                .collect();
+            self.message_line_diff.extend(wrapped.iter().map(|line| {
+                diff_lines
+                    .contains(&line.as_str())
+                    .then(|| DiffLine::of(line))
+            }));
             self.messages_expanded.extend(wrapped);
         }
-        // Auto-scroll to bottom when new messages are added
-        let max_scroll = self.messages_expanded.len().saturating_sub(10);
-        self.message_scroll = max_scroll;
+        if !self.search_query.is_empty() {
+            let query = self.search_query.to_ascii_lowercase();
+            self.search_matches = self
+                .messages_expanded
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.to_ascii_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect();
+            self.search_cursor = self
+                .search_cursor
+                .min(self.search_matches.len().saturating_sub(1));
+        }
     }
 
     fn wrap_message(&self, message: &str, width: usize) -> Vec<String> {
-        if width < 20 {
+        if width < 8 {
             return vec![message.to_string()];
         }
 
@@ -114,17 +1119,17 @@ impl AppState {
         let lines: Vec<&str> = message.lines().collect();
 
         for line in lines {
-            if line.len() <= width {
+            if display_width(line) <= width {
                 wrapped.push(line.to_string());
             } else {
-                // Handle long lines by breaking at word boundaries
-                let words: Vec<&str> = line.split_whitespace().collect();
+                // Handle long lines by breaking at word boundaries; a word wider
+                // than the pane (a path, a URL) is broken wherever it has to be.
                 let mut current_line = String::new();
 
-                for word in words {
+                for word in line.split_whitespace().flat_map(|word| split_to_width(word, width)) {
                     if current_line.is_empty() {
                         current_line = word.to_string();
-                    } else if current_line.len() + word.len() + 1 <= width {
+                    } else if display_width(&current_line) + display_width(word) < width {
                         current_line.push(' ');
                         current_line.push_str(word);
                     } else {
@@ -147,3 +1152,295 @@ impl AppState {
         wrapped
     }
 }
+
+/// Case-insensitive subsequence match, so "srcidx" matches "src/index.ts".
+/// An empty query matches everything.
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|q| text.any(|t| t == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_happen_at_the_cursor() {
+        let mut state = AppState::new();
+        state.insert_str("add sum");
+        state.move_cursor_word_left();
+        state.insert_str("a ");
+        assert_eq!(state.input_text, "add a sum");
+
+        state.move_cursor_line_end();
+        state.delete_word_before_cursor();
+        assert_eq!(state.input_text, "add a ");
+
+        state.move_cursor_line_start();
+        state.move_cursor_right();
+        state.delete_at_cursor();
+        assert_eq!(state.input_text, "ad a ");
+        state.delete_to_end();
+        assert_eq!(state.input_text, "a");
+    }
+
+    #[test]
+    fn cursor_moves_over_multibyte_chars() {
+        let mut state = AppState::new();
+        state.insert_str("añb");
+        state.move_cursor_left();
+        state.move_cursor_left();
+        state.delete_before_cursor();
+        assert_eq!(state.input_text, "ñb");
+        assert_eq!(state.cursor_position, 0);
+    }
+
+    #[test]
+    fn combining_marks_and_emoji_delete_as_one_unit() {
+        let mut state = AppState::new();
+        state.insert_str("e\u{301}👍🏽");
+        state.delete_before_cursor();
+        assert_eq!(state.input_text, "e\u{301}");
+        state.move_cursor_left();
+        assert_eq!(state.cursor_position, 0);
+        state.delete_at_cursor();
+        assert!(state.input_text.is_empty());
+    }
+
+    #[test]
+    fn selection_follows_messages_as_old_ones_drop_off() {
+        let mut state = AppState::new();
+        for i in 0..100 {
+            state.add_message(format!("message {i}"));
+        }
+        state.update_messages_expanded(100);
+        state.select_previous_message();
+        state.select_previous_message();
+        assert_eq!(state.selected_message, Some(98));
+
+        state.add_message("message 100".to_string());
+        assert_eq!(state.selected_message, Some(97));
+        assert!(state.selected_message_text().unwrap().ends_with("message 98"));
+    }
+
+    #[test]
+    fn detail_filter_skips_non_matching_edits() {
+        let mut state = AppState::new();
+        for (task, path) in [
+            ("add sum", "src/math.ts"),
+            ("fix typo", "README.md"),
+            ("add test", "src/math.test.ts"),
+        ] {
+            state.push_edit_detail(EditDetail {
+                task: task.to_string(),
+                path: path.into(),
+                content: String::new(),
+                bytes: 0,
+                timestamp: chrono::Utc::now(),
+                applied: true,
+                verification: None,
+                attempts: 1,
+                planned: false,
+            });
+        }
+
+        state.detail_filter = "mth".to_string();
+        state.update_detail_filter();
+        assert_eq!(state.visible_detail_indices(), vec![0, 2]);
+        assert_eq!(state.detail_cursor, 2);
+        state.show_older_detail();
+        assert_eq!(state.detail_cursor, 0);
+        state.show_older_detail();
+        assert_eq!(state.detail_cursor, 0);
+    }
+
+    #[test]
+    fn restored_tabs_bring_back_the_active_conversation() {
+        let mut state = AppState::new();
+        state.add_message("first tab".to_string());
+        state.title_active_tab("fix the build");
+        state.new_tab();
+        state.add_message("second tab".to_string());
+        let tabs = state.snapshot_tabs();
+        assert_eq!(tabs.len(), 2);
+        assert_eq!(tabs[1].messages.len(), 1);
+
+        let mut resumed = AppState::new();
+        resumed.restore_tabs(tabs, 0);
+        assert_eq!(resumed.tabs.len(), 2);
+        assert_eq!(resumed.tabs[0].title, "fix the build");
+        assert_eq!(resumed.messages[0].body, "first tab");
+        assert_ne!(resumed.tabs[0].id, resumed.tabs[1].id);
+    }
+
+    #[test]
+    fn tabs_keep_their_own_messages_and_session() {
+        let mut state = AppState::new();
+        state.add_message("first tab".to_string());
+        state.session.record_error("task one", "boom");
+
+        state.new_tab();
+        assert!(state.messages.is_empty());
+        assert!(state.session.turns.is_empty());
+        state.add_message("second tab".to_string());
+        let second = state.active_tab_id();
+
+        state.previous_tab();
+        assert_eq!(state.messages[0].body, "first tab");
+        assert_eq!(state.session.turns.len(), 1);
+
+        state.switch_tab(state.tab_index(second).unwrap());
+        assert!(state.close_active_tab());
+        assert_eq!(state.tabs.len(), 1);
+        assert_eq!(state.messages[0].body, "first tab");
+        assert!(!state.close_active_tab());
+    }
+
+    #[test]
+    fn resizing_rewraps_and_clamps_the_scroll() {
+        let mut state = AppState::new();
+        for i in 0..20 {
+            state.add_message(format!("message {i} with a few words that wrap in a narrow pane"));
+        }
+        state.update_messages_expanded(100);
+        assert_eq!(state.messages_expanded.len(), 20);
+        assert_eq!(state.message_scroll, 10);
+
+        state.resize_messages(30, 5);
+        assert!(state.messages_expanded.iter().all(|line| line.len() <= 26));
+        assert_eq!(state.message_scroll, state.max_message_scroll());
+
+        state.message_scroll = 0;
+        state.resize_messages(100, 40);
+        assert_eq!(state.message_scroll, 0);
+        assert_eq!(state.max_message_scroll(), 0);
+    }
+
+    #[test]
+    fn starred_tasks_list_first_without_repeats() {
+        let mut state = AppState::new();
+        state.record_input("fix the build");
+        state.record_input("add a test");
+        state.record_input("fix the build");
+        assert_eq!(state.query_entries(), vec![("fix the build", false), ("add a test", false)]);
+
+        state.query_cursor = 1;
+        state.toggle_favorite();
+        assert_eq!(state.favorites, vec!["add a test"]);
+        assert_eq!(state.query_entries(), vec![("add a test", true), ("fix the build", false)]);
+
+        state.query_cursor = 0;
+        state.toggle_favorite();
+        assert!(state.favorites.is_empty());
+    }
+
+    #[test]
+    fn completes_commands_and_file_references() {
+        let mut state = AppState::new();
+        state.repo_files = vec!["src/index.ts".to_string(), "src/sum.ts".to_string()];
+
+        state.insert_str("fix @su");
+        assert_eq!(state.completions(), vec!["@src/sum.ts"]);
+        assert!(state.accept_completion());
+        assert_eq!(state.input_text, "fix @src/sum.ts ");
+
+        state.set_input("/cl".to_string());
+        assert_eq!(state.completions(), vec!["/clear"]);
+        state.set_input("fix /cl".to_string());
+        assert!(state.completions().is_empty());
+    }
+
+    #[test]
+    fn search_finds_and_cycles_through_matches() {
+        let mut state = AppState::new();
+        state.add_message("tsc: passed".to_string());
+        state.add_message("Reading src/a.ts".to_string());
+        state.add_message("TSC: failed".to_string());
+        state.update_messages_expanded(100);
+
+        state.search_query = "tsc".to_string();
+        state.update_search();
+        assert_eq!(state.search_matches, vec![0, 2]);
+
+        state.next_search_match();
+        assert_eq!(state.search_cursor, 1);
+        state.next_search_match();
+        assert_eq!(state.search_cursor, 0);
+        state.previous_search_match();
+        assert_eq!(state.search_cursor, 1);
+    }
+
+    #[test]
+    fn history_navigation_restores_the_draft() {
+        let mut state = AppState::new();
+        state.record_input("first");
+        state.record_input("second");
+        state.input_text = "half-typed".to_string();
+
+        state.history_previous();
+        assert_eq!(state.input_text, "second");
+        state.history_previous();
+        state.history_previous();
+        assert_eq!(state.input_text, "first");
+
+        state.history_next();
+        assert_eq!(state.input_text, "second");
+        state.history_next();
+        assert_eq!(state.input_text, "half-typed");
+        assert_eq!(state.history_cursor, None);
+    }
+
+    #[test]
+    fn reruns_thread_under_the_original_task() {
+        let mut state = AppState::new();
+        state.push_message(Message::new(MessageKind::User, "Processing task: add a sum"));
+        state.push_message(Message::new(MessageKind::Agent, "Wrote src/sum.ts"));
+        state.selected_message = Some(1);
+        assert!(!state.stage_rerun());
+
+        state.selected_message = Some(0);
+        assert!(state.stage_rerun());
+        assert_eq!(state.input_text, "add a sum");
+        let (tab, original) = state.rerun_source.unwrap();
+        assert_eq!((tab, original), (state.active_tab_id(), state.messages[0].timestamp));
+
+        state.push_message(
+            Message::new(MessageKind::User, "Processing task: add a sum")
+                .with_rerun_of(Some(original)),
+        );
+        state.update_messages_expanded(80);
+        assert!(state.messages_expanded.last().unwrap().starts_with("↳ ["));
+        state.selected_message = Some(2);
+        assert!(state.stage_rerun());
+        assert_eq!(
+            state.rerun_source.map(|(_, original)| original),
+            Some(state.messages[0].timestamp)
+        );
+
+        state.set_input(String::new());
+        assert_eq!(state.rerun_source, None);
+    }
+
+    #[test]
+    fn bookmarks_toggle_and_survive_the_list_emptying() {
+        let mut state = AppState::new();
+        assert_eq!(state.toggle_bookmark(), None);
+        state.push_message(Message::new(MessageKind::Agent, "Wrote src/sum.ts"));
+        state.push_message(Message::new(MessageKind::Error, "tsc: failed"));
+        state.selected_message = Some(0);
+        assert_eq!(state.toggle_bookmark(), Some(true));
+        state.selected_message = Some(1);
+        assert_eq!(state.toggle_bookmark(), Some(true));
+        assert_eq!(state.toggle_bookmark(), Some(false));
+        assert_eq!(state.bookmarks.len(), 1);
+
+        state.bookmark_cursor = Some(0);
+        state.remove_bookmark();
+        assert!(state.bookmarks.is_empty());
+        assert_eq!(state.bookmark_cursor, None);
+    }
+}
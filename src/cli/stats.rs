@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use crate::cli::message::MessageKind;
+use crate::cli::state::ConversationTab;
+use crate::i18n;
+
+/// Usage over some conversations, worked out from their messages: what was
+/// asked, who answered, what ran in between, and how long it took.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct Stats {
+    pub sessions: usize,
+    /// Tasks submitted, finished or not.
+    pub tasks: usize,
+    /// Finished tasks whose result was an error or a rejected edit.
+    pub failed: usize,
+    /// Finished tasks by the agent that answered.
+    pub by_agent: BTreeMap<String, usize>,
+    /// Progress messages by what ran: the gate's name, or the event
+    /// (`files_requested`, `edit_proposed`, ...).
+    pub tools: BTreeMap<String, usize>,
+    /// Seconds from submitting a task to its result, `None` until one finishes.
+    pub latency: Option<Latency>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Latency {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl Stats {
+    /// Stats over `sessions`, each given as its tabs.
+    pub fn collect<'a>(sessions: impl IntoIterator<Item = &'a [ConversationTab]>) -> Self {
+        let mut stats = Self::default();
+        let mut latencies = Vec::new();
+        for tabs in sessions {
+            stats.sessions += 1;
+            for tab in tabs {
+                let mut submitted = None;
+                for message in &tab.messages {
+                    match message.kind {
+                        MessageKind::User => {
+                            stats.tasks += 1;
+                            submitted = Some(message.timestamp);
+                        }
+                        MessageKind::Tool => {
+                            let tool = message.agent.clone().or_else(|| {
+                                message
+                                    .raw_json
+                                    .as_ref()?
+                                    .get("event")?
+                                    .as_str()
+                                    .map(str::to_string)
+                            });
+                            *stats
+                                .tools
+                                .entry(tool.unwrap_or_else(|| "other".to_string()))
+                                .or_default() += 1;
+                        }
+                        // A result carries the agent's name; app notices don't.
+                        MessageKind::Agent | MessageKind::Error if message.agent.is_some() => {
+                            let Some(started) = submitted.take() else {
+                                continue;
+                            };
+                            let agent = message.agent.clone().unwrap_or_default();
+                            *stats.by_agent.entry(agent).or_default() += 1;
+                            if message.kind == MessageKind::Error {
+                                stats.failed += 1;
+                            }
+                            latencies.push(
+                                (message.timestamp - started).num_milliseconds() as f64 / 1000.0,
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        latencies.sort_by(f64::total_cmp);
+        stats.latency = (!latencies.is_empty()).then(|| Latency {
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p99: percentile(&latencies, 99.0),
+        });
+        stats
+    }
+
+    pub fn finished(&self) -> usize {
+        self.by_agent.values().sum()
+    }
+
+    /// The report `/stats` and `codepilot stats` show, one line per topic.
+    pub fn lines(&self) -> Vec<String> {
+        let finished = self.finished();
+        let failure_rate = match finished {
+            0 => String::new(),
+            _ => format!(" ({:.0}%)", self.failed as f64 * 100.0 / finished as f64),
+        };
+        let counts = |counts: &BTreeMap<String, usize>| {
+            if counts.is_empty() {
+                return i18n::tr("stats-none");
+            }
+            counts
+                .iter()
+                .map(|(name, count)| format!("{name} {count}"))
+                .collect::<Vec<_>>()
+                .join(" · ")
+        };
+        vec![
+            format!(
+                "{}{failure_rate}",
+                i18n::tr_args(
+                    "stats-totals",
+                    &[
+                        ("sessions", &self.sessions.to_string()),
+                        ("tasks", &self.tasks.to_string()),
+                        ("finished", &finished.to_string()),
+                        ("failed", &self.failed.to_string()),
+                    ],
+                )
+            ),
+            i18n::tr_args("stats-agents", &[("counts", &counts(&self.by_agent))]),
+            i18n::tr_args("stats-tools", &[("counts", &counts(&self.tools))]),
+            match self.latency {
+                Some(latency) => i18n::tr_args(
+                    "stats-latency",
+                    &[
+                        ("p50", &format!("{:.1}", latency.p50)),
+                        ("p90", &format!("{:.1}", latency.p90)),
+                        ("p99", &format!("{:.1}", latency.p99)),
+                    ],
+                ),
+                None => i18n::tr("stats-latency-none"),
+            },
+        ]
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty `values`.
+fn percentile(values: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::message::Message;
+    use crate::events::OrchestratorEvent;
+
+    #[test]
+    fn counts_tasks_tools_and_failures_per_exchange() {
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let message = |kind, seconds, agent: Option<&str>| Message {
+            timestamp: at(seconds),
+            agent: agent.map(str::to_string),
+            ..Message::new(kind, "")
+        };
+        let read = OrchestratorEvent::FilesRequested {
+            paths: vec!["src/sum.ts".to_string()],
+        };
+        let tab = ConversationTab {
+            messages: vec![
+                message(MessageKind::User, 0, None),
+                Message::from_event(&read, String::new()),
+                message(MessageKind::Agent, 10, Some("codepilot")),
+                message(MessageKind::System, 11, None),
+                message(MessageKind::User, 20, None),
+                message(MessageKind::Error, 24, Some("codepilot")),
+                message(MessageKind::User, 30, None),
+            ],
+            ..ConversationTab::default()
+        };
+
+        let stats = Stats::collect([std::slice::from_ref(&tab)]);
+        assert_eq!(
+            (stats.sessions, stats.tasks, stats.finished(), stats.failed),
+            (1, 3, 2, 1)
+        );
+        assert_eq!(stats.tools.get("files_requested"), Some(&1));
+        assert_eq!(
+            stats.latency.map(|latency| (latency.p50, latency.p99)),
+            Some((4.0, 10.0))
+        );
+        assert!(stats.lines()[0].ends_with("1 failed (50%)"));
+    }
+}
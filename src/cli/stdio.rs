@@ -0,0 +1,262 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::cli::headless::Report;
+use crate::config::Config;
+use crate::events::OrchestratorEvent;
+use crate::orchestrator::{CodeTaskOrchestrator, ConfirmationResponse};
+use crate::policy::ActionClass;
+use crate::session::SessionContext;
+
+/// One line of input to `codepilot stdio`.
+#[derive(Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    /// Run `task`; everything sent back about it carries `id`.
+    Query { id: u64, task: String },
+    /// Answer the `confirm` sent for query `id`. `content`, if given, is
+    /// written instead of what the model proposed.
+    Confirm {
+        id: u64,
+        approve: bool,
+        #[serde(default)]
+        content: Option<String>,
+    },
+    /// Forget earlier tasks, like `/clear`.
+    Clear,
+}
+
+/// One line of output from `codepilot stdio`.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply<'a> {
+    Event {
+        id: u64,
+        #[serde(flatten)]
+        event: &'a OrchestratorEvent,
+    },
+    /// An edit needs approval; answer with a `confirm` request.
+    Confirm {
+        id: u64,
+        path: &'a Path,
+        class: ActionClass,
+        content: &'a str,
+        /// Why it asks when it otherwise wouldn't; see `ConfirmationRequest`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        injection: Option<&'a str>,
+    },
+    /// The query is done: the same report as `ask --json`.
+    Result {
+        id: u64,
+        #[serde(flatten)]
+        report: &'a Report,
+    },
+    Error {
+        id: Option<u64>,
+        message: String,
+    },
+}
+
+fn send(reply: &Reply) {
+    match serde_json::to_string(reply) {
+        Ok(line) => println!("{line}"),
+        Err(e) => eprintln!("Error: could not encode a reply: {e}"),
+    }
+}
+
+fn send_error(id: Option<u64>, message: impl Into<String>) {
+    send(&Reply::Error {
+        id,
+        message: message.into(),
+    });
+}
+
+/// `codepilot stdio`: newline-delimited JSON on stdin and stdout, for editor
+/// extensions that run CodePilot as a backend process. Queries run one at a
+/// time in a shared session; events stream out while they do, and a
+/// `confirm` line goes out whenever an edit needs approval.
+pub async fn stdio(config: &Config) -> Result<()> {
+    config.validate()?;
+    let mut input = BufReader::new(tokio::io::stdin()).lines();
+    let mut session = SessionContext::new();
+
+    while let Some(line) = input.next_line().await? {
+        match parse(&line) {
+            None => {}
+            Some(Ok(Request::Query { id, task })) => {
+                query(config, id, &task, &mut session, &mut input).await?
+            }
+            Some(Ok(Request::Clear)) => session.clear(),
+            Some(Ok(Request::Confirm { id, .. })) => {
+                send_error(Some(id), "no edit is waiting for confirmation")
+            }
+            Some(Err(message)) => send_error(None, message),
+        }
+    }
+    Ok(())
+}
+
+/// `None` for a blank line, which is skipped rather than reported.
+fn parse(line: &str) -> Option<Result<Request, String>> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    Some(serde_json::from_str(line).map_err(|e| format!("bad request: {e}")))
+}
+
+/// Run one query, still reading stdin so confirmations can come back while it
+/// runs. Other requests in the meantime are refused.
+async fn query(
+    config: &Config,
+    id: u64,
+    task: &str,
+    session: &mut SessionContext,
+    input: &mut Lines<BufReader<Stdin>>,
+) -> Result<()> {
+    let (confirm_tx, mut confirmations) = mpsc::unbounded_channel();
+    let mut orchestrator = match CodeTaskOrchestrator::new(config).await {
+        Ok(orchestrator) => orchestrator.with_confirmation_channel(confirm_tx),
+        Err(e) => {
+            send_error(Some(id), e.to_string());
+            return Ok(());
+        }
+    };
+    let forwarder = forward_events(id, orchestrator.subscribe());
+
+    let mut pending: Option<oneshot::Sender<ConfirmationResponse>> = None;
+    let mut input_open = true;
+    let result = {
+        let run = orchestrator.run_task(task, session);
+        tokio::pin!(run);
+        loop {
+            tokio::select! {
+                result = &mut run => break result,
+                Some(request) = confirmations.recv() => {
+                    send(&Reply::Confirm {
+                        id,
+                        path: &request.path,
+                        class: request.class,
+                        content: &request.content,
+                        injection: request.injection.as_deref(),
+                    });
+                    pending = Some(request.respond);
+                }
+                line = input.next_line(), if input_open => match line? {
+                    // Dropping the pending responder declines it.
+                    None => {
+                        input_open = false;
+                        pending = None;
+                    }
+                    Some(line) => match parse(&line) {
+                        None => {}
+                        Some(Ok(Request::Confirm { id: answered, approve, content })) if answered == id => {
+                            match pending.take() {
+                                Some(respond) => {
+                                    let _ = respond.send(response(approve, content));
+                                }
+                                None => send_error(Some(id), "no edit is waiting for confirmation"),
+                            }
+                        }
+                        Some(Ok(Request::Confirm { id: other, .. })) => {
+                            send_error(Some(other), "no such query is running")
+                        }
+                        Some(Ok(Request::Query { id: other, .. })) => {
+                            send_error(Some(other), format!("query {id} is still running"))
+                        }
+                        Some(Ok(Request::Clear)) => send_error(None, format!("query {id} is still running")),
+                        Some(Err(message)) => send_error(None, message),
+                    },
+                },
+            }
+        }
+    };
+    drop(orchestrator);
+    let events = forwarder.await.unwrap_or_default();
+
+    match &result {
+        Ok(result) => session.record_result(task, result),
+        Err(e) => session.record_error(task, &e.to_string()),
+    }
+    let report = Report::new(config, task, &result, events);
+    send(&Reply::Result {
+        id,
+        report: &report,
+    });
+    Ok(())
+}
+
+fn response(approve: bool, content: Option<String>) -> ConfirmationResponse {
+    match (approve, content) {
+        (false, _) => ConfirmationResponse::Decline,
+        (true, Some(content)) => ConfirmationResponse::ApproveEdited(content),
+        (true, None) => ConfirmationResponse::Approve,
+    }
+}
+
+/// Send each event as it comes in, and hand them all back for the report once
+/// the orchestrator is dropped.
+fn forward_events(
+    id: u64,
+    mut receiver: broadcast::Receiver<OrchestratorEvent>,
+) -> tokio::task::JoinHandle<Vec<OrchestratorEvent>> {
+    tokio::spawn(async move {
+        let mut events = Vec::new();
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    send(&Reply::Event { id, event: &event });
+                    events.push(event);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        events
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_and_replies_are_tagged_by_type() {
+        assert_eq!(
+            parse(r#"{"type": "query", "id": 1, "task": "add a sum"}"#),
+            Some(Ok(Request::Query {
+                id: 1,
+                task: "add a sum".to_string(),
+            }))
+        );
+        assert_eq!(
+            parse(r#"{"type": "confirm", "id": 1, "approve": true}"#),
+            Some(Ok(Request::Confirm {
+                id: 1,
+                approve: true,
+                content: None,
+            }))
+        );
+        assert!(matches!(parse(r#"{"type": "quit"}"#), Some(Err(_))));
+        assert_eq!(parse("  "), None);
+
+        let event = OrchestratorEvent::TaskStarted {
+            task: "add a sum".to_string(),
+        };
+        let line = serde_json::to_string(&Reply::Event {
+            id: 1,
+            event: &event,
+        })
+        .unwrap();
+        assert_eq!(
+            line,
+            r#"{"type":"event","id":1,"event":"task_started","task":"add a sum"}"#
+        );
+        assert_eq!(
+            response(true, Some("x".to_string())),
+            ConfirmationResponse::ApproveEdited("x".to_string())
+        );
+    }
+}
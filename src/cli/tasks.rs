@@ -0,0 +1,95 @@
+use std::future::Future;
+
+use anyhow::Result;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Owns everything the TUI runs in the background: tasks, connection checks,
+/// session naming. Each gets a child of one token, so `shutdown` stops them
+/// all at once and `Task::cancel` stops just one.
+#[derive(Debug, Default)]
+pub struct TaskManager {
+    root: CancellationToken,
+}
+
+/// One piece of background work, polled each tick with `is_finished`.
+#[derive(Debug)]
+pub struct Task<T> {
+    handle: JoinHandle<Option<T>>,
+    token: CancellationToken,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` until it finishes or is cancelled. Cancelling drops it where
+    /// it's waiting, like an abort, so whatever it must undo happens on drop:
+    /// the orchestrator puts back an edit whose gate hadn't finished, and tsc
+    /// is killed with its future.
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        work: impl Future<Output = T> + Send + 'static,
+    ) -> Task<T> {
+        let token = self.root.child_token();
+        let cancelled = token.clone();
+        let handle = tokio::spawn(async move {
+            tokio::select! {
+                biased;
+                _ = cancelled.cancelled() => None,
+                output = work => Some(output),
+            }
+        });
+        Task { handle, token }
+    }
+
+    /// Cancel everything spawned so far; called on the way out, before the
+    /// terminal is restored.
+    pub fn shutdown(&self) {
+        self.root.cancel();
+    }
+}
+
+impl<T> Task<T> {
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// What the work returned, or an error if it was cancelled or panicked.
+    pub async fn join(self) -> Result<T> {
+        match self.handle.await {
+            Ok(Some(output)) => Ok(output),
+            Ok(None) => anyhow::bail!("was cancelled"),
+            Err(e) => anyhow::bail!("panicked: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelling_one_task_or_all_of_them_stops_the_work() {
+        let tasks = TaskManager::new();
+        let done = tasks.spawn(async { 2 + 2 });
+        assert_eq!(done.join().await.unwrap(), 4);
+
+        let one = tasks.spawn(std::future::pending::<()>());
+        one.cancel();
+        assert_eq!(one.join().await.unwrap_err().to_string(), "was cancelled");
+
+        let (a, b) = (
+            tasks.spawn(std::future::pending::<()>()),
+            tasks.spawn(std::future::pending::<()>()),
+        );
+        tasks.shutdown();
+        assert!(a.join().await.is_err() && b.join().await.is_err());
+        assert!(tasks.spawn(async { 1 }).join().await.is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::{
+    cursor::Show,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    terminal::{
+        Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+        enable_raw_mode,
+    },
+};
+
+use crate::redact;
+
+/// The TUI's hold on the terminal: raw mode, the alternate screen, bracketed
+/// paste, and mouse capture. Dropping it gives all of them back, however the
+/// app exits - an error, an early return, or a panic unwinding through it.
+pub struct TerminalGuard(());
+
+impl TerminalGuard {
+    pub fn enter() -> io::Result<Self> {
+        // Bracketed paste delivers a pasted block as one Event::Paste, so its
+        // characters can't fire navigation shortcuts mid-paste.
+        execute!(
+            io::stdout(),
+            Clear(ClearType::All),
+            EnterAlternateScreen,
+            EnableBracketedPaste,
+            EnableMouseCapture
+        )?;
+        let guard = Self(());
+        enable_raw_mode()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Put the terminal back the way a shell expects it. Safe to call more than
+/// once; the second time does nothing visible.
+fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        LeaveAlternateScreen,
+        Clear(ClearType::All),
+        Show
+    );
+}
+
+/// On a panic in the UI thread, restore the terminal before anything is
+/// printed, write a crash report, and say where it is. Panics in background
+/// work only get the report: the task manager turns them into an error the
+/// UI shows, and printing over the screen would garble it.
+pub fn install_panic_hook(save_state_dir: Option<String>) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let on_ui_thread = std::thread::current().name() == Some("main");
+        if on_ui_thread {
+            restore();
+        }
+        let dir = save_state_dir
+            .as_deref()
+            .map_or_else(std::env::temp_dir, PathBuf::from);
+        let report = write_crash_report(&dir, &info.to_string());
+        if on_ui_thread {
+            previous(info);
+            match report {
+                Ok(path) => eprintln!("CodePilot crashed; a report is in {}", path.display()),
+                Err(e) => {
+                    eprintln!("CodePilot crashed, and the crash report couldn't be written: {e}")
+                }
+            }
+        }
+    }));
+}
+
+/// `{dir}/crash-<time>.txt`: the version, the panic message, and a backtrace,
+/// with secrets masked like any other output.
+fn write_crash_report(dir: &Path, panic: &str) -> io::Result<PathBuf> {
+    let now = chrono::Utc::now();
+    let path = dir.join(format!("crash-{}.txt", now.format("%Y%m%dT%H%M%S")));
+    let report = format!(
+        "CodePilot {} crashed at {}\n\n{}\n\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        now.to_rfc3339(),
+        panic,
+        std::backtrace::Backtrace::force_capture()
+    );
+    std::fs::create_dir_all(dir)?;
+    std::fs::File::create(&path)?.write_all(redact::text(&report).as_bytes())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_reports_carry_the_panic_with_secrets_masked() {
+        let dir = std::env::temp_dir().join(format!("codepilot-test-crash-{}", std::process::id()));
+        let path = write_crash_report(
+            &dir,
+            "panicked at src/cli/ui.rs:12:5: sk-abcdefghijklmnopqrstuv",
+        )
+        .unwrap();
+        let report = std::fs::read_to_string(&path).unwrap();
+        assert!(report.starts_with("CodePilot "));
+        assert!(report.contains("panicked at src/cli/ui.rs:12:5: [redacted]"));
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,140 @@
+use ratatui::style::Color;
+
+/// The TUI's palette. Picked with `THEME` in `.env`; individual colors can be
+/// overridden with `THEME_<ROLE>=#rrggbb` (e.g. `THEME_ACCENT=#ff8800`), which
+/// is how a user-defined theme is built on top of a built-in one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    pub accent: Color,
+    pub accent_2: Color,
+    pub ok: Color,
+    pub warn: Color,
+    pub err: Color,
+    pub muted: Color,
+    pub fg: Color,
+    /// Background of the selected row and the active tab.
+    pub selection: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+impl Theme {
+    /// Tokyo-night-ish; the original look.
+    pub const DARK: Theme = Theme {
+        name: "dark",
+        accent: Color::Rgb(122, 162, 247),
+        accent_2: Color::Rgb(187, 154, 247),
+        ok: Color::Rgb(158, 206, 106),
+        warn: Color::Rgb(224, 175, 104),
+        err: Color::Rgb(247, 118, 142),
+        muted: Color::Rgb(86, 95, 137),
+        fg: Color::Rgb(192, 202, 245),
+        selection: Color::Rgb(41, 46, 66),
+    };
+
+    pub const LIGHT: Theme = Theme {
+        name: "light",
+        accent: Color::Rgb(46, 126, 233),
+        accent_2: Color::Rgb(152, 84, 241),
+        ok: Color::Rgb(88, 117, 57),
+        warn: Color::Rgb(140, 108, 62),
+        err: Color::Rgb(245, 42, 101),
+        muted: Color::Rgb(132, 140, 181),
+        fg: Color::Rgb(55, 96, 191),
+        selection: Color::Rgb(196, 200, 218),
+    };
+
+    pub const SOLARIZED: Theme = Theme {
+        name: "solarized",
+        accent: Color::Rgb(38, 139, 210),
+        accent_2: Color::Rgb(108, 113, 196),
+        ok: Color::Rgb(133, 153, 0),
+        warn: Color::Rgb(181, 137, 0),
+        err: Color::Rgb(220, 50, 47),
+        muted: Color::Rgb(88, 110, 117),
+        fg: Color::Rgb(147, 161, 161),
+        selection: Color::Rgb(7, 54, 66),
+    };
+
+    pub const BUILT_IN: [Theme; 3] = [Theme::DARK, Theme::LIGHT, Theme::SOLARIZED];
+
+    pub fn named(name: &str) -> Option<Theme> {
+        Self::BUILT_IN
+            .into_iter()
+            .find(|theme| theme.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The built-in theme after this one, wrapping around.
+    pub fn next(&self) -> Theme {
+        let index = Self::BUILT_IN
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .unwrap_or(0);
+        Self::BUILT_IN[(index + 1) % Self::BUILT_IN.len()]
+    }
+
+    /// Apply `(role, color)` overrides such as `("accent", "#ff8800")`. Returns the
+    /// overrides that didn't apply - an unknown role or an unparseable color.
+    pub fn with_overrides(mut self, overrides: &[(String, String)]) -> (Theme, Vec<String>) {
+        let mut rejected = Vec::new();
+        for (role, value) in overrides {
+            let Some(color) = parse_color(value) else {
+                rejected.push(format!("{role}={value}"));
+                continue;
+            };
+            match role.as_str() {
+                "accent" => self.accent = color,
+                "accent_2" => self.accent_2 = color,
+                "ok" => self.ok = color,
+                "warn" => self.warn = color,
+                "err" => self.err = color,
+                "muted" => self.muted = color,
+                "fg" => self.fg = color,
+                "selection" => self.selection = color,
+                _ => rejected.push(format!("{role}={value}")),
+            }
+        }
+        (self, rejected)
+    }
+}
+
+/// `#rrggbb` (or `rrggbb`) as an RGB color.
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_single_roles() {
+        let overrides = vec![
+            ("accent".to_string(), "#ff8800".to_string()),
+            ("bogus".to_string(), "#000000".to_string()),
+            ("fg".to_string(), "not-a-color".to_string()),
+        ];
+        let (theme, rejected) = Theme::named("Solarized")
+            .unwrap()
+            .with_overrides(&overrides);
+        assert_eq!(theme.accent, Color::Rgb(255, 136, 0));
+        assert_eq!(theme.fg, Theme::SOLARIZED.fg);
+        assert_eq!(rejected, vec!["bogus=#000000", "fg=not-a-color"]);
+    }
+
+    #[test]
+    fn next_cycles_through_built_ins() {
+        assert_eq!(Theme::DARK.next().name, "light");
+        assert_eq!(Theme::SOLARIZED.next().name, "dark");
+    }
+}
@@ -1,32 +1,85 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, List, ListItem, Padding, Paragraph, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Cell, Clear, List, ListItem, Padding, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Table, Wrap,
+    },
     Frame,
 };
-use crate::cli::state::AppState;
-
-// Tokyo-night-ish accent palette.
-const ACCENT: Color = Color::Rgb(122, 162, 247); // blue
-const ACCENT_2: Color = Color::Rgb(187, 154, 247); // purple
-const OK: Color = Color::Rgb(158, 206, 106); // green
-const WARN: Color = Color::Rgb(224, 175, 104); // orange
-const ERR: Color = Color::Rgb(247, 118, 142); // red
-const MUTED: Color = Color::Rgb(86, 95, 137); // dim gray-blue
-const FG: Color = Color::Rgb(192, 202, 245); // soft white
+use crate::cli::links;
+use crate::cli::message::{Message, MessageKind};
+use crate::cli::state::{AppState, HistorySearch, PendingConfirmation, SessionPicker, StatsView};
+use crate::cli::commands;
+use crate::cli::help;
+use crate::cli::setup::SetupWizard;
+use crate::cli::keymap::Action;
+use crate::cli::theme::Theme;
+use crate::config::AgentMode;
+use crate::formatter::{DiffLine, display_width};
+use crate::i18n;
+use crate::policy::ActionClass;
+use crate::runs::{self, TscDiagnostic};
 
 pub fn render(f: &mut Frame, app: &AppState) {
-    if app.show_details {
+    if app.show_message {
+        render_selected_message(f, app);
+    } else if app.show_details {
         render_details(f, app);
-        return;
+    } else {
+        render_main(f, app);
     }
     if app.show_help {
-        render_help(f);
-        return;
+        render_help(f, app);
+    }
+    if app.quit_prompt {
+        render_quit_prompt(f, app);
+    }
+}
+
+fn render_main(f: &mut Frame, app: &AppState) {
+    let chunks = main_layout(f.area());
+
+    render_title(f, chunks[0], app);
+    render_input(f, chunks[1], app);
+    let (messages_area, log_area) = activity_layout(chunks[2], app.show_log_pane);
+    render_messages(f, messages_area, app);
+    if let Some(log_area) = log_area {
+        render_logs(f, log_area, app);
+    }
+    render_status_bar(f, chunks[3], app);
+    if app.is_input_mode {
+        render_completions(f, chunks[1], app);
     }
 
-    let chunks = Layout::default()
+    if let Some(pending) = &app.pending_confirmation {
+        render_confirmation(f, &app.theme, pending);
+    }
+    if app.show_queries {
+        render_queries(f, app);
+    }
+    if let Some(picker) = &app.session_picker {
+        render_session_picker(f, &app.theme, picker);
+    }
+    if let Some(search) = &app.history_search {
+        render_history_search(f, &app.theme, search);
+    }
+    if let Some(cursor) = app.bookmark_cursor {
+        render_bookmarks(f, &app.theme, &app.bookmarks, cursor);
+    }
+    if let Some(stats) = &app.stats_view {
+        render_stats(f, &app.theme, stats);
+    }
+    if let Some(setup) = &app.setup {
+        render_setup(f, &app.theme, setup);
+    }
+}
+
+/// Title, input, activity, and status-bar areas of the main view. Shared with the
+/// mouse handling in `app.rs` so clicks map onto what's actually drawn.
+pub fn main_layout(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints(
@@ -38,45 +91,173 @@ pub fn render(f: &mut Frame, app: &AppState) {
             ]
             .as_ref(),
         )
-        .split(f.area());
+        .split(area)
+}
 
-    render_title(f, chunks[0]);
-    render_input(f, chunks[1], app);
-    render_messages(f, chunks[2], app);
-    render_status_bar(f, chunks[3], app);
+/// Conversation and (when the split view is on) log-pane areas within the activity area.
+pub fn activity_layout(area: Rect, show_log_pane: bool) -> (Rect, Option<Rect>) {
+    if !show_log_pane {
+        return (area, None);
+    }
+    let halves = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+    (halves[0], Some(halves[1]))
+}
+
+/// Index into `messages_expanded` of the activity-pane line at screen `row`, if
+/// the row is inside the pane's borders.
+pub fn message_line_at(app: &AppState, messages_area: Rect, row: u16) -> Option<usize> {
+    let first_row = messages_area.y + 1;
+    let last_row = messages_area.y + messages_area.height.saturating_sub(1);
+    if row < first_row || row >= last_row {
+        return None;
+    }
+    let line = app.message_scroll + (row - first_row) as usize;
+    (line < app.messages_expanded.len()).then_some(line)
+}
+
+/// A link as drawn in the activity pane: where its text starts on screen, the
+/// text, and what it opens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenLink {
+    pub x: u16,
+    pub y: u16,
+    pub text: String,
+    pub target: String,
+}
+
+/// Links on the activity-pane lines currently on screen. Each wrapped line is
+/// searched on its own, and links running past the right border are left out.
+pub fn activity_links(app: &AppState, messages_area: Rect) -> Vec<ScreenLink> {
+    // Border, padding, then the two-column icon.
+    let text_x = messages_area.x + 4;
+    let right = (messages_area.x + messages_area.width).saturating_sub(2);
+    let visible_height = messages_area.height.saturating_sub(2) as usize;
+    let repo = std::path::Path::new(&app.target_repo_path);
+
+    let mut links = Vec::new();
+    for (offset, line) in app
+        .messages_expanded
+        .iter()
+        .skip(app.message_scroll)
+        .take(visible_height)
+        .enumerate()
+    {
+        for link in links::find(line, repo, &app.repo_files) {
+            let x = text_x + Span::raw(&line[..link.range.start]).width() as u16;
+            let text = line[link.range].to_string();
+            if x + Span::raw(text.as_str()).width() as u16 > right {
+                continue;
+            }
+            links.push(ScreenLink {
+                x,
+                y: messages_area.y + 1 + offset as u16,
+                text,
+                target: link.target,
+            });
+        }
+    }
+    links
 }
 
-fn render_title(f: &mut Frame, area: Rect) {
-    let title = Paragraph::new(Line::from(vec![
-        Span::styled("›› ", Style::default().fg(ACCENT_2).add_modifier(Modifier::BOLD)),
+fn render_title(f: &mut Frame, area: Rect, app: &AppState) {
+    let t = &app.theme;
+    let mut lines = vec![Line::from(vec![
+        Span::styled("›› ", Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD)),
         Span::styled(
             "CodePilot",
-            Style::default().fg(ACCENT).add_modifier(Modifier::BOLD),
+            Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
         ),
-        Span::styled(" — JS/TS coding agent", Style::default().fg(MUTED)),
-    ]))
-    .alignment(Alignment::Center)
-    .block(
+        Span::styled(format!(" — {}", i18n::tr("title-tagline")), Style::default().fg(t.muted)),
+        Span::styled(
+            format!(" — {}", i18n::tr("title-tagline")),
+            Style::default().fg(t.muted),
+        ),
+        Span::styled(
+            format!(
+                " · {}",
+                if app.target_repo_path.is_empty() {
+                    "."
+                } else {
+                    &app.target_repo_path
+                }
+            ),
+            Style::default().fg(t.muted),
+        ),
+    ])];
+    if app.tabs.len() > 1 {
+        lines.push(render_tab_strip(app));
+    }
+    let title = Paragraph::new(lines).alignment(Alignment::Center).block(
         Block::default()
             .borders(Borders::BOTTOM)
             .border_type(BorderType::Plain)
-            .border_style(Style::default().fg(MUTED)),
+            .border_style(Style::default().fg(t.muted)),
     );
 
     f.render_widget(title, area);
 }
 
+/// " 1 fix the sum type │ 2 new " - the active tab is highlighted.
+fn render_tab_strip(app: &AppState) -> Line<'static> {
+    let t = &app.theme;
+    let mut spans = Vec::new();
+    for (i, tab) in app.tabs.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" │ ", Style::default().fg(t.muted)));
+        }
+        let title = if tab.title.is_empty() { i18n::tr("tab-new") } else { tab.title.clone() };
+        let style = if i == app.active_tab {
+            Style::default().fg(t.accent).bg(t.selection).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(t.muted)
+        };
+        spans.push(Span::styled(format!(" {} {title} ", i + 1), style));
+    }
+    Line::from(spans)
+}
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// " ⠹ Working… 12s · running tsc " - animated off the elapsed time, so it moves
+/// on every redraw tick without any extra state.
+fn working_label(app: &AppState) -> String {
+    let elapsed = app.processing_started.map(|started| started.elapsed()).unwrap_or_default();
+    let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+    let queued = match app.task_queue.len() {
+        0 => String::new(),
+        n => format!("· {} ", i18n::tr_args("working-queued", &[("count", &n.to_string())])),
+    };
+    let working = i18n::tr_args("working", &[("seconds", &elapsed.as_secs().to_string())]);
+    match &app.current_step {
+        Some(step) => format!(" {frame} {working} · {step} {queued}"),
+        None => format!(" {frame} {working} {queued}"),
+    }
+}
+
 fn render_input(f: &mut Frame, area: Rect, app: &AppState) {
-    let (border_color, label) = if app.is_input_mode {
-        (ACCENT_2, " Describe a task · Enter to run · Shift+Enter for newline · Esc to cancel ")
+    let t = &app.theme;
+    let hints = commands::matching(&app.input_text);
+    let (border_color, label) = if app.is_input_mode && !hints.is_empty() {
+        let usage: Vec<String> = hints
+            .iter()
+            .map(|command| format!("{} — {}", command.usage, command.description()))
+            .collect();
+        (t.accent, format!(" {} ", usage.join(" · ")))
+    } else if app.is_input_mode && app.is_processing {
+        (t.accent_2, format!(" {} ", i18n::tr("input-hint-queue")))
+    } else if app.is_input_mode {
+        (t.accent_2, format!(" {} ", i18n::tr("input-hint")))
     } else if app.is_processing {
-        (WARN, " Working… ")
+        (t.warn, working_label(app))
     } else {
-        (MUTED, " Press 'i' to describe a code task ")
+        (t.muted, format!(" {} ", i18n::tr("input-hint-normal")))
     };
 
     let input = Paragraph::new(app.input_text.clone())
-        .style(Style::default().fg(FG))
+        .style(Style::default().fg(t.fg))
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -92,57 +273,144 @@ fn render_input(f: &mut Frame, area: Rect, app: &AppState) {
     if app.is_input_mode {
         let before_cursor = &app.input_text[..app.cursor_position.min(app.input_text.len())];
         let row = before_cursor.matches('\n').count() as u16;
-        let col = before_cursor.rsplit('\n').next().unwrap_or("").chars().count() as u16;
+        let col = display_width(before_cursor.rsplit('\n').next().unwrap_or("")) as u16;
         f.set_cursor_position((area.x + col + 2, area.y + 1 + row));
     }
 }
 
+/// Popup under the input box listing completions for the word being typed.
+/// Overlaps the activity pane; ↑/↓ pick, Tab accepts.
+fn render_completions(f: &mut Frame, input_area: Rect, app: &AppState) {
+    let t = &app.theme;
+    let completions = app.completions();
+    if completions.is_empty() {
+        return;
+    }
+    let selected = app.completion_cursor.min(completions.len() - 1);
+    let width = completions.iter().map(|c| display_width(c)).max().unwrap_or(0) as u16 + 4;
+    let below = input_area.y + input_area.height;
+    let area = Rect {
+        x: input_area.x + 1,
+        y: below,
+        width: width.max(24).min(input_area.width.saturating_sub(2)),
+        height: (completions.len() as u16 + 2).min(f.area().height.saturating_sub(below)),
+    };
+
+    let items: Vec<ListItem> = completions
+        .iter()
+        .enumerate()
+        .map(|(i, completion)| {
+            let item = ListItem::new(Span::styled(completion.clone(), Style::default().fg(t.fg)));
+            if i == selected {
+                item.style(Style::default().bg(t.selection).add_modifier(Modifier::BOLD))
+            } else {
+                item
+            }
+        })
+        .collect();
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(Span::styled(" ↑/↓ · Tab ", Style::default().fg(t.muted)))
+            .border_style(Style::default().fg(t.accent)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
 fn render_messages(f: &mut Frame, area: Rect, app: &AppState) {
+    let t = &app.theme;
     let visible_height = area.height.saturating_sub(2) as usize; // Account for borders
 
     // Get the messages to display based on scroll position
-    let start_idx = app.message_scroll;
+    let start_idx = app.message_scroll.min(app.messages_expanded.len());
     let end_idx = (start_idx + visible_height).min(app.messages_expanded.len());
 
     let visible_messages: Vec<ListItem> = if app.messages_expanded.is_empty() {
-        vec![ListItem::new(Line::from(Span::styled(
-            "  No activity yet — press 'i' and describe a code task.",
-            Style::default().fg(MUTED),
-        )))]
+        let empty = if app.messages.is_empty() || app.message_filter.is_empty() {
+            format!("  {}", i18n::tr("activity-empty"))
+        } else {
+            let filter = app.message_filter.label();
+            format!(
+                "  {}",
+                i18n::tr_args("activity-empty-filtered", &[("filter", filter.as_str())])
+            )
+        };
+        vec![ListItem::new(Line::from(Span::styled(empty, Style::default().fg(t.muted))))]
     } else {
         app.messages_expanded[start_idx..end_idx]
             .iter()
-            .map(|msg| {
-                let (icon, color) = if msg.contains("Error") || msg.contains("Failed") {
-                    ("✗ ", ERR)
-                } else if msg.contains("Success") || msg.contains("Wrote") {
-                    ("✓ ", OK)
-                } else if msg.contains("Processing") || msg.contains("Working") {
-                    ("⚙ ", WARN)
-                } else {
-                    ("· ", FG)
+            .enumerate()
+            .map(|(offset, msg)| {
+                let selected = app.selected_message.is_some()
+                    && app.message_line_owner.get(start_idx + offset).copied()
+                        == app.selected_message;
+                let kind = app
+                    .message_line_owner
+                    .get(start_idx + offset)
+                    .and_then(|&owner| app.messages.get(owner))
+                    .map(|message| message.kind)
+                    .unwrap_or_default();
+                let (icon, color) = match kind {
+                    MessageKind::Error => ("✗ ", t.err),
+                    // A gate run that failed is still progress, but worth flagging.
+                    MessageKind::Tool if msg.contains("failed") => ("✗ ", t.err),
+                    MessageKind::Agent => ("✓ ", t.ok),
+                    MessageKind::Tool => ("⚙ ", t.warn),
+                    MessageKind::User => ("› ", t.accent_2),
+                    MessageKind::System => ("· ", t.fg),
                 };
-                ListItem::new(Line::from(vec![
-                    Span::styled(icon, Style::default().fg(color).add_modifier(Modifier::BOLD)),
-                    Span::styled(msg.clone(), Style::default().fg(color)),
-                ]))
+                let color = match app.message_line_diff.get(start_idx + offset).copied().flatten() {
+                    Some(DiffLine::Added) => t.ok,
+                    Some(DiffLine::Removed) => t.err,
+                    Some(DiffLine::Hunk) => t.accent,
+                    Some(DiffLine::Header) => t.muted,
+                    Some(DiffLine::Context) | None => color,
+                };
+                let mut spans = vec![Span::styled(
+                    icon,
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                )];
+                spans.extend(highlight_matches(
+                    t,
+                    msg,
+                    &app.search_query,
+                    Style::default().fg(color),
+                ));
+                let item = ListItem::new(Line::from(spans));
+                if selected {
+                    item.style(Style::default().bg(t.selection))
+                } else {
+                    item
+                }
             })
             .collect()
     };
 
-    let mut title = " Activity ".to_string();
-    if app.messages_expanded.len() > visible_height {
-        let total_lines = app.messages_expanded.len();
-        let current_line = start_idx + 1;
-        let end_line = end_idx;
-        let mut indicators = String::new();
-        if start_idx > 0 {
-            indicators.push('↑');
-        }
-        if end_idx < total_lines {
-            indicators.push('↓');
-        }
-        title = format!(" Activity ({}-{}/{}) {} ", current_line, end_line, total_lines, indicators);
+    let mut title = format!(" {} ", i18n::tr("activity-title"));
+    let overflows = app.messages_expanded.len() > visible_height;
+    if overflows {
+        title = format!(
+            " {} ({}-{}/{}) ",
+            i18n::tr("activity-title"),
+            start_idx + 1,
+            end_idx,
+            app.messages_expanded.len()
+        );
+    }
+    if !app.message_filter.is_empty() {
+        title = format!("{}· {} ", title, app.message_filter.label());
+    }
+    if !app.search_query.is_empty() {
+        let position = if app.search_matches.is_empty() { 0 } else { app.search_cursor + 1 };
+        title = format!(
+            "{}· /{} {}/{} ",
+            title,
+            app.search_query,
+            position,
+            app.search_matches.len()
+        );
     }
 
     let messages_list = List::new(visible_messages).block(
@@ -150,148 +418,944 @@ fn render_messages(f: &mut Frame, area: Rect, app: &AppState) {
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
             .padding(Padding::horizontal(1))
-            .title(Span::styled(title, Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)))
-            .border_style(Style::default().fg(MUTED)),
+            .title(Span::styled(title, Style::default().fg(t.accent).add_modifier(Modifier::BOLD)))
+            .border_style(Style::default().fg(t.muted)),
     );
 
     f.render_widget(messages_list, area);
+    for link in activity_links(app, area) {
+        let width = Span::raw(link.text.as_str()).width() as u16;
+        f.buffer_mut().set_style(
+            Rect::new(link.x, link.y, width, 1),
+            Style::default().add_modifier(Modifier::UNDERLINED),
+        );
+    }
+    if overflows {
+        // Drawn over the right border, between the corners. One position per
+        // scroll offset, so the thumb reaches the bottom at the last line.
+        let mut scrollbar_state =
+            ScrollbarState::new(app.messages_expanded.len() - visible_height + 1)
+                .viewport_content_length(visible_height)
+                .position(start_idx);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_style(Style::default().fg(t.muted))
+            .thumb_style(Style::default().fg(t.accent));
+        f.render_stateful_widget(scrollbar, area.inner(Margin::new(0, 1)), &mut scrollbar_state);
+    }
+}
+
+/// The newest log lines that fit, tailing like `tail -f`.
+fn render_logs(f: &mut Frame, area: Rect, app: &AppState) {
+    let t = &app.theme;
+    let visible_height = area.height.saturating_sub(2) as usize;
+    let start = app.logs.len().saturating_sub(visible_height);
+    let items: Vec<ListItem> = if app.logs.is_empty() {
+        vec![ListItem::new(Span::styled(i18n::tr("log-empty"), Style::default().fg(t.muted)))]
+    } else {
+        app.logs[start..]
+            .iter()
+            .map(|line| {
+                let color = if line.starts_with("error ") || line.contains("passed=false") {
+                    t.err
+                } else if line.contains("passed=true") || line.contains("applied=true") {
+                    t.ok
+                } else {
+                    t.muted
+                };
+                ListItem::new(Span::styled(line.clone(), Style::default().fg(color)))
+            })
+            .collect()
+    };
+
+    let logs = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(" {} ({}) ", i18n::tr("log-title"), app.logs.len()),
+                Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD),
+            ))
+            .border_style(Style::default().fg(t.muted)),
+    );
+    f.render_widget(logs, area);
+}
+
+/// Split `text` into spans with every ASCII case-insensitive occurrence of
+/// `query` highlighted. ASCII lowercasing keeps byte offsets valid for `text`.
+fn highlight_matches(t: &Theme, text: &str, query: &str, base: Style) -> Vec<Span<'static>> {
+    if query.is_empty() {
+        return vec![Span::styled(text.to_string(), base)];
+    }
+
+    let haystack = text.to_ascii_lowercase();
+    let needle = query.to_ascii_lowercase();
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (start, _) in haystack.match_indices(&needle) {
+        if start > last {
+            spans.push(Span::styled(text[last..start].to_string(), base));
+        }
+        let end = start + needle.len();
+        spans.push(Span::styled(
+            text[start..end].to_string(),
+            Style::default().fg(Color::Black).bg(t.warn).add_modifier(Modifier::BOLD),
+        ));
+        last = end;
+    }
+    if last < text.len() {
+        spans.push(Span::styled(text[last..].to_string(), base));
+    }
+    spans
 }
 
 fn render_status_bar(f: &mut Frame, area: Rect, app: &AppState) {
+    let t = &app.theme;
+    if app.is_command_mode {
+        let line = Line::from(vec![
+            Span::styled(format!(":{}", app.command_text), Style::default().fg(t.fg)),
+            Span::styled("▏", Style::default().fg(t.accent)),
+        ]);
+        f.render_widget(Paragraph::new(line), area);
+        return;
+    }
+    if app.is_search_mode {
+        let line = Line::from(vec![
+            Span::styled(
+                format!(" {} ", i18n::tr("status-search")),
+                Style::default().fg(Color::Black).bg(t.warn).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(format!(" /{}", app.search_query), Style::default().fg(t.fg)),
+            Span::styled(
+                format!("  ·  {}", i18n::tr("status-search-keys")),
+                Style::default().fg(t.muted),
+            ),
+        ]);
+        f.render_widget(Paragraph::new(line), area);
+        return;
+    }
+
     let (mode_label, mode_color) = if app.is_input_mode {
-        (" INSERT ", ACCENT_2)
+        (format!(" {} ", i18n::tr("status-insert")), t.accent_2)
+    } else if app.keymap.name == "default" {
+        (format!(" {} ", i18n::tr("status-normal")), t.accent)
     } else {
-        (" NORMAL ", ACCENT)
+        (format!(" {} · {} ", i18n::tr("status-normal"), app.keymap.name), t.accent)
+    };
+
+    let agent_color = match app.agent_mode {
+        AgentMode::Ask => t.warn,
+        AgentMode::Agent => t.ok,
+        AgentMode::Plan => t.accent,
     };
 
-    let repo = if app.target_repo_path.is_empty() {
-        ".".to_string()
+    // ● green once the provider has answered, red after a failed request.
+    let (dot_color, connection) = match app.llm_reachable {
+        _ if app.checking_connection => (t.warn, i18n::tr("connection-checking")),
+        Some(true) => (t.ok, i18n::tr("connection-up")),
+        Some(false) => match app.keymap.keys_for(Action::CheckConnection) {
+            Some(keys) => (t.err, i18n::tr_args("connection-down-retry", &[("keys", &keys)])),
+            None => (t.err, i18n::tr("connection-down")),
+        },
+        None => (t.muted, i18n::tr("connection-unknown")),
+    };
+    let read_only = if app.read_only {
+        Span::styled(
+            format!(" {} ", i18n::tr("status-read-only")),
+            Style::default().fg(Color::Black).bg(t.err).add_modifier(Modifier::BOLD),
+        )
     } else {
-        app.target_repo_path.clone()
+        Span::raw("")
+    };
+    let state = if app.is_processing {
+        Span::styled(
+            format!(" {} ", i18n::tr("status-working")),
+            Style::default().fg(t.warn).add_modifier(Modifier::BOLD),
+        )
+    } else {
+        Span::styled(format!(" {} ", i18n::tr("status-idle")), Style::default().fg(t.muted))
     };
 
-    let line = Line::from(vec![
+    let left = Line::from(vec![
         Span::styled(mode_label, Style::default().fg(Color::Black).bg(mode_color).add_modifier(Modifier::BOLD)),
-        Span::styled(format!("  repo: {repo}  "), Style::default().fg(MUTED)),
-        Span::styled("·  'h' help  Ctrl+O details  'q' quit", Style::default().fg(MUTED)),
+        Span::raw(" "),
+        Span::styled(
+            format!(" {} ", app.agent_mode.label()),
+            Style::default().fg(Color::Black).bg(agent_color).add_modifier(Modifier::BOLD),
+        ),
+        read_only,
+        state,
+        Span::styled("● ", Style::default().fg(dot_color)),
+        Span::styled(format!("{} @ {}", app.model, app.provider), Style::default().fg(t.fg)),
+        Span::styled(format!(" ({connection})"), Style::default().fg(t.muted)),
     ]);
+    let right = Line::from(vec![
+        Span::styled(
+            i18n::tr_args(
+                "status-tokens",
+                &[
+                    ("sent", &compact_count(app.prompt_tokens)),
+                    ("received", &compact_count(app.completion_tokens)),
+                ],
+            ),
+            Style::default().fg(t.muted),
+        ),
+        Span::styled(format!("  ·  {} ", i18n::tr("status-help")), Style::default().fg(t.muted)),
+    ])
+    .alignment(Alignment::Right);
 
-    f.render_widget(Paragraph::new(line), area);
+    f.render_widget(Paragraph::new(left), area);
+    f.render_widget(Paragraph::new(right), area);
 }
 
-fn section(s: &str) -> Line<'static> {
-    Line::from(Span::styled(s.to_string(), Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)))
+/// 950 → "950", 12_345 → "12.3k", 2_500_000 → "2.5M".
+fn compact_count(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", n as f64 / 1_000.0),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
 }
 
-fn key(k: &str, desc: &str) -> Line<'static> {
+fn section(t: &Theme, s: &str) -> Line<'static> {
+    Line::from(Span::styled(
+        s.to_string(),
+        Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn key(t: &Theme, k: &str, desc: &str) -> Line<'static> {
     Line::from(vec![
-        Span::styled(format!("  {:<12}", k), Style::default().fg(ACCENT_2).add_modifier(Modifier::BOLD)),
-        Span::styled(desc.to_string(), Style::default().fg(FG)),
+        Span::styled(
+            format!("  {:<14}", k),
+            Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(desc.to_string(), Style::default().fg(t.fg)),
     ])
 }
 
-fn render_help(f: &mut Frame) {
-    let lines = vec![
+/// Help overlay over whatever was on screen, listing the section for where it
+/// was opened first. Built from the active keymap, so remapped keys show as
+/// they are.
+fn render_help(f: &mut Frame, app: &AppState) {
+    let t = &app.theme;
+    let mut lines = Vec::new();
+    for group in help::sections(&app.keymap, app.help_context, &app.help_query) {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        let title = if group.current {
+            format!("{} · {}", group.title, i18n::tr("help-here"))
+        } else {
+            group.title
+        };
+        lines.push(section(t, &title));
+        lines.extend(group.entries.iter().map(|(keys, desc)| key(t, keys, desc)));
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled(
+            i18n::tr("help-no-match"),
+            Style::default().fg(t.muted),
+        )));
+    }
+
+    let area = centered_rect(80, 85, f.area());
+    let visible = area.height.saturating_sub(4) as usize;
+    let scroll = app.help_scroll.min(lines.len().saturating_sub(visible));
+    let footer = if app.is_help_search || !app.help_query.is_empty() {
+        format!(" /{} · {} ", app.help_query, i18n::tr("help-search-keys"))
+    } else {
+        format!(" {} ", i18n::tr("help-keys"))
+    };
+    let help_paragraph = Paragraph::new(lines)
+        .scroll((scroll as u16, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .padding(Padding::uniform(1))
+                .title(Span::styled(
+                    format!(" {} ", i18n::tr("help-title")),
+                    Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+                ))
+                .title_bottom(Span::styled(footer, Style::default().fg(t.muted)))
+                .border_style(Style::default().fg(t.accent_2)),
+        )
+        .alignment(Alignment::Left);
+
+    f.render_widget(Clear, area);
+    f.render_widget(help_paragraph, area);
+}
+
+/// A rectangle of `percent_x` by `percent_y` of `area`, centered in it.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn render_confirmation(f: &mut Frame, t: &Theme, pending: &PendingConfirmation) {
+    let (title, color) = match pending.class {
+        ActionClass::Destructive => (format!(" {} ", i18n::tr("confirm-title-destructive")), t.err),
+        _ => (format!(" {} ", i18n::tr("confirm-title")), t.warn),
+    };
+
+    let mut lines = vec![
+        detail_field(t, &i18n::tr("field-file"), pending.path.display().to_string()),
+        detail_field(
+            t,
+            &i18n::tr("field-kind"),
+            if pending.edited {
+                i18n::tr_args("confirm-edited", &[("kind", pending.class.label())])
+            } else {
+                pending.class.label().to_string()
+            },
+        ),
+        detail_field(
+            t,
+            &i18n::tr("field-size"),
+            i18n::tr_args(
+                "confirm-size",
+                &[
+                    ("bytes", &pending.content.len().to_string()),
+                    ("lines", &pending.content.lines().count().to_string()),
+                ],
+            ),
+        ),
+        Line::from(""),
+    ];
+    if let Some(injection) = &pending.injection {
+        lines.insert(
+            3,
+            Line::from(Span::styled(
+                i18n::tr_args("confirm-injection", &[("excerpt", injection)]),
+                Style::default().fg(t.err).add_modifier(Modifier::BOLD),
+            )),
+        );
+    }
+    lines.extend(
+        pending
+            .content
+            .lines()
+            .map(|l| Line::from(Span::styled(l.to_string(), Style::default().fg(t.muted)))),
+    );
+
+    let area = centered_rect(70, 60, f.area());
+    let modal = Paragraph::new(lines)
+        .style(Style::default().fg(t.fg))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .padding(Padding::horizontal(1))
+                .title(Span::styled(
+                    title,
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ))
+                .title_bottom(Span::styled(
+                    format!(" {} ", i18n::tr("confirm-keys")),
+                    Style::default().fg(t.muted),
+                ))
+                .border_style(Style::default().fg(color)),
+        );
+
+    f.render_widget(Clear, area);
+    f.render_widget(modal, area);
+}
+
+/// Asked to quit mid-task. Aborting stops the task wherever it is; an edit
+/// written but not yet checked by the gate is put back on the way out.
+fn render_quit_prompt(f: &mut Frame, app: &AppState) {
+    let t = &app.theme;
+    let step = app.current_step.clone().unwrap_or_else(|| i18n::tr("status-working"));
+    let mut lines = vec![
         Line::from(Span::styled(
-            "›› CodePilot — JS/TS coding agent",
-            Style::default().fg(ACCENT).add_modifier(Modifier::BOLD),
+            i18n::tr_args("quit-running", &[("step", &step)]),
+            Style::default().fg(t.fg),
         )),
+        Line::from(Span::styled(
+            i18n::tr("quit-revert"),
+            Style::default().fg(t.muted),
+        )),
+    ];
+    if !app.task_queue.is_empty() {
+        lines.push(Line::from(Span::styled(
+            i18n::tr_args("quit-queued", &[("count", &app.task_queue.len().to_string())]),
+            Style::default().fg(t.muted),
+        )));
+    }
+    lines.extend([
         Line::from(""),
-        section("Task input"),
-        key("i", "describe a code task"),
-        key("Enter", "submit the task (input mode)"),
-        key("Shift+Enter", "insert a newline instead of submitting"),
-        Line::from(""),
-        section("Navigation"),
-        key("h", "toggle this help screen"),
-        key("Ctrl+O", "view edit detail (j/k or PageUp/Dn to browse history)"),
-        key("Esc", "exit current mode or quit"),
-        key("Ctrl+C", "press twice quickly to exit"),
-        Line::from(""),
-        section("Scrolling"),
-        key("j / k", "scroll messages down / up"),
-        key("PageUp/Dn", "scroll faster"),
-        key("Home/End", "jump to top / bottom"),
+        key(t, "w", &i18n::tr("quit-wait")),
+        key(t, "a", &i18n::tr("quit-abort")),
+        key(t, "k / Esc", &i18n::tr("quit-keep")),
+    ]);
+
+    let area = centered_rect(60, 35, f.area());
+    let modal = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(" {} ", i18n::tr("quit-title")),
+                Style::default().fg(t.warn).add_modifier(Modifier::BOLD),
+            ))
+            .border_style(Style::default().fg(t.warn)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(modal, area);
+}
+
+/// Recent and starred tasks; Enter puts one back in the input box.
+fn render_queries(f: &mut Frame, app: &AppState) {
+    let t = &app.theme;
+    let entries = app.query_entries();
+    let items: Vec<ListItem> = if entries.is_empty() {
+        vec![ListItem::new(Span::styled(i18n::tr("tasks-empty"), Style::default().fg(t.muted)))]
+    } else {
+        entries
+            .iter()
+            .enumerate()
+            .map(|(i, &(task, starred))| {
+                let (star, color) = if starred { ("★ ", t.warn) } else { ("  ", t.muted) };
+                let line = Line::from(vec![
+                    Span::styled(star, Style::default().fg(color)),
+                    Span::styled(task.replace('\n', " ⏎ "), Style::default().fg(t.fg)),
+                ]);
+                if i == app.query_cursor {
+                    ListItem::new(line).style(
+                        Style::default()
+                            .bg(t.selection)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    ListItem::new(line)
+                }
+            })
+            .collect()
+    };
+
+    let area = centered_rect(70, 60, f.area());
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(" {} ", i18n::tr("tasks-title")),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ))
+            .title_bottom(Span::styled(
+                format!(" {} ", i18n::tr("tasks-keys")),
+                Style::default().fg(t.muted),
+            ))
+            .border_style(Style::default().fg(t.accent)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
+fn render_session_picker(f: &mut Frame, t: &Theme, picker: &SessionPicker) {
+    let items: Vec<ListItem> = picker
+        .sessions
+        .iter()
+        .enumerate()
+        .map(|(i, (_, session))| {
+            let line = Line::from(vec![
+                Span::styled(format!("{:<40}", session.title()), Style::default().fg(t.fg)),
+                Span::styled(
+                    format!(
+                        "  {} · {}",
+                        session.saved_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                        i18n::tr_args(
+                            "sessions-counts",
+                            &[
+                                ("messages", &session.message_count().to_string()),
+                                ("tabs", &session.tabs.len().to_string())
+                            ],
+                        )
+                    ),
+                    Style::default().fg(t.muted),
+                ),
+            ]);
+            if i == picker.cursor {
+                ListItem::new(line).style(
+                    Style::default()
+                        .bg(t.selection)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect();
+
+    let area = centered_rect(80, 60, f.area());
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(" {} ", i18n::tr("sessions-title")),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ))
+            .title_bottom(Span::styled(
+                format!(" {} ", i18n::tr("sessions-keys")),
+                Style::default().fg(t.muted),
+            ))
+            .border_style(Style::default().fg(t.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
+fn render_history_search(f: &mut Frame, t: &Theme, search: &HistorySearch) {
+    let items: Vec<ListItem> = search
+        .hits
+        .iter()
+        .enumerate()
+        .map(|(i, hit)| {
+            let line = Line::from(Span::styled(hit.line(), Style::default().fg(t.fg)));
+            if i == search.cursor {
+                ListItem::new(line).style(
+                    Style::default()
+                        .bg(t.selection)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect();
+
+    let area = centered_rect(80, 60, f.area());
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(
+                    " {} ",
+                    i18n::tr_args(
+                        "history-title",
+                        &[
+                            ("count", &search.hits.len().to_string()),
+                            ("query", &search.query)
+                        ]
+                    )
+                ),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ))
+            .title_bottom(Span::styled(
+                format!(" {} ", i18n::tr("history-keys")),
+                Style::default().fg(t.muted),
+            ))
+            .border_style(Style::default().fg(t.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
+fn render_bookmarks(f: &mut Frame, t: &Theme, bookmarks: &[Message], cursor: usize) {
+    let items: Vec<ListItem> = bookmarks
+        .iter()
+        .enumerate()
+        .map(|(i, message)| {
+            let first = message.body.lines().next().unwrap_or_default();
+            let line = Line::from(vec![
+                Span::styled(
+                    format!(
+                        "{} ",
+                        message
+                            .timestamp
+                            .with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M")
+                    ),
+                    Style::default().fg(t.muted),
+                ),
+                Span::styled(
+                    format!("{:<6} ", message.kind.label()),
+                    Style::default().fg(t.accent_2),
+                ),
+                Span::styled(first.to_string(), Style::default().fg(t.fg)),
+            ]);
+            if i == cursor {
+                ListItem::new(line).style(
+                    Style::default()
+                        .bg(t.selection)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect();
+
+    let area = centered_rect(80, 60, f.area());
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(" {} ", i18n::tr("bookmarks-title")),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ))
+            .title_bottom(Span::styled(
+                format!(" {} ", i18n::tr("bookmarks-keys")),
+                Style::default().fg(t.muted),
+            ))
+            .border_style(Style::default().fg(t.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
+fn render_stats(f: &mut Frame, t: &Theme, stats: &StatsView) {
+    let body =
+        |text: String| Line::from(Span::styled(format!("  {text}"), Style::default().fg(t.fg)));
+    let mut lines = vec![section(t, &i18n::tr("stats-run"))];
+    lines.extend(stats.session.lines().into_iter().map(body));
+    lines.push(Line::from(""));
+    lines.push(section(t, &i18n::tr("stats-saved")));
+    match &stats.history {
+        Some(history) => lines.extend(history.lines().into_iter().map(body)),
+        None => lines.push(Line::from(Span::styled(
+            format!("  {}", i18n::tr("stats-unsaved")),
+            Style::default().fg(t.muted),
+        ))),
+    }
+
+    let area = centered_rect(70, 50, f.area());
+    let modal = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::uniform(1))
+            .title(Span::styled(
+                format!(" {} ", i18n::tr("stats-title")),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ))
+            .title_bottom(Span::styled(
+                format!(" {} ", i18n::tr("stats-keys")),
+                Style::default().fg(t.muted),
+            ))
+            .border_style(Style::default().fg(t.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(modal, area);
+}
+
+fn render_setup(f: &mut Frame, t: &Theme, setup: &SetupWizard) {
+    let mut lines = vec![
+        Line::from(Span::styled(i18n::tr("setup-intro"), Style::default().fg(t.fg))),
         Line::from(""),
-        Line::from(Span::styled("Press 'h' or 'Esc' to return", Style::default().fg(MUTED))),
     ];
+    for (i, field) in setup.fields.iter().enumerate() {
+        let value = if field.secret {
+            "•".repeat(field.value.chars().count())
+        } else {
+            field.value.clone()
+        };
+        let focused = i == setup.focus;
+        let marker = if focused { "› " } else { "  " };
+        let value_style = if focused {
+            Style::default().fg(t.fg).bg(t.selection)
+        } else {
+            Style::default().fg(t.muted)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{marker}{:<10}", i18n::tr(field.label)),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(format!("{value} "), value_style),
+        ]));
+    }
+    lines.push(Line::from(""));
+    if setup.checking {
+        lines.push(Line::from(Span::styled(
+            i18n::tr("setup-checking"),
+            Style::default().fg(t.warn),
+        )));
+    } else if let Some(error) = &setup.error {
+        lines.push(Line::from(Span::styled(format!("✗ {error}"), Style::default().fg(t.err))));
+    }
 
-    let help_paragraph = Paragraph::new(lines)
+    let area = centered_rect(70, 50, f.area());
+    let modal = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .padding(Padding::horizontal(1))
+            .title(Span::styled(
+                format!(" {} ", i18n::tr("setup-title")),
+                Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+            ))
+            .title_bottom(Span::styled(
+                format!(" {} ", i18n::tr("setup-keys")),
+                Style::default().fg(t.muted),
+            ))
+            .border_style(Style::default().fg(t.accent)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(modal, area);
+}
+
+fn render_selected_message(f: &mut Frame, app: &AppState) {
+    let t = &app.theme;
+    let text = app.selected_message_text().unwrap_or_default();
+    let body = Paragraph::new(text)
+        .wrap(Wrap { trim: false })
+        .style(Style::default().fg(t.fg))
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .padding(Padding::uniform(1))
-                .title(Span::styled(" Help ", Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)))
-                .border_style(Style::default().fg(ACCENT_2)),
-        )
-        .alignment(Alignment::Left);
+                .title(Span::styled(
+                    format!(" {} ", i18n::tr("message-title")),
+                    Style::default().fg(t.accent).add_modifier(Modifier::BOLD),
+                ))
+                .title_bottom(Span::styled(
+                    format!(" {} ", i18n::tr("message-keys")),
+                    Style::default().fg(t.muted),
+                ))
+                .border_style(Style::default().fg(t.accent_2)),
+        );
 
-    f.render_widget(help_paragraph, f.area());
+    f.render_widget(body, f.area());
 }
 
-fn detail_field(label: &str, value: String) -> Line<'static> {
+fn detail_field(t: &Theme, label: &str, value: String) -> Line<'static> {
     Line::from(vec![
-        Span::styled(format!("{label}: "), Style::default().fg(ACCENT_2).add_modifier(Modifier::BOLD)),
-        Span::styled(value, Style::default().fg(FG)),
+        Span::styled(
+            format!("{label}: "),
+            Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(value, Style::default().fg(t.fg)),
     ])
 }
 
 fn render_details(f: &mut Frame, app: &AppState) {
-    let footer = if app.edit_history.len() > 1 {
+    let t = &app.theme;
+    let visible = app.visible_detail_indices();
+    let footer = if app.is_detail_filter_mode {
         format!(
-            " {}/{} · j/k or PageUp/Dn to browse · Ctrl+O or Esc to close ",
-            app.detail_cursor + 1,
-            app.edit_history.len()
+            " {} ",
+            i18n::tr_args(
+                "details-filter",
+                &[("filter", &format!("{}▏", app.detail_filter))]
+            )
         )
+    } else if app.edit_history.len() > 1 {
+        let position = visible.iter().position(|&i| i == app.detail_cursor).map_or(0, |p| p + 1);
+        format!(" {}/{} · {} ", position, visible.len(), i18n::tr("details-keys-browse"))
     } else {
-        " Ctrl+O or Esc to close ".to_string()
+        format!(" {} ", i18n::tr("details-keys"))
     };
+    let title = if app.detail_filter.is_empty() {
+        format!(" {} ", i18n::tr("details-title"))
+    } else {
+        let matches = if visible.len() == 1 { "details-match" } else { "details-matches" };
+        format!(
+            " {} · \"{}\" {} ",
+            i18n::tr("details-title"),
+            app.detail_filter,
+            i18n::tr_args(matches, &[("count", &visible.len().to_string())])
+        )
+    };
+
+    let current = app
+        .edit_history
+        .get(app.detail_cursor)
+        .filter(|_| visible.contains(&app.detail_cursor));
+    let diagnostics = current
+        .and_then(|detail| detail.verification.as_deref())
+        .and_then(|verification| verification.strip_prefix("tsc: failed - "))
+        .map(runs::parse_tsc_diagnostics)
+        .unwrap_or_default();
 
-    let lines: Vec<Line> = match app.edit_history.get(app.detail_cursor) {
+    let mut lines: Vec<Line> = match current {
         Some(detail) => {
-            let (status_text, status_color) = if detail.applied {
-                ("Applied", OK)
+            let (status_text, status_color) = if detail.planned {
+                (i18n::tr("details-planned"), t.accent)
+            } else if detail.applied {
+                (i18n::tr("details-applied"), t.ok)
             } else {
-                ("Rejected", ERR)
+                (i18n::tr("details-rejected"), t.err)
             };
 
             let mut lines = vec![
-                detail_field("Task", detail.task.clone()),
-                detail_field("File", detail.path.display().to_string()),
-                detail_field("Size", format!("{} bytes", detail.bytes)),
-                detail_field("Time", detail.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+                detail_field(t, &i18n::tr("field-task"), detail.task.clone()),
+                detail_field(
+                    t,
+                    &i18n::tr("field-file"),
+                    detail.path.display().to_string(),
+                ),
+                detail_field(
+                    t,
+                    &i18n::tr("field-size"),
+                    i18n::tr_args("details-size", &[("bytes", &detail.bytes.to_string())]),
+                ),
+                detail_field(
+                    t,
+                    &i18n::tr("field-time"),
+                    detail.timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                ),
                 Line::from(vec![
-                    Span::styled("Status: ", Style::default().fg(ACCENT_2).add_modifier(Modifier::BOLD)),
+                    Span::styled(
+                        format!("{}: ", i18n::tr("field-status")),
+                        Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD),
+                    ),
                     Span::styled(status_text, Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
                 ]),
             ];
-            if let Some(verification) = &detail.verification {
-                lines.push(detail_field("Verification", verification.clone()));
+            if !diagnostics.is_empty() {
+                let count = diagnostics.len();
+                let errors = if count == 1 { "details-tsc-error" } else { "details-tsc-errors" };
+                lines.push(detail_field(
+                    t,
+                    &i18n::tr("field-verification"),
+                    i18n::tr_args(errors, &[("count", &count.to_string())]),
+                ));
+            } else if let Some(verification) = &detail.verification {
+                lines.push(detail_field(t, &i18n::tr("field-verification"), verification.clone()));
+            }
+            if detail.attempts > 1 {
+                lines.push(detail_field(
+                    t,
+                    &i18n::tr("field-attempts"),
+                    detail.attempts.to_string(),
+                ));
             }
-            lines.push(Line::from(""));
-            lines.push(Line::from(Span::styled("Content:", Style::default().fg(ACCENT_2).add_modifier(Modifier::BOLD))));
-            lines.extend(detail.content.lines().map(|l| Line::from(l.to_string())));
             lines
         }
+        None if !app.edit_history.is_empty() => vec![Line::from(Span::styled(
+            i18n::tr("details-no-match"),
+            Style::default().fg(t.muted),
+        ))],
         None => vec![Line::from(Span::styled(
-            "No edits yet — run a task and its full file path + content will show up here.",
-            Style::default().fg(MUTED),
+            i18n::tr("details-empty"),
+            Style::default().fg(t.muted),
         ))],
     };
+    let mut content: Vec<Line> = Vec::new();
+    if let Some(detail) = current {
+        content.push(Line::from(""));
+        content.push(Line::from(Span::styled(
+            format!("{}:", i18n::tr("field-content")),
+            Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD),
+        )));
+        content.extend(detail.content.lines().map(|l| Line::from(l.to_string())));
+    }
 
-    let body = Paragraph::new(lines)
-        .wrap(Wrap { trim: false })
-        .style(Style::default().fg(FG))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .padding(Padding::uniform(1))
-                .title(Span::styled(" Last Edit ", Style::default().fg(ACCENT).add_modifier(Modifier::BOLD)))
-                .title_bottom(Span::styled(footer, Style::default().fg(MUTED)))
-                .border_style(Style::default().fg(ACCENT_2)),
-        );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::uniform(1))
+        .title(Span::styled(title, Style::default().fg(t.accent).add_modifier(Modifier::BOLD)))
+        .title_bottom(Span::styled(footer, Style::default().fg(t.muted)))
+        .border_style(Style::default().fg(t.accent_2));
+    let inner = block.inner(f.area());
+    f.render_widget(block, f.area());
 
-    f.render_widget(body, f.area());
+    if diagnostics.is_empty() {
+        lines.extend(content);
+        let body = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .style(Style::default().fg(t.fg));
+        f.render_widget(body, inner);
+        return;
+    }
+
+    // Header rows plus up to MAX_DIAGNOSTIC_ROWS errors, then the content below.
+    let table_height = diagnostics.len().min(MAX_DIAGNOSTIC_ROWS) as u16 + 2;
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(lines.len() as u16 + 1),
+            Constraint::Length(table_height),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+    f.render_widget(Paragraph::new(lines).style(Style::default().fg(t.fg)), sections[0]);
+    render_diagnostics(f, t, sections[1], &diagnostics, app.detail_table_scroll);
+    f.render_widget(
+        Paragraph::new(content).wrap(Wrap { trim: false }).style(Style::default().fg(t.fg)),
+        sections[2],
+    );
+}
+
+/// At most this many tsc errors are listed in the detail view's table.
+const MAX_DIAGNOSTIC_ROWS: usize = 10;
+
+/// tsc errors as a table. `scroll` shifts the message column left by that many
+/// characters (←/→ in the detail view), since messages are often wider than
+/// the pane.
+fn render_diagnostics(
+    f: &mut Frame,
+    t: &Theme,
+    area: Rect,
+    diagnostics: &[TscDiagnostic],
+    scroll: usize,
+) {
+    let header = Row::new(
+        [
+            "field-file",
+            "diagnostic-line",
+            "diagnostic-column",
+            "diagnostic-code",
+            "diagnostic-message",
+        ]
+        .map(i18n::tr),
+    )
+    .style(Style::default().fg(t.accent_2).add_modifier(Modifier::BOLD))
+    .bottom_margin(1);
+    let rows = diagnostics.iter().take(MAX_DIAGNOSTIC_ROWS).map(|d| {
+        Row::new(vec![
+            Cell::from(d.file.clone()),
+            Cell::from(Line::from(d.line.to_string()).alignment(Alignment::Right)),
+            Cell::from(Line::from(d.column.to_string()).alignment(Alignment::Right)),
+            Cell::from(Span::styled(d.code.clone(), Style::default().fg(t.err))),
+            Cell::from(d.message.chars().skip(scroll).collect::<String>()),
+        ])
+    });
+    let file_width = diagnostics
+        .iter()
+        .map(|d| d.file.len())
+        .max()
+        .unwrap_or(4)
+        .clamp(4, 40) as u16;
+    let widths = [
+        Constraint::Length(file_width),
+        Constraint::Length(5),
+        Constraint::Length(4),
+        Constraint::Length(7),
+        Constraint::Min(20),
+    ];
+    f.render_widget(Table::new(rows, widths).header(header).style(Style::default().fg(t.fg)), area);
 }
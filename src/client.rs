@@ -0,0 +1,195 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::config::{AgentMode, Config};
+use crate::events::OrchestratorEvent;
+use crate::orchestrator::{
+    CodeTaskOrchestrator, ConfirmationRequest, ConfirmationResponse, TaskResult,
+};
+use crate::session::SessionContext;
+
+/// Answers edits that need approval, in place of the TUI's prompt.
+pub type Approver = Box<dyn FnMut(&ConfirmationRequest) -> ConfirmationResponse + Send>;
+
+/// CodePilot for other Rust programs: the orchestrator plus a session, without
+/// the TUI or the CLI.
+///
+/// ```no_run
+/// # async fn demo() -> anyhow::Result<()> {
+/// use codepilot::{AgentMode, CodepilotClient, Config};
+///
+/// let mut client = CodepilotClient::builder(Config::from_env()?)
+///     .repo("../web")
+///     .mode(AgentMode::Agent)
+///     .build()
+///     .await?;
+/// let result = client.query("add a sum function to src/math.ts").await?;
+/// println!("{} applied: {}", result.edit.path, result.applied);
+/// # Ok(())
+/// # }
+/// ```
+pub struct CodepilotClient {
+    orchestrator: CodeTaskOrchestrator,
+    confirmations: mpsc::UnboundedReceiver<ConfirmationRequest>,
+    approver: Option<Approver>,
+    session: SessionContext,
+}
+
+/// Settings for a `CodepilotClient`, on top of a `Config`.
+pub struct ClientBuilder {
+    config: Config,
+    approver: Option<Approver>,
+}
+
+impl ClientBuilder {
+    pub fn api_key(mut self, key: impl Into<String>) -> Self {
+        self.config.openai_api_key = Some(key.into());
+        self
+    }
+
+    /// An OpenAI-compatible endpoint, e.g. `http://localhost:11434/v1`.
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.config.openai_base_url = Some(url.into());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.config.model = model.into();
+        self
+    }
+
+    /// The JS/TS project to edit.
+    pub fn repo(mut self, path: impl AsRef<Path>) -> Self {
+        self.config.target_repo_path = path.as_ref().display().to_string();
+        self
+    }
+
+    pub fn mode(mut self, mode: AgentMode) -> Self {
+        self.config.agent_mode = mode;
+        self
+    }
+
+    /// Refuse every write, whatever the mode; see `Config::read_only`.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.config.read_only = read_only;
+        self
+    }
+
+    /// Globs limiting what the agent may read or edit; see `PathFilter`.
+    pub fn paths(mut self, allowed: &[&str], denied: &[&str]) -> Self {
+        self.config.allowed_paths = allowed.iter().map(|path| path.to_string()).collect();
+        self.config.denied_paths = denied.iter().map(|path| path.to_string()).collect();
+        self
+    }
+
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.config.max_retries = retries;
+        self
+    }
+
+    /// Decide edits that need approval with `approver`. Without one they're
+    /// declined, unless the config has `auto_approve`.
+    pub fn approve_with(
+        mut self,
+        approver: impl FnMut(&ConfirmationRequest) -> ConfirmationResponse + Send + 'static,
+    ) -> Self {
+        self.approver = Some(Box::new(approver));
+        self
+    }
+
+    pub async fn build(self) -> Result<CodepilotClient> {
+        self.config.validate()?;
+        let (confirm_tx, confirmations) = mpsc::unbounded_channel();
+        let orchestrator = CodeTaskOrchestrator::new(&self.config)
+            .await?
+            .with_confirmation_channel(confirm_tx);
+        Ok(CodepilotClient {
+            orchestrator,
+            confirmations,
+            approver: self.approver,
+            session: SessionContext::new(),
+        })
+    }
+}
+
+impl CodepilotClient {
+    pub fn builder(config: Config) -> ClientBuilder {
+        ClientBuilder {
+            config,
+            approver: None,
+        }
+    }
+
+    /// Run `task` to completion. Tasks share a session, so a follow-up can
+    /// refer back to earlier ones the way it would in a TUI tab.
+    #[tracing::instrument(name = "query", skip_all)]
+    pub async fn query(&mut self, task: &str) -> Result<TaskResult> {
+        let result = {
+            let run = self.orchestrator.run_task(task, &self.session);
+            tokio::pin!(run);
+            loop {
+                tokio::select! {
+                    result = &mut run => break result,
+                    Some(request) = self.confirmations.recv() => {
+                        let response = match &mut self.approver {
+                            Some(approver) => approver(&request),
+                            None => ConfirmationResponse::Decline,
+                        };
+                        let _ = request.respond.send(response);
+                    }
+                }
+            }
+        };
+        match &result {
+            Ok(result) => self.session.record_result(task, result),
+            Err(e) => self.session.record_error(task, &e.to_string()),
+        }
+        result
+    }
+
+    /// Progress for every task run from now on: files read, edits, gate runs.
+    pub fn events(&self) -> broadcast::Receiver<OrchestratorEvent> {
+        self.orchestrator.subscribe()
+    }
+
+    /// Whether the provider answers with the configured key and model.
+    pub async fn check_connection(&self) -> Result<()> {
+        self.orchestrator.check_connection().await
+    }
+
+    pub fn session(&self) -> &SessionContext {
+        &self.session
+    }
+
+    /// Start over: later tasks no longer see the earlier ones.
+    pub fn clear_session(&mut self) {
+        self.session.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn build_requires_an_api_key_and_starts_an_empty_session() {
+        let mut config = Config::from_vars([]);
+        config.openai_api_key = None;
+        assert!(
+            CodepilotClient::builder(config.clone())
+                .build()
+                .await
+                .is_err()
+        );
+
+        let client = CodepilotClient::builder(config)
+            .api_key("sk-test")
+            .mode(AgentMode::Plan)
+            .build()
+            .await
+            .unwrap();
+        assert!(client.session().turns.is_empty());
+    }
+}
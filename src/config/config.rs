@@ -1,11 +1,49 @@
+use std::collections::HashMap;
 use std::env;
+use std::str::FromStr;
+
 use anyhow::Result;
 
+use crate::error::CodepilotError;
+
+/// How much the agent may do without asking (`AGENT_MODE` in `.env`). Distinct
+/// from the TUI's NORMAL/INSERT input mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AgentMode {
+    /// Pause for confirmation before every file write.
+    Ask,
+    /// Run unattended; only destructive edits need confirmation.
+    #[default]
+    Agent,
+    /// Propose the edit and stop: nothing is written and no gate runs.
+    Plan,
+}
+
+impl AgentMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ask" => Some(AgentMode::Ask),
+            "agent" => Some(AgentMode::Agent),
+            "plan" => Some(AgentMode::Plan),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AgentMode::Ask => "ASK",
+            AgentMode::Agent => "AGENT",
+            AgentMode::Plan => "PLAN",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     // LLM Configuration
     pub openai_base_url: Option<String>,
     pub openai_api_key: Option<String>,
+    pub model: String,
 
     // Agent Configuration
     pub agent_name: String,
@@ -14,63 +52,326 @@ pub struct Config {
     pub max_retries: u32,
     pub max_loops: u32,
     pub save_state_dir: Option<String>,
+    /// Encrypt everything saved under `save_state_dir` with a passphrase; see
+    /// `cli::encryption`.
+    pub encrypt_state: bool,
 
     // Target repo for code edits
     pub target_repo_path: String,
 
+    // Safety
+    pub agent_mode: AgentMode,
+    /// Approve every confirmation automatically - the `--yes` escape hatch for
+    /// runs with nobody at the keyboard.
+    pub auto_approve: bool,
+    /// `READ_ONLY` (`--read-only`): refuse every file write, whatever the
+    /// mode. Tasks still read the repo and propose edits.
+    pub read_only: bool,
+    /// Glob patterns limiting which repo paths the agent may read or edit.
+    pub allowed_paths: Vec<String>,
+    pub denied_paths: Vec<String>,
+    /// `REDACT_PATTERNS`: a regex for more secrets to mask in output, on top
+    /// of the token shapes `redact` knows.
+    pub redact_patterns: Option<String>,
+
+    // Appearance
+    /// Built-in TUI theme: dark, light, or solarized.
+    pub theme: String,
+    /// `THEME_<ROLE>=#rrggbb` color overrides as `(role, value)`, role lowercased.
+    pub theme_overrides: Vec<(String, String)>,
+    /// Normal-mode key preset: default or vim.
+    pub keymap: String,
+    /// `KEYMAP_<ACTION>=<keys>` rebindings as `(action, keys)`, action lowercased.
+    pub keymap_overrides: Vec<(String, String)>,
+    /// Emit OSC 8 escapes so URLs and repo files are clickable in the terminal.
+    pub hyperlinks: bool,
+    /// `WRAP_WIDTH`: columns responses are wrapped to, `0` for no wrapping.
+    /// Unset, they're fitted to the activity pane or the terminal.
+    pub wrap_width: Option<usize>,
+    /// `TRUNCATE_AT`: characters of a long value shown before it's cut short,
+    /// `0` to show everything. `z` expands a message either way.
+    pub truncate_at: Option<usize>,
+    /// `LOCALE`: the interface language. Anything but `en` is read from
+    /// `{LOCALE_DIR}/{LOCALE}.ftl`, falling back to English message by message.
+    pub locale: String,
+    pub locale_dir: String,
+
     // Logging
+    /// `RUST_LOG`: the `tracing` filter, e.g. `info` or `codepilot=debug`.
     pub log_level: String,
+    /// `LOG_DIR`: where the JSON log file is kept, defaulting to
+    /// `{save_state_dir}/logs`. With neither set, nothing is written. The
+    /// default is sealed under ENCRYPT_STATE, and skipped by the commands that
+    /// never unlock it; `LOG_DIR` is always plain text.
+    pub log_dir: Option<String>,
+
+    // Record and replay
+    /// `CODEPILOT_RECORD`: append every LLM exchange to `fixtures_path`.
+    pub record_llm: bool,
+    /// `CODEPILOT_REPLAY`: answer from `fixtures_path` instead of the
+    /// provider - offline, and without an API key.
+    pub replay_llm: bool,
+    /// `CODEPILOT_FIXTURES`: the JSON-lines file both of them use.
+    pub fixtures_path: String,
+
+    /// Values `from_env` couldn't read and replaced with the default, as
+    /// `NAME='value'`. `validate` refuses to go on with any.
+    pub unreadable: Vec<String>,
 }
 
 impl Config {
+    /// Settings from the environment and `.env`. An API key set in neither is
+    /// looked up in the keyring, which means running its helper, so only then.
     pub fn from_env() -> Result<Self> {
         dotenv::dotenv().ok();
+        let mut config = Self::from_vars(env::vars());
+        if config.openai_api_key.is_none() {
+            config.openai_api_key = super::secrets::lookup("openai");
+        }
+        Ok(config)
+    }
 
-        Ok(Self {
+    /// Settings from `vars` alone, as if they were the whole environment:
+    /// no `.env`, no keyring. Unset variables take their defaults, which is
+    /// how tests get a config that doesn't depend on the machine.
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let vars: HashMap<String, String> = vars.into_iter().collect();
+        let var = |name: &str| vars.get(name).cloned();
+        let mut unreadable = Vec::new();
+        let agent_mode = match var("AGENT_MODE") {
+            Some(mode) => AgentMode::parse(&mode).unwrap_or_else(|| {
+                unreadable.push(format!("AGENT_MODE='{mode}'"));
+                AgentMode::default()
+            }),
+            None => AgentMode::default(),
+        };
+
+        Self {
             // LLM Configuration
-            openai_base_url: env::var("OPENAI_BASE_URL").ok(),
-            openai_api_key: env::var("OPENAI_API_KEY").ok(),
+            openai_base_url: var("OPENAI_BASE_URL"),
+            openai_api_key: var("OPENAI_API_KEY").filter(|key| !key.is_empty()),
+            model: var("OPENAI_MODEL")
+                .unwrap_or_else(|| "gpt-4-turbo".to_string()),
 
             // Agent Configuration
-            agent_name: env::var("AGENT_NAME")
-                .unwrap_or_else(|_| "CodePilotAgent".to_string()),
-            user_name: env::var("USER_NAME")
-                .unwrap_or_else(|_| "User".to_string()),
-            system_prompt: env::var("SYSTEM_PROMPT")
-                .unwrap_or_else(|_| "You are a coding agent that edits JS/TS codebases.".to_string()),
-            max_retries: env::var("MAX_RETRIES")
-                .unwrap_or_else(|_| "3".to_string())
-                .parse()
-                .unwrap_or(3),
-            max_loops: env::var("MAX_LOOPS")
-                .unwrap_or_else(|_| "10".to_string())
-                .parse()
-                .unwrap_or(10),
-            save_state_dir: env::var("SAVE_STATE_DIR").ok(),
-
-            target_repo_path: env::var("TARGET_REPO_PATH")
-                .unwrap_or_else(|_| ".".to_string()),
+            agent_name: var("AGENT_NAME").unwrap_or_else(|| "CodePilotAgent".to_string()),
+            user_name: var("USER_NAME").unwrap_or_else(|| "User".to_string()),
+            system_prompt: var("SYSTEM_PROMPT").unwrap_or_else(|| {
+                "You are a coding agent that edits JS/TS codebases.".to_string()
+            }),
+            max_retries: parsed("MAX_RETRIES", var("MAX_RETRIES"), &mut unreadable).unwrap_or(3),
+            max_loops: parsed("MAX_LOOPS", var("MAX_LOOPS"), &mut unreadable).unwrap_or(10),
+            save_state_dir: var("SAVE_STATE_DIR"),
+            encrypt_state: var("ENCRYPT_STATE")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+
+            target_repo_path: var("TARGET_REPO_PATH")
+                .unwrap_or_else(|| ".".to_string()),
+
+            // Safety
+            agent_mode,
+            auto_approve: var("AUTO_APPROVE")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            read_only: var("READ_ONLY")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            allowed_paths: var("ALLOWED_PATHS")
+                .map(|v| split_list(&v))
+                .unwrap_or_default(),
+            denied_paths: var("DENIED_PATHS")
+                .map(|v| split_list(&v))
+                .unwrap_or_default(),
+            redact_patterns: var("REDACT_PATTERNS").filter(|v| !v.trim().is_empty()),
+
+            // Appearance
+            theme: var("THEME").unwrap_or_else(|| "dark".to_string()),
+            theme_overrides: prefixed_vars(&vars, "THEME_"),
+            keymap: var("KEYMAP").unwrap_or_else(|| "default".to_string()),
+            keymap_overrides: prefixed_vars(&vars, "KEYMAP_"),
+            hyperlinks: var("HYPERLINKS")
+                .map(|v| {
+                    !(v == "0" || v.eq_ignore_ascii_case("false") || v.eq_ignore_ascii_case("off"))
+                })
+                .unwrap_or(true),
+            wrap_width: parsed("WRAP_WIDTH", var("WRAP_WIDTH"), &mut unreadable),
+            truncate_at: parsed("TRUNCATE_AT", var("TRUNCATE_AT"), &mut unreadable),
+            locale: var("LOCALE").unwrap_or_else(|| "en".to_string()),
+            locale_dir: var("LOCALE_DIR").unwrap_or_else(|| "locales".to_string()),
 
             // Logging
-            log_level: env::var("RUST_LOG")
-                .unwrap_or_else(|_| "info".to_string()),
-        })
+            log_level: var("RUST_LOG")
+                .unwrap_or_else(|| "info".to_string()),
+            log_dir: var("LOG_DIR").filter(|v| !v.trim().is_empty()),
+
+            // Record and replay
+            record_llm: var("CODEPILOT_RECORD")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            replay_llm: var("CODEPILOT_REPLAY")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            fixtures_path: var("CODEPILOT_FIXTURES")
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| "codepilot-fixtures.jsonl".to_string()),
+
+            unreadable,
+        }
     }
 
+    /// Refuse settings that can't work: a missing API key first (unless
+    /// replaying, which never calls the provider), then everything
+    /// `validate_settings` checks.
     pub fn validate(&self) -> Result<()> {
-        if self.openai_api_key.as_ref().map_or(true, |s| s.is_empty()) {
-            return Err(anyhow::anyhow!("OPENAI_API_KEY cannot be empty"));
+        match self.openai_api_key.as_deref().map(str::trim) {
+            _ if self.replay_llm => {}
+            None | Some("") => {
+                return Err(
+                    CodepilotError::Config("OPENAI_API_KEY cannot be empty".to_string()).into(),
+                );
+            }
+            Some(key) if key.starts_with("your_") => {
+                return Err(CodepilotError::Config(
+                    "OPENAI_API_KEY is still the template placeholder".to_string(),
+                )
+                .into());
+            }
+            Some(_) => {}
         }
+        self.validate_settings()
+    }
 
-        Ok(())
+    /// `validate` short of the API key, which the TUI's setup form can ask
+    /// for: values that didn't parse, URL syntax, and flags that contradict
+    /// each other. Every problem is listed, not just the first.
+    pub fn validate_settings(&self) -> Result<()> {
+        let mut problems: Vec<String> = self
+            .unreadable
+            .iter()
+            .map(|value| format!("{value} is not a valid value"))
+            .collect();
+        if let Some(url) = &self.openai_base_url
+            && !is_http_url(url)
+        {
+            problems.push(format!("OPENAI_BASE_URL '{url}' is not an http(s) URL"));
+        }
+        if self.model.trim().is_empty() {
+            problems.push("OPENAI_MODEL is empty".to_string());
+        }
+        if self.max_loops == 0 {
+            problems.push("MAX_LOOPS must be at least 1".to_string());
+        }
+        if !std::path::Path::new(&self.target_repo_path).is_dir() {
+            problems.push(format!(
+                "TARGET_REPO_PATH '{}' is not a directory",
+                self.target_repo_path
+            ));
+        }
+        if self.encrypt_state && self.save_state_dir.is_none() {
+            problems.push(
+                "ENCRYPT_STATE is set but SAVE_STATE_DIR isn't, so there is nothing to encrypt"
+                    .to_string(),
+            );
+        }
+        if self.record_llm && self.replay_llm {
+            problems.push("CODEPILOT_RECORD and CODEPILOT_REPLAY can't both be set".to_string());
+        }
+        if let Some(pattern) = &self.redact_patterns
+            && regex::Regex::new(pattern).is_err()
+        {
+            problems.push("REDACT_PATTERNS is not a valid regex".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(CodepilotError::Config(problems.join("; ")).into())
+        }
+    }
+}
+
+/// `name` parsed, `None` while it's unset. A value that doesn't parse is noted
+/// in `unreadable` and treated as unset.
+fn parsed<T: FromStr>(
+    name: &str,
+    value: Option<String>,
+    unreadable: &mut Vec<String>,
+) -> Option<T> {
+    let value = value?;
+    match value.trim().parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            unreadable.push(format!("{name}='{value}'"));
+            None
+        }
     }
 }
 
+/// `http://` or `https://` followed by a host, with no spaces.
+fn is_http_url(url: &str) -> bool {
+    url.split_once("://").is_some_and(|(scheme, rest)| {
+        matches!(scheme, "http" | "https")
+            && !rest.is_empty()
+            && !rest.starts_with('/')
+            && !rest.contains(char::is_whitespace)
+    })
+}
+
+/// Every `{prefix}NAME=value` env var as `(name, value)`, name lowercased.
+fn prefixed_vars(vars: &HashMap<String, String>, prefix: &str) -> Vec<(String, String)> {
+    vars.iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(prefix)
+                .map(|name| (name.to_ascii_lowercase(), value.clone()))
+        })
+        .collect()
+}
+
+/// Split a comma-separated env value, dropping empty entries.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 pub fn get_openai_api_key() -> Result<String> {
-    std::env::var("OPENAI_API_KEY").map_err(|_| anyhow::anyhow!("OPENAI_API_KEY not found"))
+    std::env::var("OPENAI_API_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+        .or_else(|| super::secrets::lookup("openai"))
+        .ok_or_else(|| anyhow::anyhow!("OPENAI_API_KEY not found"))
 }
 
 pub fn get_openai_base_url() -> Result<String> {
     Ok(std::env::var("OPENAI_BASE_URL")
         .unwrap_or_else(|_| "https://api.openai.com/v1".to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_lists_every_setting_that_cannot_work() {
+        let mut config = Config::from_vars([("MAX_LOOPS".to_string(), "ten".to_string())]);
+        config.openai_api_key = Some("sk-test".to_string());
+        config.openai_base_url = Some("api.openai.com/v1".to_string());
+        config.encrypt_state = true;
+        config.save_state_dir = None;
+
+        let message = config.validate().unwrap_err().to_string();
+        assert_eq!(
+            message,
+            "MAX_LOOPS='ten' is not a valid value; \
+             OPENAI_BASE_URL 'api.openai.com/v1' is not an http(s) URL; \
+             ENCRYPT_STATE is set but SAVE_STATE_DIR isn't, so there is nothing to encrypt"
+        );
+        assert!(is_http_url("http://localhost:11434/v1") && !is_http_url("https:///v1"));
+
+        config.openai_api_key = None;
+        assert_eq!(config.validate().unwrap_err().to_string(), "OPENAI_API_KEY cannot be empty");
+        assert!(config.validate_settings().is_err());
+    }
+}
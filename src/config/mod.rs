@@ -1,4 +1,6 @@
 // Configuration module for the application
+#[allow(clippy::module_inception)]
 pub mod config;
+pub mod secrets;
 
 pub use config::*; 
\ No newline at end of file
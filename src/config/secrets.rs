@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::Result;
+
+/// The service name secrets are filed under; the account is what they're for.
+const SERVICE: &str = "codepilot";
+
+/// Secrets `codepilot auth` can keep in the OS keyring instead of `.env`: the
+/// API key, and the passphrase for `ENCRYPT_STATE`.
+pub const ACCOUNTS: [&str; 2] = ["openai", "state"];
+
+/// What each account's lookup found, so the helper runs once per account per
+/// process. `set` and `clear` forget the entry they change.
+static LOOKUPS: LazyLock<Mutex<HashMap<String, Option<String>>>> = LazyLock::new(Default::default);
+
+/// The OS secret store, reached through the command-line tool it ships with
+/// rather than a native binding. There's none for Windows: there, secrets go
+/// in `.env` or the environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Store {
+    /// The macOS keychain, via `security`.
+    Keychain,
+    /// GNOME Keyring, KWallet, and the like, via libsecret's `secret-tool`.
+    SecretService,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Lookup,
+    Store,
+    Clear,
+}
+
+fn store() -> Option<Store> {
+    if cfg!(target_os = "macos") {
+        Some(Store::Keychain)
+    } else if cfg!(unix) {
+        Some(Store::SecretService)
+    } else {
+        None
+    }
+}
+
+fn command(store: Store, op: Op, account: &str) -> Command {
+    let mut command = match store {
+        Store::Keychain => Command::new("security"),
+        Store::SecretService => Command::new("secret-tool"),
+    };
+    match (store, op) {
+        (Store::Keychain, Op::Lookup) => {
+            command.args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"])
+        }
+        // A trailing `-w` makes `security` prompt for the key itself, so it
+        // never shows up in a process listing.
+        (Store::Keychain, Op::Store) => command.args([
+            "add-generic-password",
+            "-U",
+            "-s",
+            SERVICE,
+            "-a",
+            account,
+            "-w",
+        ]),
+        (Store::Keychain, Op::Clear) => {
+            command.args(["delete-generic-password", "-s", SERVICE, "-a", account])
+        }
+        (Store::SecretService, Op::Lookup) => {
+            command.args(["lookup", "service", SERVICE, "account", account])
+        }
+        // Reads the key from stdin, prompting when that's a terminal.
+        (Store::SecretService, Op::Store) => command
+            .args(["store", "--label", &format!("CodePilot {account} secret")])
+            .args(["service", SERVICE, "account", account]),
+        (Store::SecretService, Op::Clear) => {
+            command.args(["clear", "service", SERVICE, "account", account])
+        }
+    };
+    command
+}
+
+/// The secret saved for `account`, if there's a keyring and it has one. Any
+/// failure - no tool installed, a locked keyring - counts as "not saved".
+pub fn lookup(account: &str) -> Option<String> {
+    let mut lookups = LOOKUPS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    lookups
+        .entry(account.to_string())
+        .or_insert_with(|| fetch(account))
+        .clone()
+}
+
+fn fetch(account: &str) -> Option<String> {
+    let output = command(store()?, Op::Lookup, account)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let secret = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !secret.is_empty()).then_some(secret)
+}
+
+/// `codepilot auth set`: the keyring tool asks for the secret on the terminal
+/// (or reads it from piped stdin) and saves it, replacing any earlier one.
+pub fn set(account: &str) -> Result<()> {
+    run(Op::Store, account)
+}
+
+/// `codepilot auth clear`.
+pub fn clear(account: &str) -> Result<()> {
+    run(Op::Clear, account)
+}
+
+fn run(op: Op, account: &str) -> Result<()> {
+    LOOKUPS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .remove(account);
+    let Some(store) = store() else {
+        anyhow::bail!(
+            "the keyring isn't supported on this OS (only macOS and Unix with secret-tool); \
+             set the secret in .env instead"
+        );
+    };
+    let mut command = command(store, op, account);
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| anyhow::anyhow!("could not run {program}: {e} (is it installed?)"))?;
+    if !status.success() {
+        anyhow::bail!("{program} failed ({status})");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_filed_under_the_service_and_account() {
+        let args = |store, op| {
+            command(store, op, "openai")
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            args(Store::SecretService, Op::Lookup),
+            ["lookup", "service", "codepilot", "account", "openai"]
+        );
+        assert_eq!(
+            args(Store::Keychain, Op::Store).last().map(String::as_str),
+            Some("-w")
+        );
+        assert_eq!(
+            command(Store::Keychain, Op::Clear, "openai").get_program(),
+            std::ffi::OsStr::new("security")
+        );
+    }
+}
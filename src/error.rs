@@ -0,0 +1,88 @@
+use async_openai::error::OpenAIError;
+
+use crate::i18n;
+use crate::orchestrator::Declined;
+use crate::policy::ActionClass;
+
+/// The failures worth telling apart. Functions still return `anyhow::Result`,
+/// with one of these somewhere in the chain under any added context; `find`
+/// digs it out, for exit codes, remediation hints, or a library caller's own
+/// `match`. Anything else is a plain I/O or internal error.
+#[derive(Debug, thiserror::Error)]
+pub enum CodepilotError {
+    /// Settings that can't work as given, e.g. a missing API key - as opposed
+    /// to anything that went wrong while using them.
+    #[error("{0}")]
+    Config(String),
+    /// The LLM provider failed: refused the key, unreachable, rate limited,
+    /// an unknown model, a reply it couldn't parse.
+    #[error(transparent)]
+    Provider(#[from] OpenAIError),
+    /// The model's reply couldn't be used: no edit within `MAX_LOOPS` steps,
+    /// an unparseable reply, a path outside the repo or the path globs.
+    #[error("{0}")]
+    Agent(String),
+    /// An edit that needed approval didn't get it.
+    #[error(transparent)]
+    Declined(#[from] Declined),
+    /// An edit refused outright because `READ_ONLY` (`--read-only`) is set.
+    #[error("read-only mode: refused the {} edit to {path}; nothing was written", .class.label())]
+    ReadOnly { class: ActionClass, path: String },
+    /// The terminal couldn't be set up, drawn to, or read from.
+    #[error("terminal: {0}")]
+    Ui(#[source] std::io::Error),
+}
+
+impl CodepilotError {
+    /// The first `CodepilotError` in `error`'s chain.
+    pub fn find(error: &anyhow::Error) -> Option<&Self> {
+        error.chain().find_map(|cause| cause.downcast_ref::<Self>())
+    }
+
+    /// What to do about it, in the configured locale, when there's something
+    /// more useful to say than the message itself.
+    pub fn hint(&self) -> Option<String> {
+        let id = match self {
+            CodepilotError::Config(message) if message.contains("OPENAI_API_KEY") => "hint-api-key",
+            CodepilotError::Config(_) => "hint-config",
+            CodepilotError::Provider(OpenAIError::ApiError(response)) => match response
+                .status_code
+                .as_u16()
+            {
+                401 | 403 => "hint-auth",
+                404 => "hint-model",
+                429 => "hint-rate-limit",
+                _ if response.api_error.code.as_deref() == Some("model_not_found") => "hint-model",
+                _ => return None,
+            },
+            CodepilotError::Provider(OpenAIError::Reqwest(_)) => "hint-unreachable",
+            CodepilotError::Provider(OpenAIError::JSONDeserialize(..)) => "hint-schema",
+            CodepilotError::ReadOnly { .. } => "hint-read-only",
+            _ => return None,
+        };
+        Some(i18n::tr(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn found_under_added_context() {
+        let error =
+            anyhow::Error::from(CodepilotError::Agent("no edit within 10 steps".to_string()))
+                .context("task failed");
+        assert!(matches!(
+            CodepilotError::find(&error),
+            Some(CodepilotError::Agent(_))
+        ));
+        assert_eq!(format!("{error:#}"), "task failed: no edit within 10 steps");
+        assert!(CodepilotError::find(&anyhow::anyhow!("disk full")).is_none());
+        assert!(
+            CodepilotError::Config("LOCALE=fr needs a catalog".to_string())
+                .hint()
+                .is_some()
+        );
+    }
+}
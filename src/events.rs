@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use crate::policy::ActionClass;
+use crate::runs::RunKind;
+
+/// Progress reported by `CodeTaskOrchestrator` while a task runs, over a
+/// `tokio::sync::broadcast` channel (see `CodeTaskOrchestrator::subscribe`).
+/// The TUI turns these into activity-log lines; embedders can do whatever they
+/// like with them. Sending never blocks the task - a slow subscriber just lags.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum OrchestratorEvent {
+    TaskStarted {
+        task: String,
+    },
+    /// The model asked to see these files before editing.
+    FilesRequested {
+        paths: Vec<String>,
+    },
+    /// Something fed to the model - a file, `tsc` output - had a line that
+    /// reads like instructions to it. Edits in this task will ask first.
+    InjectionSuspected {
+        source: String,
+        excerpt: String,
+    },
+    EditProposed {
+        path: String,
+        attempt: u32,
+    },
+    ConfirmationRequested {
+        path: PathBuf,
+        class: ActionClass,
+    },
+    GateStarted {
+        kind: RunKind,
+        attempt: u32,
+    },
+    GateFinished {
+        kind: RunKind,
+        attempt: u32,
+        passed: bool,
+    },
+    /// The edit was rejected and reverted; another attempt is coming.
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+    },
+    TaskFinished {
+        path: PathBuf,
+        applied: bool,
+        attempts: u32,
+    },
+    /// A chat completion came back; token counts are 0 if the provider sent no usage.
+    LlmResponded {
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    },
+    /// A chat completion request failed outright (network, auth, rate limit).
+    LlmFailed {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl OrchestratorEvent {
+    /// Log line for the event: a snake_case name plus its fields as `key=value`.
+    pub fn log_line(&self) -> String {
+        match self {
+            Self::TaskStarted { task } => format!("task_started task={task:?}"),
+            Self::FilesRequested { paths } => format!("files_requested paths={}", paths.join(",")),
+            Self::InjectionSuspected { source, excerpt } => {
+                format!("injection_suspected source={source} excerpt={excerpt:?}")
+            }
+            Self::EditProposed { path, attempt } => {
+                format!("edit_proposed path={path} attempt={attempt}")
+            }
+            Self::ConfirmationRequested { path, class } => {
+                format!(
+                    "confirmation_requested path={} class={}",
+                    path.display(),
+                    class.label()
+                )
+            }
+            Self::GateStarted { kind, attempt } => {
+                format!("gate_started kind={} attempt={attempt}", kind.label())
+            }
+            Self::GateFinished {
+                kind,
+                attempt,
+                passed,
+            } => {
+                format!(
+                    "gate_finished kind={} attempt={attempt} passed={passed}",
+                    kind.label()
+                )
+            }
+            Self::Retrying {
+                attempt,
+                max_attempts,
+            } => {
+                format!("retrying attempt={attempt} max_attempts={max_attempts}")
+            }
+            Self::TaskFinished {
+                path,
+                applied,
+                attempts,
+            } => {
+                format!(
+                    "task_finished path={} applied={applied} attempts={attempts}",
+                    path.display()
+                )
+            }
+            Self::LlmResponded {
+                prompt_tokens,
+                completion_tokens,
+            } => {
+                format!(
+                    "llm_responded prompt_tokens={prompt_tokens} completion_tokens={completion_tokens}"
+                )
+            }
+            Self::LlmFailed { message } => {
+                format!(
+                    "llm_failed message={:?}",
+                    message.lines().next().unwrap_or("")
+                )
+            }
+            Self::Error { message } => {
+                format!("error message={:?}", message.lines().next().unwrap_or(""))
+            }
+        }
+    }
+}
@@ -1,19 +1,542 @@
+use std::sync::LazyLock;
+
 use serde_json::Value;
 use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::Config;
+use crate::error::CodepilotError;
+use crate::i18n;
+use crate::redact;
+
+/// A response split into the parts each output needs to show differently;
+/// see `ResponseFormatter::render`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RenderedResponse {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    /// Prose, still with its Markdown emphasis and inline links.
+    Text { text: String },
+    Code {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        code: String,
+    },
+    Table { header: Vec<String>, rows: Vec<Vec<String>> },
+    Json { value: Value },
+    /// A line that was nothing but a link.
+    Link { label: String, url: String },
+    Diff { diff: String },
+}
+
+impl RenderedResponse {
+    /// Plain text with the default formatter; see `ResponseFormatter::text`.
+    pub fn text(&self) -> String {
+        ResponseFormatter::new().text(self)
+    }
+
+    /// Every table in the response as `(header, rows)`: Markdown tables and
+    /// arrays of like JSON objects, with their cells in full.
+    pub fn tables(&self) -> Vec<(Vec<String>, Vec<Vec<String>>)> {
+        self.blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Table { header, rows } => Some((header.clone(), rows.clone())),
+                Block::Json { value } => json_table(value, &[]),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Markdown for exports, with code, JSON, and diffs fenced.
+    pub fn markdown(&self) -> String {
+        let blocks: Vec<String> = self
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Text { text } => text.clone(),
+                Block::Code { language, code } => {
+                    format!("```{}\n{}\n```", language.as_deref().unwrap_or(""), code.trim_end())
+                }
+                Block::Table { header, rows } => {
+                    let row = |cells: &[String]| format!("| {} |", cells.join(" | "));
+                    let mut table = vec![row(header), row(&vec!["---".to_string(); header.len()])];
+                    table.extend(rows.iter().map(|cells| row(cells)));
+                    table.join("\n")
+                }
+                Block::Json { value } => match json_table(value, &[]) {
+                    Some((header, rows)) => Self {
+                        blocks: vec![Block::Table { header, rows }],
+                    }
+                    .markdown(),
+                    None => format!(
+                        "```json\n{}\n```",
+                        serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+                    ),
+                },
+                Block::Link { label, url } => format!("[{label}]({url})"),
+                Block::Diff { diff } => format!("```diff\n{}\n```", diff.trim_end()),
+            })
+            .collect();
+        blocks.join("\n\n")
+    }
+}
+
+/// What a line of a unified diff is, for coloring it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine {
+    /// `diff --git`, `index`, and the `---`/`+++` file names.
+    Header,
+    Hunk,
+    Added,
+    Removed,
+    Context,
+}
+
+impl DiffLine {
+    pub fn of(line: &str) -> Self {
+        if ["diff ", "index ", "--- ", "+++ "].iter().any(|prefix| line.starts_with(prefix)) {
+            DiffLine::Header
+        } else if line.starts_with("@@") {
+            DiffLine::Hunk
+        } else if line.starts_with('+') {
+            DiffLine::Added
+        } else if line.starts_with('-') {
+            DiffLine::Removed
+        } else {
+            DiffLine::Context
+        }
+    }
+}
+
+/// How many of `lines` make up a diff pasted without a fence: from a
+/// `diff --git` or `---`/`+++` pair to the first line that can't be part of
+/// one. Zero if they don't start one.
+fn diff_len(lines: &[&str]) -> usize {
+    let starts = lines[0].starts_with("diff --git ")
+        || (lines[0].starts_with("--- ")
+            && lines.get(1).is_some_and(|next| next.starts_with("+++ ")));
+    if !starts {
+        return 0;
+    }
+    let continues = |line: &str| {
+        [
+            "+",
+            "-",
+            " ",
+            "@@",
+            "\\",
+            "diff ",
+            "index ",
+            "new file",
+            "deleted file",
+            "similarity ",
+            "rename ",
+        ]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+    };
+    let mut len = 1;
+    while len < lines.len() {
+        let line = lines[len];
+        // A blank line is a trimmed context line only if more diff follows.
+        let more = line.is_empty()
+            && lines
+                .get(len + 1)
+                .is_some_and(|next| continues(next) && !next.starts_with(' '));
+        if !continues(line) && !more {
+            break;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// `value` as a table if it's an array of objects that all have the same
+/// keys: columns named in `priority` first, then the rest in key order.
+fn json_table(value: &Value, priority: &[String]) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+    let items = value.as_array()?;
+    let first = items.first()?.as_object().filter(|first| !first.is_empty())?;
+    let uniform = items.iter().all(|item| {
+        item.as_object().is_some_and(|object| {
+            object.len() == first.len() && first.keys().all(|key| object.contains_key(key))
+        })
+    });
+    if !uniform {
+        return None;
+    }
+    let mut header: Vec<String> = priority
+        .iter()
+        .filter(|key| first.contains_key(*key))
+        .cloned()
+        .collect();
+    header.extend(first.keys().filter(|key| !priority.contains(key)).cloned());
+    let rows = items
+        .iter()
+        .map(|item| {
+            header
+                .iter()
+                .map(|key| match &item[key] {
+                    Value::String(s) => s.replace('\n', " "),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    Some((header, rows))
+}
+
+/// Color and cursor codes, which take no room on screen.
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]").expect("valid regex"));
+
+/// A line that is only a link: `[label](url)`, or a bare http(s) URL.
+static LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[([^\]]+)\]\((\S+)\)$|^(https?://\S+)$").expect("valid regex"));
+
+/// What `protect_spans` keeps intact: inline code, URLs, and brace-delimited
+/// objects up to one level of nesting.
+static SPAN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"`([^`]+)`|https?://[^\s<>()\[\]`"]+|\{[^{}]*(?:\{[^{}]*\}[^{}]*)*\}"#)
+        .expect("valid regex")
+});
+
+/// `text` as the pieces a terminal draws: graphemes with the columns each
+/// takes (two for CJK and most emoji, none for combining marks), and escape
+/// sequences with none.
+fn glyphs(text: &str) -> Vec<(&str, usize)> {
+    let mut glyphs = Vec::new();
+    let mut start = 0;
+    let graphemes =
+        |text| UnicodeSegmentation::graphemes(text, true).map(|g: &str| (g, g.width().min(2)));
+    for escape in ANSI_ESCAPE.find_iter(text) {
+        glyphs.extend(graphemes(&text[start..escape.start()]));
+        glyphs.push((escape.as_str(), 0));
+        start = escape.end();
+    }
+    glyphs.extend(graphemes(&text[start..]));
+    glyphs
+}
+
+/// Columns `text` takes up on a terminal, rather than its bytes or chars.
+pub fn display_width(text: &str) -> usize {
+    glyphs(text).iter().map(|(_, width)| width).sum()
+}
+
+/// `text` in pieces at most `width` columns wide, for breaking a word too
+/// long for its line. A glyph wider than `width` still gets a piece.
+pub fn split_to_width(text: &str, width: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let (mut start, mut end, mut used) = (0, 0, 0);
+    for (glyph, glyph_width) in glyphs(text) {
+        if used + glyph_width > width && used > 0 {
+            pieces.push(&text[start..end]);
+            (start, used) = (end, 0);
+        }
+        end += glyph.len();
+        used += glyph_width;
+    }
+    pieces.push(&text[start..]);
+    pieces
+}
+
+/// `text` cut to `width` columns, ending in `…` when anything was left off.
+fn truncate_to_width(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let mut used = 0;
+    let mut kept: String = glyphs(text)
+        .into_iter()
+        .take_while(|(_, glyph_width)| {
+            used += glyph_width;
+            used < width
+        })
+        .map(|(glyph, _)| glyph)
+        .collect();
+    kept.push('…');
+    kept
+}
 
-pub struct ResponseFormatter;
+/// A table as space-padded columns under a dashed rule, dropping columns from
+/// the right until it fits in `options.max_width`. Ragged rows are cut or
+/// padded to the header; a table without one takes its widest row.
+fn columns(header: &[String], rows: &[Vec<String>], options: &TableOptions) -> String {
+    let max_width = options.max_width;
+    let count = if header.is_empty() {
+        rows.iter().map(Vec::len).max().unwrap_or(0)
+    } else {
+        header.len()
+    };
+    let fit = |cells: &[String]| -> Vec<String> {
+        (0..count)
+            .map(|i| {
+                cells.get(i).map_or_else(String::new, |cell| {
+                    truncate_to_width(cell, options.max_cell)
+                })
+            })
+            .collect()
+    };
+    let header = fit(header);
+    let rows: Vec<Vec<String>> = rows.iter().map(|row| fit(row)).collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|cell| display_width(cell)).collect();
+    for row in &rows {
+        for (max, cell) in widths.iter_mut().zip(row) {
+            *max = (*max).max(display_width(cell));
+        }
+    }
+    let total =
+        |widths: &[usize]| widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+    let all = widths.len();
+    while widths.len() > 1 && total(&widths) > max_width {
+        widths.pop();
+    }
+
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - display_width(cell))))
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+    let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    let mut lines = vec![line(&header), rule.join("  ")];
+    lines.extend(rows.iter().map(|row| line(row)));
+    if widths.len() < all {
+        let dropped: Vec<&str> = header[widths.len()..]
+            .iter()
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+            .collect();
+        match dropped.as_slice() {
+            [] => lines.push(format!("(+{} more)", all - widths.len())),
+            names => lines.push(format!("(+{} more: {})", all - widths.len(), names.join(", "))),
+        }
+    }
+    lines.join("\n")
+}
+
+/// How arrays of like objects, and Markdown tables, are laid out as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOptions {
+    /// The widest a table gets; columns past it are left off and named below.
+    pub max_width: usize,
+    /// Columns of a cell shown before it's cut off with `…`.
+    pub max_cell: usize,
+    /// Keys shown first, in this order, so they're the last to be dropped.
+    pub priority: Vec<String>,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            max_cell: 40,
+            priority: ["id", "number", "name", "title", "state", "status"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// Columns prose is wrapped to when nothing says otherwise.
+const DEFAULT_WRAP_WIDTH: usize = 80;
+
+#[derive(Debug, Clone)]
+pub struct ResponseFormatter {
+    /// Columns prose is wrapped to; `None` leaves its lines as they are.
+    wrap_width: Option<usize>,
+    /// Graphemes of a JSON string shown before it's cut off and marked with
+    /// how much is hidden.
+    max_string: usize,
+    /// Spaces per level of nested JSON.
+    indent: usize,
+    table: TableOptions,
+}
+
+impl Default for ResponseFormatter {
+    fn default() -> Self {
+        Self {
+            wrap_width: Some(DEFAULT_WRAP_WIDTH),
+            max_string: 50,
+            indent: 2,
+            table: TableOptions::default(),
+        }
+    }
+}
 
 impl ResponseFormatter {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Wrapped as `WRAP_WIDTH` says, or to the `available` columns when it's
+    /// unset. Tables are kept to the same width.
+    pub fn fitted(config: &Config, available: usize) -> Self {
+        let width = match config.wrap_width {
+            Some(0) => None,
+            Some(width) => Some(width),
+            None => Some(available.max(20)),
+        };
+        let mut formatter = Self::new().with_wrap_width(width);
+        if let Some(width) = width {
+            formatter.table.max_width = width;
+        }
+        match config.truncate_at {
+            Some(0) => formatter.expanded(),
+            Some(graphemes) => formatter.with_max_string(graphemes),
+            None => formatter,
+        }
+    }
+
+    /// Nothing cut short: long strings and table cells shown in full, for
+    /// expanding a message.
+    pub fn expanded(mut self) -> Self {
+        self.max_string = usize::MAX;
+        self.table.max_cell = usize::MAX;
+        self
+    }
+
+    /// Whether anything in `response` is cut short as this formatter shows it.
+    pub fn truncates(&self, response: &RenderedResponse) -> bool {
+        self.text(response) != self.clone().expanded().text(response)
+    }
+
+    pub fn with_wrap_width(mut self, width: Option<usize>) -> Self {
+        self.wrap_width = width;
+        self
+    }
+
+    pub fn with_max_string(mut self, graphemes: usize) -> Self {
+        // Room for at least one grapheme before the `...`.
+        self.max_string = graphemes.max(4);
+        self
+    }
+
+    pub fn with_indent(mut self, spaces: usize) -> Self {
+        self.indent = spaces;
+        self
+    }
+
+    pub fn with_table_options(mut self, table: TableOptions) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Plain text for the TUI and terminal output: prose cleaned and wrapped
+    /// as before, code and diffs verbatim, tables lined up in columns.
+    pub fn text(&self, response: &RenderedResponse) -> String {
+        let blocks: Vec<String> = response
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Text { text } => {
+                    // Code spans, URLs, and JSON are set aside first so the
+                    // Markdown and spacing passes can't touch them.
+                    let (masked, spans) = self.protect_spans(text);
+                    let cleaned = self.space_punctuation(&self.clean_markdown(&masked));
+                    self.wrap_and_format(&restore_spans(&cleaned, &spans))
+                }
+                Block::Code { code, .. } => code.trim_end().to_string(),
+                Block::Table { header, rows } => columns(header, rows, &self.table),
+                Block::Json { value } => match json_table(value, &self.table.priority) {
+                    Some((header, rows)) => columns(&header, &rows, &self.table),
+                    None => self.format_json_value(value, 0),
+                },
+                Block::Link { label, url } if label == url => url.clone(),
+                Block::Link { label, url } => format!("{label} ({url})"),
+                Block::Diff { diff } => diff.trim_end().to_string(),
+            })
+            .collect();
+        blocks.join("\n\n")
     }
 
     /// Formats response text to be more user-friendly for AI application display
     pub fn format_response(&self, response: &str) -> String {
-        let cleaned = self.clean_markdown(response);
-        let formatted = self.format_json_blocks(&cleaned);
-        let final_text = self.format_code_blocks(&formatted);
-        self.wrap_and_format(&final_text)
+        self.text(&self.render(response))
+    }
+
+    /// Split a response into blocks: fenced code (JSON and diffs by their
+    /// language or look), pipe tables, lines that are only a link, standalone
+    /// JSON, and the prose between them.
+    /// Secrets are masked first, so no output made from the blocks has them.
+    pub fn render(&self, response: &str) -> RenderedResponse {
+        let response = redact::text(response);
+        let mut blocks = Vec::new();
+        let mut prose = Vec::new();
+        let mut lines = response.lines();
+        while let Some(line) = lines.next() {
+            let Some(language) = line.trim_start().strip_prefix("```") else {
+                prose.push(line);
+                continue;
+            };
+            self.push_prose(&mut blocks, &prose);
+            prose.clear();
+            let code: Vec<&str> = lines
+                .by_ref()
+                .take_while(|line| !line.trim_start().starts_with("```"))
+                .collect();
+            blocks.push(fenced(language.trim(), code.join("\n")));
+        }
+        self.push_prose(&mut blocks, &prose);
+        RenderedResponse { blocks }
+    }
+
+    fn push_prose(&self, blocks: &mut Vec<Block>, lines: &[&str]) {
+        let mut text: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i].trim();
+            if let Some(caps) = LINK.captures(line) {
+                push_text(blocks, &text);
+                text.clear();
+                let (label, url) = match caps.get(3) {
+                    Some(url) => (url.as_str(), url.as_str()),
+                    None => (&caps[1], &caps[2]),
+                };
+                blocks.push(Block::Link {
+                    label: label.to_string(),
+                    url: url.to_string(),
+                });
+                i += 1;
+                continue;
+            }
+            let diff = diff_len(&lines[i..]);
+            if diff > 0 {
+                push_text(blocks, &text);
+                text.clear();
+                blocks.push(Block::Diff {
+                    diff: lines[i..i + diff].join("\n"),
+                });
+                i += diff;
+                continue;
+            }
+            let table_len = lines[i..]
+                .iter()
+                .take_while(|line| line.trim().starts_with('|'))
+                .count();
+            if table_len >= 2 && is_rule(lines[i + 1]) {
+                push_text(blocks, &text);
+                text.clear();
+                blocks.push(Block::Table {
+                    header: cells(lines[i]),
+                    rows: lines[i + 2..i + table_len].iter().map(|line| cells(line)).collect(),
+                });
+                i += table_len;
+                continue;
+            }
+            text.push(lines[i]);
+            i += 1;
+        }
+        push_text(blocks, &text);
     }
 
     /// Remove markdown formatting and convert to plain text
@@ -21,7 +544,7 @@ impl ResponseFormatter {
         let mut result = text.to_string();
 
         // Remove markdown headers (### ## #)
-        let header_regex = Regex::new(r"^#{1,6}\s*(.*)$").unwrap();
+        let header_regex = Regex::new(r"(?m)^#{1,6}\s*(.*)$").unwrap();
         result = header_regex.replace_all(&result, "$1").to_string();
 
         // Remove bold/italic markers (**text** or *text*)
@@ -42,26 +565,35 @@ impl ResponseFormatter {
         result
     }
 
-    /// Format JSON blocks to be more readable
-    fn format_json_blocks(&self, text: &str) -> String {
-        // Try to parse and pretty-print JSON objects
-        let json_regex = Regex::new(r"(\{[^{}]*(?:\{[^{}]*\}[^{}]*)*\})").unwrap();
-        
-        json_regex.replace_all(text, |caps: &regex::Captures| {
-            let json_str = &caps[1];
-            match serde_json::from_str::<Value>(json_str) {
-                Ok(value) => {
-                    self.format_json_value(&value, 0)
+    /// Swap inline code, URLs, and JSON objects for placeholders, returning
+    /// what each stands for: code without its backticks, URLs as they are,
+    /// JSON formatted. Braces that don't parse as JSON stay in the text.
+    fn protect_spans(&self, text: &str) -> (String, Vec<String>) {
+        let mut spans = Vec::new();
+        let masked = SPAN.replace_all(text, |caps: &regex::Captures| {
+            let matched = &caps[0];
+            let (span, rest) = if let Some(code) = caps.get(1) {
+                (code.as_str().to_string(), "")
+            } else if matched.starts_with('{') {
+                match serde_json::from_str::<Value>(matched) {
+                    Ok(value) => (self.format_json_value(&value, 0), ""),
+                    Err(_) => return matched.to_string(),
                 }
-                Err(_) => json_str.to_string()
-            }
-        }).to_string()
+            } else {
+                // A sentence's closing punctuation isn't part of the URL.
+                let url = matched.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                (url.to_string(), &matched[url.len()..])
+            };
+            spans.push(span);
+            format!("{SPAN_START}{}{SPAN_END}{rest}", spans.len() - 1)
+        });
+        (masked.into_owned(), spans)
     }
 
     /// Recursively format JSON values with proper indentation
     fn format_json_value(&self, value: &Value, indent_level: usize) -> String {
-        let indent = "  ".repeat(indent_level);
-        let next_indent = "  ".repeat(indent_level + 1);
+        let indent = " ".repeat(self.indent * indent_level);
+        let next_indent = " ".repeat(self.indent * (indent_level + 1));
 
         match value {
             Value::Object(map) => {
@@ -101,8 +633,12 @@ impl ResponseFormatter {
                 }
             }
             Value::String(s) => {
-                if s.len() > 50 {
-                    format!("\"{}...\"", &s[..47])
+                // Count graphemes, not bytes: slicing at byte 47 panics mid-character.
+                let length = s.graphemes(true).count();
+                if length > self.max_string {
+                    let shown = self.max_string - 1;
+                    let truncated: String = s.graphemes(true).take(shown).collect();
+                    format!("\"{}…\" (+{} chars)", truncated, length - shown)
                 } else {
                     format!("\"{}\"", s)
                 }
@@ -113,16 +649,27 @@ impl ResponseFormatter {
         }
     }
 
-    /// Format code blocks with proper indentation
-    fn format_code_blocks(&self, text: &str) -> String {
-        // Add proper spacing around code-like content
-        let mut result = text.to_string();
-        
-        // Add spacing around colons and equal signs for better readability
-        result = result.replace(":", ": ");
-        result = result.replace("  :", ": "); // Fix double spaces
-        result = result.replace("= ", " = ");
-        
+    /// Space out a colon between words ("Error:missing" reads "Error: missing")
+    /// and an `=` with a space only after it. Colons next to digits or other
+    /// punctuation - times, ports, `std::io`, drive letters - are left alone.
+    fn space_punctuation(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::with_capacity(text.len());
+        for (i, &c) in chars.iter().enumerate() {
+            let prev = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1).copied();
+            match c {
+                ':' if prev.is_some_and(char::is_alphabetic)
+                    && next.is_some_and(char::is_alphabetic) =>
+                {
+                    result.push_str(": ")
+                }
+                '=' if prev.is_some_and(char::is_alphanumeric) && next == Some(' ') => {
+                    result.push_str(" =")
+                }
+                _ => result.push(c),
+            }
+        }
         result
     }
 
@@ -139,8 +686,10 @@ impl ResponseFormatter {
             }
 
             // Handle long lines by wrapping them
-            if trimmed.len() > 80 {
-                let wrapped = self.wrap_line(trimmed, 80);
+            if let Some(width) = self.wrap_width
+                && display_width(trimmed) > width
+            {
+                let wrapped = self.wrap_line(trimmed, width);
                 formatted_lines.extend(wrapped);
             } else {
                 formatted_lines.push(trimmed.to_string());
@@ -175,7 +724,7 @@ impl ResponseFormatter {
         for word in words {
             if current_line.is_empty() {
                 current_line = word.to_string();
-            } else if current_line.len() + word.len() + 1 <= width {
+            } else if display_width(&current_line) + display_width(word) < width {
                 current_line.push(' ');
                 current_line.push_str(word);
             } else {
@@ -209,18 +758,125 @@ impl ResponseFormatter {
 
     /// Format error messages
     pub fn format_error(&self, error: &str) -> String {
-        format!("Error: {}", self.format_response(error))
+        self.text(&self.render_error(error))
     }
 
     /// Format success messages
     pub fn format_success(&self, message: &str) -> String {
-        format!("Success: {}", self.format_response(message))
+        self.text(&self.render_success(message))
+    }
+
+    pub fn render_error(&self, error: &str) -> RenderedResponse {
+        self.labelled(&i18n::tr("response-error"), error)
+    }
+
+    pub fn render_success(&self, message: &str) -> RenderedResponse {
+        self.labelled(&i18n::tr("response-success"), message)
     }
+
+    /// `error` rendered like `render_error`, followed by what to do about it
+    /// when it's a failure CodePilot recognizes.
+    pub fn render_failure(&self, error: &anyhow::Error) -> RenderedResponse {
+        let mut response = self.render_error(&error.to_string());
+        if let Some(hint) = remediation(error) {
+            response.blocks.push(Block::Text { text: format!("→ {hint}") });
+        }
+        response
+    }
+
+    /// `message` rendered with `label` leading its first line.
+    fn labelled(&self, label: &str, message: &str) -> RenderedResponse {
+        let mut response = self.render(message);
+        match response.blocks.first_mut() {
+            Some(Block::Text { text }) => *text = format!("{label} {text}"),
+            _ => response.blocks.insert(0, Block::Text { text: label.to_string() }),
+        }
+        response
+    }
+}
+
+/// Around the index of a span `protect_spans` set aside; private-use
+/// characters, so nothing a model writes is mistaken for one.
+const SPAN_START: char = '\u{E000}';
+const SPAN_END: char = '\u{E001}';
+
+fn restore_spans(text: &str, spans: &[String]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(SPAN_START) {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + SPAN_START.len_utf8()..];
+        let Some(end) = after.find(SPAN_END) else {
+            break;
+        };
+        if let Some(span) = after[..end].parse::<usize>().ok().and_then(|i| spans.get(i)) {
+            result.push_str(span);
+        }
+        rest = &after[end + SPAN_END.len_utf8()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// What to do about `error`, judged by the `CodepilotError` in its chain: a
+/// config problem, or how the LLM provider failed.
+pub fn remediation(error: &anyhow::Error) -> Option<String> {
+    CodepilotError::find(error)?.hint()
+}
+
+/// A fenced block by its language, or by what's in it when there's none.
+fn fenced(language: &str, code: String) -> Block {
+    let looks_like_diff =
+        code.starts_with("diff --git") || code.starts_with("--- ") || code.starts_with("@@");
+    if language == "diff" || language == "patch" || (language.is_empty() && looks_like_diff) {
+        return Block::Diff { diff: code };
+    }
+    if (language == "json" || language.is_empty())
+        && let Ok(value) = serde_json::from_str::<Value>(&code)
+        && (value.is_object() || value.is_array())
+    {
+        return Block::Json { value };
+    }
+    Block::Code {
+        language: (!language.is_empty()).then(|| language.to_string()),
+        code,
+    }
+}
+
+/// Prose lines as a block, unless they're blank or one JSON value.
+fn push_text(blocks: &mut Vec<Block>, lines: &[&str]) {
+    let text = lines.join("\n");
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    if let Ok(value) = serde_json::from_str::<Value>(text)
+        && (value.is_object() || value.is_array())
+    {
+        blocks.push(Block::Json { value });
+        return;
+    }
+    blocks.push(Block::Text { text: text.to_string() });
+}
+
+fn cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+/// `|---|:--:|`, the line between a table's header and its rows.
+fn is_rule(line: &str) -> bool {
+    cells(line)
+        .iter()
+        .all(|cell| !cell.is_empty() && cell.chars().all(|c| c == '-' || c == ':'))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_openai::error::OpenAIError;
 
     #[test]
     fn test_json_formatting() {
@@ -230,6 +886,195 @@ mod tests {
         assert!(formatted.contains("name: \"test\""));
     }
 
+    #[test]
+    fn test_long_multibyte_strings_truncate_safely() {
+        let formatter = ResponseFormatter::new();
+        let value = Value::String("é".repeat(60));
+        let formatted = formatter.format_json_value(&value, 0);
+        assert_eq!(formatted, format!("\"{}…\" (+11 chars)", "é".repeat(49)));
+
+        let response = formatter.render(&format!(r#"{{"note": "{}"}}"#, "é".repeat(60)));
+        assert!(formatter.truncates(&response));
+        assert!(formatter.clone().expanded().text(&response).contains(&"é".repeat(60)));
+    }
+
+    #[test]
+    fn test_render_splits_blocks() {
+        let formatter = ResponseFormatter::new();
+        let response = "Updated **sum**:\n\n```ts\nexport const sum = (a: number) => a;\n```\n\n| file | bytes |\n|---|---:|\n| src/sum.ts | 40 |\n\n```\n--- a/src/sum.ts\n+++ b/src/sum.ts\n```\n[Docs](https://example.com/docs)\n{\"ok\": true}";
+        let rendered = formatter.render(response);
+        let kinds: Vec<&str> = rendered
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Text { .. } => "text",
+                Block::Code { .. } => "code",
+                Block::Table { .. } => "table",
+                Block::Json { .. } => "json",
+                Block::Link { .. } => "link",
+                Block::Diff { .. } => "diff",
+            })
+            .collect();
+        assert_eq!(kinds, ["text", "code", "table", "diff", "link", "json"]);
+
+        let text = rendered.text();
+        assert!(text.starts_with("Updated sum:"));
+        assert!(text.contains("export const sum = (a: number) => a;"));
+        assert!(text.contains("src/sum.ts  40"));
+        assert!(text.contains("Docs (https://example.com/docs)"));
+        assert!(rendered.markdown().contains("```ts\nexport const sum"));
+        assert_eq!(
+            serde_json::to_value(&rendered.blocks[4]).unwrap(),
+            serde_json::json!({"type": "link", "label": "Docs", "url": "https://example.com/docs"})
+        );
+        assert_eq!(formatter.format_error(""), "Error:");
+    }
+
+    #[test]
+    fn test_uniform_object_arrays_become_tables() {
+        let rows = r#"[{"body": "Crashes on start", "title": "Fix login", "id": 12},
+            {"body": "", "title": "Add dark mode", "id": 7}]"#;
+        let table = ResponseFormatter::new().format_response(rows);
+        assert_eq!(
+            table,
+            "id  title          body\n--  -------------  ----------------\n12  Fix login      Crashes on start\n7   Add dark mode"
+        );
+
+        let narrow = ResponseFormatter::new().with_table_options(TableOptions {
+            max_width: 20,
+            ..TableOptions::default()
+        });
+        assert!(narrow.format_response(rows).ends_with("(+1 more: body)"));
+
+        let ragged = columns(
+            &["id".to_string(), "title".to_string()],
+            &[
+                vec![
+                    "1".to_string(),
+                    "Fix login".to_string(),
+                    "stray cell".to_string(),
+                ],
+                vec!["2".to_string()],
+            ],
+            &TableOptions {
+                max_width: 6,
+                ..TableOptions::default()
+            },
+        );
+        assert_eq!(ragged, "id\n--\n1\n2\n(+1 more: title)");
+        let headerless = columns(
+            &[],
+            &[vec!["a".to_string(), "bbbbbbbb".to_string()]],
+            &TableOptions {
+                max_width: 4,
+                ..TableOptions::default()
+            },
+        );
+        assert!(headerless.ends_with("(+1 more)"));
+
+        let mixed = r#"[{"id": 1}, {"name": "x"}]"#;
+        assert!(!ResponseFormatter::new().format_response(mixed).contains("--"));
+    }
+
+    #[test]
+    fn test_unfenced_diffs_keep_their_lines() {
+        let response = "Here's the patch:\ndiff --git a/src/sum.ts b/src/sum.ts\n--- a/src/sum.ts\n+++ b/src/sum.ts\n@@ -1,2 +1,2 @@\n-export const sum = (a: number) => a;\n+export const sum = (a: number, b: number) => a + b;\n   // unchanged\n\nThat's all.";
+        let rendered = ResponseFormatter::new().render(response);
+        let Some(Block::Diff { diff }) = rendered.blocks.get(1) else {
+            panic!("expected a diff block: {:?}", rendered.blocks);
+        };
+        assert!(
+            diff.ends_with("+export const sum = (a: number, b: number) => a + b;\n   // unchanged")
+        );
+        assert!(rendered.text().contains("\n   // unchanged"));
+        assert_eq!(rendered.blocks.len(), 3);
+
+        let kinds: Vec<DiffLine> = diff.lines().map(DiffLine::of).collect();
+        assert_eq!(
+            kinds,
+            [
+                DiffLine::Header,
+                DiffLine::Header,
+                DiffLine::Header,
+                DiffLine::Hunk,
+                DiffLine::Removed,
+                DiffLine::Added,
+                DiffLine::Context
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap_width_and_json_layout_are_configurable() {
+        let sentence = "the quick brown fox jumps over the lazy dog ".repeat(3);
+        let narrow = ResponseFormatter::new().with_wrap_width(Some(30)).format_response(&sentence);
+        assert!(narrow.lines().count() > 3 && narrow.lines().all(|line| line.len() <= 30));
+        let unwrapped = ResponseFormatter::new().with_wrap_width(None).format_response(&sentence);
+        assert_eq!(unwrapped.lines().count(), 1);
+
+        let formatter = ResponseFormatter::new().with_indent(4).with_max_string(10);
+        let value = serde_json::json!({"a": {"b": "abcdefghijklmnop", "c": 1}});
+        assert_eq!(
+            formatter.format_json_value(&value, 0),
+            "a: b: \"abcdefghi…\" (+7 chars)\n        c: 1"
+        );
+
+        let mut config = Config::from_vars([]);
+        config.wrap_width = Some(0);
+        assert_eq!(ResponseFormatter::fitted(&config, 40).wrap_width, None);
+        config.wrap_width = None;
+        assert_eq!(ResponseFormatter::fitted(&config, 40).wrap_width, Some(40));
+    }
+
+    #[test]
+    fn test_spacing_leaves_urls_times_code_and_json_alone() {
+        let formatter = ResponseFormatter::new();
+        let format = |text: &str| formatter.format_response(text);
+        assert_eq!(format("Error:missing semicolon"), "Error: missing semicolon");
+        assert_eq!(format("Set count= 3 in config"), "Set count = 3 in config");
+        assert_eq!(
+            format("See https://example.com:8080/a?b=c:d."),
+            "See https://example.com:8080/a?b=c:d."
+        );
+        assert_eq!(
+            format("Ran at 14:03:27 on 2026-10-14T14:03:00Z"),
+            "Ran at 14:03:27 on 2026-10-14T14:03:00Z"
+        );
+        assert_eq!(
+            format("Use `let x= a:b;` and std::io::stdin"),
+            "Use let x= a:b; and std::io::stdin"
+        );
+        assert_eq!(
+            format(r#"The response was {"url":"http://x.io/a:b"} today"#),
+            r#"The response was url: "http://x.io/a:b" today"#
+        );
+        assert_eq!(format("**Fix** `a*b*c` now"), "Fix a*b*c now");
+    }
+
+    #[test]
+    fn test_failures_carry_a_remediation_hint() {
+        let formatter = ResponseFormatter::new();
+        let missing = anyhow::Error::new(CodepilotError::Config(
+            "OPENAI_API_KEY must be set".to_string(),
+        ));
+        let text = formatter.text(&formatter.render_failure(&missing));
+        assert!(text.starts_with("Error: OPENAI_API_KEY must be set"));
+        assert!(
+            text.ends_with(
+                "→ Run codepilot auth set to save a key, or add OPENAI_API_KEY to .env."
+            )
+        );
+
+        let parse_error = serde_json::from_str::<Value>("<html>").unwrap_err();
+        let schema = anyhow::Error::new(CodepilotError::Provider(OpenAIError::JSONDeserialize(
+            parse_error,
+            "<html>".to_string(),
+        )))
+        .context("the model call failed");
+        assert!(remediation(&schema).is_some_and(|hint| hint.contains("OpenAI-compatible")));
+        assert_eq!(remediation(&anyhow::anyhow!("the reply had no FILE: block")), None);
+    }
+
     #[test]
     fn test_markdown_cleaning() {
         let formatter = ResponseFormatter::new();
@@ -239,4 +1084,20 @@ mod tests {
         assert!(!cleaned.contains("**"));
         assert!(!cleaned.contains("`"));
     }
+
+    #[test]
+    fn widths_count_terminal_columns() {
+        assert_eq!(display_width("✅ done"), 7);
+        assert_eq!(display_width("\x1b[32mok\x1b[0m"), 2);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(split_to_width("日本語です", 4), ["日本", "語で", "す"]);
+        assert_eq!(truncate_to_width("日本語です", 5), "日本…");
+
+        let table = ResponseFormatter::new().text(
+            &ResponseFormatter::new()
+                .render("| name | ok |\n|---|---|\n| 日本 | ✅ |\n| ab | no |"),
+        );
+        let widths: Vec<usize> = table.lines().map(display_width).collect();
+        assert_eq!(widths[2], widths[3]);
+    }
 }
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, OnceLock};
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::error::CodepilotError;
+
+/// The English catalog, built in; every message is looked up here when the
+/// configured locale doesn't have it.
+static ENGLISH: LazyLock<HashMap<String, String>> =
+    LazyLock::new(|| parse(include_str!("../locales/en.ftl")));
+
+/// The `LOCALE` catalog, once `init` has loaded one.
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+static PLACEABLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\s*\$([A-Za-z][A-Za-z0-9_-]*)\s*\}").expect("valid regex"));
+
+/// Load `{LOCALE_DIR}/{LOCALE}.ftl` for `tr` to use. English needs no file.
+pub fn init(config: &Config) -> Result<()> {
+    if config.locale.is_empty() || config.locale == "en" {
+        return Ok(());
+    }
+    let path = Path::new(&config.locale_dir).join(format!("{}.ftl", config.locale));
+    let text = std::fs::read_to_string(&path).map_err(|e| {
+        CodepilotError::Config(format!(
+            "LOCALE={} needs a catalog at {}: {e}",
+            config.locale,
+            path.display()
+        ))
+    })?;
+    let _ = CATALOG.set(parse(&text));
+    Ok(())
+}
+
+/// The text for message `id`; the id itself if no catalog has it.
+pub fn tr(id: &str) -> String {
+    tr_args(id, &[])
+}
+
+/// The text for message `id`, with each `{ $name }` in it filled in from `args`.
+pub fn tr_args(id: &str, args: &[(&str, &str)]) -> String {
+    let Some(text) = CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(id))
+        .or_else(|| ENGLISH.get(id))
+    else {
+        return id.to_string();
+    };
+    PLACEABLE
+        .replace_all(text, |caps: &regex::Captures| {
+            args.iter()
+                .find(|(name, _)| *name == &caps[1])
+                .map_or_else(|| caps[0].to_string(), |(_, value)| value.to_string())
+        })
+        .into_owned()
+}
+
+/// The subset of Fluent the catalogs use: `id = text` messages, indented
+/// lines continuing the one above, `#` comments, and `{ $name }` placeables.
+fn parse(text: &str) -> HashMap<String, String> {
+    let mut messages = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in text.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with([' ', '\t'])
+            && let Some((_, value)) = &mut current
+        {
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(line.trim());
+            continue;
+        }
+        messages.extend(current.take());
+        if let Some((id, value)) = line.split_once('=') {
+            current = Some((id.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    messages.extend(current);
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::commands::COMMANDS;
+    use crate::cli::keymap::Action;
+
+    #[test]
+    fn catalogs_parse_and_english_covers_the_interface() {
+        let catalog = parse("# comment\nhello = Hallo, { $name }!\nlong =\n    zwei\n    Zeilen\n");
+        assert_eq!(catalog["hello"], "Hallo, { $name }!");
+        assert_eq!(catalog["long"], "zwei\nZeilen");
+
+        assert_eq!(
+            tr_args("help-normal-title", &[("keymap", "vim")]),
+            "Normal mode (vim keymap)"
+        );
+        assert_eq!(tr("no-such-message"), "no-such-message");
+        for action in Action::ALL {
+            assert!(
+                ENGLISH.contains_key(&format!("action-{}", action.name())),
+                "{action:?}"
+            );
+        }
+        for command in COMMANDS {
+            assert!(
+                ENGLISH.contains_key(&format!("command-{}", command.name)),
+                "{}",
+                command.name
+            );
+        }
+        let looked_up = Regex::new(r#"i18n::tr(?:_args)?\(\s*"([a-z0-9-]+)""#).unwrap();
+        for source in [
+            include_str!("cli/app.rs"),
+            include_str!("cli/ui.rs"),
+            include_str!("cli/stats.rs"),
+        ] {
+            for id in looked_up.captures_iter(source) {
+                assert!(ENGLISH.contains_key(&id[1]), "{}", &id[1]);
+            }
+        }
+    }
+}
@@ -1,11 +1,28 @@
 pub mod config;
 pub mod cli;
 pub mod orchestrator;
+pub mod error;
 pub mod formatter;
+pub mod i18n;
+pub mod redact;
+pub mod replay;
 pub mod runs;
+pub mod policy;
+pub mod events;
+pub mod session;
+pub mod client;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-pub use config::{Config, get_openai_api_key, get_openai_base_url};
+pub use error::CodepilotError;
+pub use config::{AgentMode, Config, get_openai_api_key, get_openai_base_url};
 pub use cli::{App, AppState};
-pub use orchestrator::{CodeTaskOrchestrator, FileEdit, TaskResult};
-pub use formatter::ResponseFormatter;
+pub use orchestrator::{
+    CodeTaskOrchestrator, ConfirmationRequest, ConfirmationResponse, FileEdit, TaskResult,
+};
+pub use formatter::{Block, DiffLine, RenderedResponse, ResponseFormatter, TableOptions};
 pub use runs::{RunKind, RunStatus};
+pub use policy::ActionClass;
+pub use events::OrchestratorEvent;
+pub use session::SessionContext;
+pub use client::{Approver, ClientBuilder, CodepilotClient};
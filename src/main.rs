@@ -1,14 +1,245 @@
+use std::io::IsTerminal;
+use std::process::ExitCode;
+
 use anyhow::Result;
-use codepilot::App;
+use codepilot::cli::persistence;
+use codepilot::cli::stats::Stats;
+use codepilot::cli::{
+    archive, args, completions, doctor, encryption, headless, logging, script, serve, stdio,
+};
+use codepilot::config::secrets;
+use codepilot::CodepilotError;
+use codepilot::{App, Config, i18n, redact};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            if let Some(hint) = codepilot::formatter::remediation(&e) {
+                eprintln!("→ {hint}");
+            }
+            ExitCode::from(headless::Failure::of(&e).exit_code())
+        }
+    }
+}
+
+async fn run() -> Result<ExitCode> {
     // Load environment variables
     dotenv::dotenv().ok();
 
+    let matches = args::command().get_matches();
+    match matches.subcommand() {
+        Some(("ask", ask)) => {
+            let config = args::apply_overrides(Config::from_env()?, ask)?;
+            i18n::init(&config)?;
+            redact::init(&config)?;
+            logging::init(&config, None)?;
+            let output = if ask.get_flag("json") {
+                headless::Output::Json
+            } else {
+                headless::Output::Text
+            };
+            return Ok(headless::ask(&config, &args::task(ask)?, output).await);
+        }
+        Some(("config", config)) => {
+            return match config.subcommand() {
+                Some(("init", init)) => {
+                    let path = std::path::Path::new(
+                        init.get_one::<String>("path").expect("has a default"),
+                    );
+                    doctor::init(path, init.get_flag("force"))?;
+                    println!(
+                        "Wrote {}; fill in OPENAI_API_KEY, then run `codepilot config doctor`",
+                        path.display()
+                    );
+                    Ok(ExitCode::SUCCESS)
+                }
+                Some(("doctor", _)) => {
+                    Ok(doctor::report(&doctor::doctor(&Config::from_env()?).await))
+                }
+                _ => Ok(doctor::report(&doctor::validate(&Config::from_env()?))),
+            };
+        }
+        Some(("auth", auth)) => {
+            let (action, auth) = auth.subcommand().expect("subcommand_required");
+            let service = auth.get_one::<String>("service").expect("has a default");
+            if action == "set" {
+                secrets::set(service)?;
+                let used = if service == "state" {
+                    "ENCRYPT_STATE"
+                } else {
+                    "OPENAI_API_KEY"
+                };
+                println!(
+                    "Saved the {service} secret to the keyring; \
+                     it's used for {used} when .env doesn't set it"
+                );
+            } else {
+                secrets::clear(service)?;
+                println!("Deleted the saved {service} secret");
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(("status", status)) => {
+            let config = Config::from_env()?;
+            config.validate()?;
+            let provider = doctor::ProviderStatus::check(&config).await;
+            if status.get_flag("json") {
+                println!("{}", serde_json::to_string(&provider)?);
+            } else {
+                println!("{}", provider.line());
+            }
+            return Ok(provider.failure.map_or(ExitCode::SUCCESS, |failure| {
+                ExitCode::from(failure.exit_code())
+            }));
+        }
+        Some(("history", history)) => {
+            let Some(("search", history)) = history.subcommand() else {
+                unreachable!("clap requires a history subcommand");
+            };
+            let config = Config::from_env()?;
+            let Some(save_state_dir) = config.save_state_dir.clone() else {
+                anyhow::bail!(CodepilotError::Config(
+                    "SAVE_STATE_DIR is not set, so no sessions are saved".to_string()
+                ));
+            };
+            encryption::setup(&config)?;
+            let query = history
+                .get_many::<String>("query")
+                .expect("required")
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let hits =
+                persistence::search(&persistence::load_all_sessions(&save_state_dir), &query);
+            if history.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&hits)?);
+            } else {
+                for hit in &hits {
+                    println!("{}", hit.line());
+                }
+            }
+            return Ok(if hits.is_empty() { ExitCode::FAILURE } else { ExitCode::SUCCESS });
+        }
+        Some(("sessions", sessions)) => {
+            let config = Config::from_env()?;
+            let Some(save_state_dir) = config.save_state_dir.as_deref() else {
+                anyhow::bail!(CodepilotError::Config(
+                    "SAVE_STATE_DIR is not set, so no sessions are saved".to_string()
+                ));
+            };
+            encryption::setup(&config)?;
+            redact::init(&config)?;
+            match sessions.subcommand().expect("subcommand_required") {
+                ("export", export) => {
+                    let id = export.get_one::<String>("id").expect("required");
+                    let out = export
+                        .get_one::<String>("out")
+                        .cloned()
+                        .unwrap_or_else(|| format!("{id}.json"));
+                    let secrets: Vec<&str> = config.openai_api_key.as_deref().into_iter().collect();
+                    std::fs::write(&out, archive::export(save_state_dir, id, &secrets)?)?;
+                    println!("Wrote {out}");
+                }
+                ("import", import) => {
+                    let file = import.get_one::<String>("file").expect("required");
+                    let path = archive::import(save_state_dir, &std::fs::read_to_string(file)?)?;
+                    println!(
+                        "Imported as {}; resume it from the startup picker",
+                        persistence::session_id(&path)
+                    );
+                }
+                _ => {
+                    for (path, session) in persistence::load_all_sessions(save_state_dir) {
+                        println!("{}", archive::list_line(&path, &session));
+                    }
+                }
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(("stats", stats)) => {
+            let config = Config::from_env()?;
+            let Some(save_state_dir) = config.save_state_dir.clone() else {
+                anyhow::bail!(CodepilotError::Config(
+                    "SAVE_STATE_DIR is not set, so no sessions are saved".to_string()
+                ));
+            };
+            encryption::setup(&config)?;
+            let sessions = persistence::load_all_sessions(&save_state_dir);
+            let usage = Stats::collect(sessions.iter().map(|(_, session)| session.tabs.as_slice()));
+            if stats.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&usage)?);
+            } else {
+                for line in usage.lines() {
+                    println!("{line}");
+                }
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(("completions", shell)) => {
+            let shell = shell.get_one::<String>("shell").expect("required");
+            print!(
+                "{}",
+                completions::generate(shell, args::command()).expect("clap checked the shell")
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(("serve", serve)) => {
+            let config = args::apply_overrides(Config::from_env()?, serve)?;
+            let addr = serve.get_one::<String>("addr").expect("has a default").parse()?;
+            redact::init(&config)?;
+            logging::init(&config, None)?;
+            serve::serve(config, addr).await?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(("stdio", stdio)) => {
+            let config = args::apply_overrides(Config::from_env()?, stdio)?;
+            redact::init(&config)?;
+            logging::init(&config, None)?;
+            stdio::stdio(&config).await?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(("run", run)) => {
+            let config = args::apply_overrides(Config::from_env()?, run)?;
+            i18n::init(&config)?;
+            redact::init(&config)?;
+            logging::init(&config, None)?;
+            let file = run.get_one::<String>("file").expect("required");
+            let steps = script::parse(&std::fs::read_to_string(file)?);
+            let save = run.get_one::<String>("save").map(std::path::Path::new);
+            return headless::run_script(&config, &steps, save, run.get_flag("keep-going")).await;
+        }
+        Some(("repl", repl)) => {
+            let config = args::apply_overrides(Config::from_env()?, repl)?;
+            i18n::init(&config)?;
+            redact::init(&config)?;
+            logging::init(&config, None)?;
+            headless::repl(&config).await?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        _ => {}
+    }
+
+    // The TUI needs a terminal on both ends; piped, behave like `ask -`.
+    if !std::io::stdin().is_terminal() {
+        let task = args::read_task(std::io::stdin())?;
+        let config = args::apply_overrides(Config::from_env()?, &matches)?;
+        i18n::init(&config)?;
+        redact::init(&config)?;
+        logging::init(&config, None)?;
+        return Ok(headless::ask(&config, &task, headless::Output::Text).await);
+    }
+    if !std::io::stdout().is_terminal() {
+        anyhow::bail!(
+            "stdout is not a terminal; use `codepilot ask <task>` to run without the TUI"
+        );
+    }
+
     // Create and run the CLI application
-    let mut app = App::new()?;
+    let mut app = App::new(matches.get_flag("read-only"))?;
     app.run().await?;
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(ExitCode::SUCCESS)
+}
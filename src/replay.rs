@@ -0,0 +1,214 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::error::CodepilotError;
+use crate::redact;
+
+/// One chat completion as it went: what was asked and what came back.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Recording {
+    /// SHA-256 of the model and the messages exactly as sent. Replay matches
+    /// on this alone, so the readable copy below can be redacted.
+    pub request: String,
+    pub model: String,
+    /// The messages, with secrets masked, for whoever reads the fixture.
+    pub messages: Value,
+    /// Masked too: a replayed edit never carries a key the model echoed.
+    pub reply: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+/// A fixture file of `Recording`s, one JSON object per line, either being
+/// written (`CODEPILOT_RECORD=1`) or answered from (`CODEPILOT_REPLAY=1`) in
+/// place of the LLM provider. In replay, a request that was recorded more
+/// than once gets its replies in the order they were recorded.
+#[derive(Debug)]
+pub enum Cassette {
+    Record(PathBuf),
+    Replay {
+        path: PathBuf,
+        /// Each recording, and whether it has been replayed yet.
+        recordings: Mutex<Vec<(Recording, bool)>>,
+    },
+}
+
+impl Cassette {
+    /// The cassette `config` asks for, if any. Replaying reads the whole
+    /// fixture up front, so a missing or damaged one fails here.
+    pub fn open(config: &Config) -> Result<Option<Self>> {
+        let path = PathBuf::from(&config.fixtures_path);
+        if config.record_llm {
+            return Ok(Some(Cassette::Record(path)));
+        }
+        if !config.replay_llm {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path).map_err(|e| {
+            CodepilotError::Config(format!(
+                "CODEPILOT_REPLAY is set but {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        let recordings = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(n, line)| {
+                serde_json::from_str(line)
+                    .map(|recording| (recording, false))
+                    .map_err(|e| {
+                        CodepilotError::Config(format!(
+                            "{} line {} is not a recording: {e}",
+                            path.display(),
+                            n + 1
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Cassette::Replay {
+            path,
+            recordings: Mutex::new(recordings),
+        }))
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Cassette::Replay { .. })
+    }
+
+    /// The recorded answer to `messages`. The first one not replayed yet wins;
+    /// once they all have been, the last is repeated.
+    pub fn replay(&self, model: &str, messages: &Value) -> Result<Recording> {
+        let Cassette::Replay { path, recordings } = self else {
+            anyhow::bail!("not replaying");
+        };
+        let request = fingerprint(model, messages);
+        let mut recordings = recordings
+            .lock()
+            .map_err(|_| anyhow::anyhow!("replay state poisoned"))?;
+        let mut matching = recordings
+            .iter_mut()
+            .filter(|(recording, _)| recording.request == request)
+            .peekable();
+        let Some(first) = matching.peek().map(|(recording, _)| recording.clone()) else {
+            return Err(CodepilotError::Config(format!(
+                "{} has no recording of this request; record it again with CODEPILOT_RECORD=1",
+                path.display()
+            ))
+            .into());
+        };
+        let mut last = first;
+        for (recording, replayed) in matching {
+            if !*replayed {
+                *replayed = true;
+                return Ok(recording.clone());
+            }
+            last = recording.clone();
+        }
+        Ok(last)
+    }
+
+    /// Append a completion to the fixture, when recording.
+    pub fn record(
+        &self,
+        model: &str,
+        messages: &Value,
+        reply: &str,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+    ) -> Result<()> {
+        let Cassette::Record(path) = self else {
+            return Ok(());
+        };
+        let mut readable = messages.clone();
+        redact::value(&mut readable, &[]);
+        let recording = Recording {
+            request: fingerprint(model, messages),
+            model: model.to_string(),
+            messages: readable,
+            reply: redact::text(reply),
+            prompt_tokens,
+            completion_tokens,
+        };
+        append(path, &serde_json::to_string(&recording)?)?;
+        Ok(())
+    }
+}
+
+fn fingerprint(model: &str, messages: &Value) -> String {
+    let request = serde_json::json!({ "model": model, "messages": messages }).to_string();
+    ring::digest::digest(&ring::digest::SHA256, request.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn append(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_replies_come_back_in_order_for_the_same_request() {
+        let path = std::env::temp_dir().join(format!(
+            "codepilot-test-replay-{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::remove_file(&path).ok();
+        let mut config = Config::from_vars([]);
+        config.fixtures_path = path.display().to_string();
+        config.record_llm = true;
+        let messages = serde_json::json!([{"role": "user", "content": "bump a, key sk-abcdefghijklmnopqrstuv"}]);
+
+        let cassette = Cassette::open(&config).unwrap().unwrap();
+        cassette
+            .record("gpt-4o", &messages, "READ: src/a.ts", 10, 4)
+            .unwrap();
+        cassette
+            .record("gpt-4o", &messages, "READ: src/b.ts", 10, 4)
+            .unwrap();
+        assert!(
+            !std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("sk-abcdefghijklmnopqrstuv")
+        );
+
+        config.record_llm = false;
+        config.replay_llm = true;
+        let cassette = Cassette::open(&config).unwrap().unwrap();
+        assert_eq!(
+            cassette.replay("gpt-4o", &messages).unwrap().reply,
+            "READ: src/a.ts"
+        );
+        assert_eq!(
+            cassette.replay("gpt-4o", &messages).unwrap().reply,
+            "READ: src/b.ts"
+        );
+        assert_eq!(
+            cassette.replay("gpt-4o", &messages).unwrap().reply,
+            "READ: src/b.ts"
+        );
+        let missing = cassette.replay("gpt-4o-mini", &messages).unwrap_err();
+        assert!(matches!(
+            CodepilotError::find(&missing),
+            Some(CodepilotError::Config(_))
+        ));
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -1,23 +1,78 @@
 use std::path::Path;
 use std::process::Stdio;
+use std::sync::LazyLock;
 use std::time::Duration;
 
 use anyhow::Result;
+use regex::Regex;
 use tokio::process::Command;
 use tokio::time::timeout;
 
 /// What a `Run` executes. Closed on purpose — see PLAN.md non-goals: this is not
 /// a generic task-runner, just the fixed set of JS/TS verification gates.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RunKind {
     TypeCheck,
 }
 
+impl RunKind {
+    /// Short name of the tool behind the gate, as shown to the user.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunKind::TypeCheck => "tsc",
+        }
+    }
+}
+
 /// Outcome of a single `Run`.
 #[derive(Debug, Clone)]
 pub enum RunStatus {
     Succeeded,
     Failed(String),
+    /// Not run because nothing was written (plan mode).
+    Skipped,
+}
+
+/// One error from `tsc`'s plain (non-`--pretty`) output, which is what it
+/// prints when stdout isn't a terminal: `src/a.ts(3,5): error TS2322: message`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TscDiagnostic {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub code: String,
+    pub message: String,
+}
+
+/// `src/a.ts(3,7): error TS2304: Cannot find name 'x'.`
+static DIAGNOSTIC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?)\((\d+),(\d+)\): error (TS\d+): (.*)$").expect("valid regex")
+});
+
+/// Pull the diagnostics out of captured `tsc` output. Indented continuation lines
+/// (elaborations like "Type 'string' is not assignable to ...") are joined onto
+/// the message they belong to; anything else unrecognised is skipped.
+pub fn parse_tsc_diagnostics(output: &str) -> Vec<TscDiagnostic> {
+    let mut diagnostics: Vec<TscDiagnostic> = Vec::new();
+    for line in output.lines() {
+        if let Some(caps) = DIAGNOSTIC.captures(line.trim_end()) {
+            diagnostics.push(TscDiagnostic {
+                file: caps[1].trim().to_string(),
+                line: caps[2].parse().unwrap_or(0),
+                column: caps[3].parse().unwrap_or(0),
+                code: caps[4].to_string(),
+                message: caps[5].to_string(),
+            });
+        } else if line.starts_with(' ')
+            && !line.trim().is_empty()
+            && let Some(last) = diagnostics.last_mut()
+        {
+            last.message.push(' ');
+            last.message.push_str(line.trim());
+        }
+    }
+    diagnostics
 }
 
 const RUN_TIMEOUT: Duration = Duration::from_secs(120);
@@ -31,9 +86,11 @@ pub async fn execute(kind: RunKind, repo_path: &Path) -> Result<RunStatus> {
 
 async fn run_tsc(repo_path: &Path) -> Result<RunStatus> {
     let mut cmd = tsc_command(repo_path);
+    // A cancelled task drops this future mid-wait; take tsc down with it.
     cmd.current_dir(repo_path)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
 
     let child = cmd.spawn()?;
     let output = match timeout(RUN_TIMEOUT, child.wait_with_output()).await {
@@ -87,3 +144,23 @@ fn tsc_command(repo_path: &Path) -> Command {
         cmd
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_tsc_errors_with_continuations() {
+        let output = "src/a.ts(3,5): error TS2322: Type 'string' is not assignable to type 'number'.\n\
+                      src/lib/b.ts(10,1): error TS2304: Cannot find name 'foo'.\n\
+                      \x20\x20Did you mean 'for'?\n\
+                      \n\
+                      Found 2 errors in 2 files.";
+        let diagnostics = parse_tsc_diagnostics(output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].file, "src/a.ts");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 5));
+        assert_eq!(diagnostics[1].code, "TS2304");
+        assert_eq!(diagnostics[1].message, "Cannot find name 'foo'. Did you mean 'for'?");
+    }
+}
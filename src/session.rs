@@ -0,0 +1,95 @@
+use crate::orchestrator::TaskResult;
+use crate::runs::RunStatus;
+
+/// How many earlier tasks are summarized into the next prompt.
+const MAX_TURNS_IN_SUMMARY: usize = 10;
+/// Task descriptions longer than this are cut short in the summary.
+const MAX_TASK_CHARS: usize = 120;
+
+/// What happened to one earlier task in the session.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SessionTurn {
+    pub task: String,
+    pub outcome: String,
+}
+
+/// Running context for a sequence of tasks, so a follow-up like "now add a test
+/// for the function you just wrote" has something to refer to. Threaded from the
+/// caller into `CodeTaskOrchestrator::run_task`; kept to one line per task so it
+/// stays cheap to send on every prompt.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct SessionContext {
+    pub turns: Vec<SessionTurn>,
+}
+
+impl SessionContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_result(&mut self, task: &str, result: &TaskResult) {
+        let verb = match (&result.verification, result.applied) {
+            (RunStatus::Skipped, _) => "planned an edit to",
+            (_, true) => "edited",
+            (_, false) => "was rejected editing",
+        };
+        self.turns.push(SessionTurn {
+            task: task.to_string(),
+            outcome: format!("{verb} {}", result.edit.path),
+        });
+    }
+
+    pub fn record_error(&mut self, task: &str, error: &str) {
+        let first_line = error.lines().next().unwrap_or("").trim();
+        self.turns.push(SessionTurn {
+            task: task.to_string(),
+            outcome: format!("failed: {first_line}"),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    /// Prompt section describing the most recent tasks, or `None` on a fresh session.
+    pub fn summary(&self) -> Option<String> {
+        if self.turns.is_empty() {
+            return None;
+        }
+
+        let start = self.turns.len().saturating_sub(MAX_TURNS_IN_SUMMARY);
+        let mut summary = String::from("Earlier tasks in this session (oldest first):");
+        for turn in &self.turns[start..] {
+            let mut task: String = turn.task.chars().take(MAX_TASK_CHARS).collect();
+            if task.len() < turn.task.len() {
+                task.push('…');
+            }
+            summary.push_str(&format!("\n- \"{task}\" - {}", turn.outcome));
+        }
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_session_has_no_summary() {
+        assert!(SessionContext::new().summary().is_none());
+    }
+
+    #[test]
+    fn summary_keeps_only_recent_turns() {
+        let mut session = SessionContext::new();
+        for i in 0..15 {
+            session.record_error(&format!("task {i}"), "LLM returned an empty response\nmore");
+        }
+
+        let summary = session.summary().unwrap();
+        assert!(!summary.contains("\"task 4\""));
+        assert!(summary.contains("\"task 5\" - failed: LLM returned an empty response"));
+        assert!(summary.contains("\"task 14\""));
+        assert!(!summary.contains("more"));
+    }
+}